@@ -0,0 +1,609 @@
+//! Manifesto do `encoder batch`.
+//!
+//! Formato simples (compatível com as listas antigas):
+//!
+//! ```toml
+//! videos = ["C:/videos/FEV_PROMO_17.mp4", "C:/videos/FEV_PROMO_18.mp4"]
+//! ```
+//!
+//! Formato completo: `[defaults]` vale pra todos os vídeos e cada `[[videos]]`
+//! sobrescreve o que precisar. Entradas simples (só o caminho) e tabelas
+//! podem ser misturadas.
+//!
+//! ```toml
+//! [defaults]
+//! client = "SIPOLATTI"
+//! formats = ["mxf", "mp4"]
+//! upload = true
+//! distribute = true
+//!
+//! [[videos]]
+//! path = "C:/videos/FEV_PROMO_17.mp4"
+//! titulo = "PROMO FEVEREIRO"
+//!
+//! [[videos]]
+//! path = "C:/videos/ESPECIAL.mp4"
+//! registro = "2024017422099-1"
+//! profile = "NATAL"            # config/SIPOLATTI/NATAL/
+//! destinos = ["BR1230"]
+//! ```
+//!
+//! Prioridade: entrada do vídeo > `[defaults]` do manifesto > flags da CLI.
+//!
+//! O andamento de cada vídeo fica em `<manifesto>.state.toml` (ver
+//! `BatchState`), o que permite retomar um batch interrompido com `--resume`.
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Formatos gerados pelo encode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Mxf,
+    Mp4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Formats {
+    pub mxf: bool,
+    pub mp4: bool,
+}
+
+impl Default for Formats {
+    fn default() -> Self {
+        Self { mxf: true, mp4: true }
+    }
+}
+
+impl Formats {
+    fn from_list(list: &[Format]) -> Self {
+        Self {
+            mxf: list.contains(&Format::Mxf),
+            mp4: list.contains(&Format::Mp4),
+        }
+    }
+}
+
+/// Campos que podem vir do `[defaults]` ou de cada vídeo. Tudo opcional:
+/// o que faltar cai no nível de cima.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ItemOptions {
+    /// Perfil de cliente (subpasta em config/).
+    pub client: Option<String>,
+    /// Sub-perfil do cliente (campanha): config/<client>/<profile>/.
+    pub profile: Option<String>,
+    /// Título da claquete (default: extraído do nome do arquivo).
+    pub titulo: Option<String>,
+    /// Registro ANCINE fixo (pula o codes.toml).
+    pub registro: Option<String>,
+    /// Código a procurar no codes.toml, no lugar do extraído do nome.
+    pub code: Option<u32>,
+    /// Diretório de saída.
+    pub output: Option<String>,
+    /// Nome dos arquivos gerados, sem extensão (default: título).
+    pub output_name: Option<String>,
+    pub formats: Option<Vec<Format>>,
+    /// Sobe o MXF pro Peach depois do encode.
+    pub upload: Option<bool>,
+    /// Distribui pras emissoras depois do upload.
+    pub distribute: Option<bool>,
+    /// IDs dos destinos (subconjunto do [peach.destinos] do cliente). Sem
+    /// isso, usa todos.
+    pub destinos: Option<Vec<String>>,
+}
+
+const OPTION_KEYS: &[&str] = &[
+    "client", "profile", "titulo", "registro", "code", "output", "output_name", "formats", "upload",
+    "distribute", "destinos",
+];
+
+impl ItemOptions {
+    /// Campo a campo: `self` se definido, senão `fallback`.
+    pub fn or(&self, fallback: &ItemOptions) -> ItemOptions {
+        ItemOptions {
+            client: self.client.clone().or_else(|| fallback.client.clone()),
+            profile: self.profile.clone().or_else(|| fallback.profile.clone()),
+            titulo: self.titulo.clone().or_else(|| fallback.titulo.clone()),
+            registro: self.registro.clone().or_else(|| fallback.registro.clone()),
+            code: self.code.or(fallback.code),
+            output: self.output.clone().or_else(|| fallback.output.clone()),
+            output_name: self.output_name.clone().or_else(|| fallback.output_name.clone()),
+            formats: self.formats.clone().or_else(|| fallback.formats.clone()),
+            upload: self.upload.or(fallback.upload),
+            distribute: self.distribute.or(fallback.distribute),
+            destinos: self.destinos.clone().or_else(|| fallback.destinos.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    pub options: ItemOptions,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    pub defaults: ItemOptions,
+    pub videos: Vec<ManifestEntry>,
+}
+
+/// Um vídeo do batch com tudo resolvido.
+#[derive(Debug, Clone)]
+pub struct BatchItem {
+    pub path: PathBuf,
+    /// `CLIENTE` ou `CLIENTE/PERFIL` (None = config raiz).
+    pub client: Option<String>,
+    pub titulo: Option<String>,
+    pub registro: Option<String>,
+    pub code: Option<u32>,
+    pub output: Option<PathBuf>,
+    pub output_name: Option<String>,
+    pub formats: Formats,
+    pub upload: bool,
+    pub distribute: bool,
+    pub destinos: Option<Vec<String>>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Não foi possível ler {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Erro ao parsear {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(content)?;
+        for key in table.keys() {
+            if key != "defaults" && key != "videos" {
+                bail!("chave desconhecida no manifesto: `{key}`");
+            }
+        }
+
+        let defaults = match table.remove("defaults") {
+            Some(v) => parse_options(v, "[defaults]")?,
+            None => ItemOptions::default(),
+        };
+
+        let Some(toml::Value::Array(list)) = table.remove("videos") else {
+            bail!("manifesto sem lista `videos`");
+        };
+        let mut videos = Vec::with_capacity(list.len());
+        for (i, value) in list.into_iter().enumerate() {
+            let entry = match value {
+                toml::Value::String(path) => ManifestEntry {
+                    path: PathBuf::from(path),
+                    options: ItemOptions::default(),
+                },
+                toml::Value::Table(mut t) => {
+                    let where_ = format!("videos[{}]", i + 1);
+                    let Some(toml::Value::String(path)) = t.remove("path") else {
+                        bail!("{where_}: falta `path`");
+                    };
+                    ManifestEntry {
+                        options: parse_options(toml::Value::Table(t), &where_)
+                            .with_context(|| format!("vídeo {path}"))?,
+                        path: PathBuf::from(path),
+                    }
+                }
+                other => bail!("videos[{}]: esperado caminho ou tabela, veio {other}", i + 1),
+            };
+            videos.push(entry);
+        }
+        Ok(Self { defaults, videos })
+    }
+
+    /// Resolve cada vídeo: entrada > `[defaults]` > `cli` (flags da linha de comando).
+    pub fn items(&self, cli: &ItemOptions) -> Result<Vec<BatchItem>> {
+        let base = self.defaults.or(cli);
+        self.videos
+            .iter()
+            .map(|e| {
+                resolve(e.path.clone(), e.options.or(&base))
+                    .with_context(|| format!("{}", e.path.display()))
+            })
+            .collect()
+    }
+}
+
+fn parse_options(value: toml::Value, where_: &str) -> Result<ItemOptions> {
+    if let Some(t) = value.as_table() {
+        for key in t.keys() {
+            if !OPTION_KEYS.contains(&key.as_str()) {
+                bail!("{where_}: chave desconhecida `{key}`");
+            }
+        }
+    }
+    value
+        .try_into()
+        .with_context(|| format!("{where_}: valor inválido"))
+}
+
+/// Valida e resolve as opções de um vídeo (também usado pelo `encoder watch`).
+pub fn resolve(path: PathBuf, o: ItemOptions) -> Result<BatchItem> {
+    let client = match (o.client, o.profile) {
+        (Some(c), Some(p)) => Some(format!("{c}/{p}")),
+        (Some(c), None) => Some(c),
+        (None, Some(p)) => bail!("profile '{p}' sem client"),
+        (None, None) => None,
+    };
+    let formats = o.formats.as_deref().map(Formats::from_list).unwrap_or_default();
+    if !formats.mxf && !formats.mp4 {
+        bail!("`formats` vazio: informe \"mxf\" e/ou \"mp4\"");
+    }
+    // Ter destinos já implica distribuir; distribuir implica subir.
+    let distribute = o.distribute.unwrap_or(o.destinos.is_some());
+    let upload = o.upload.unwrap_or(distribute);
+    if distribute && !upload {
+        bail!("distribute = true exige upload");
+    }
+    if upload && !formats.mxf {
+        bail!("upload pro Peach exige o formato mxf");
+    }
+    if upload && client.is_none() {
+        bail!("upload pro Peach exige `client` (bloco [peach] do cliente)");
+    }
+    if let Some(name) = &o.output_name {
+        if name.is_empty() || name.contains(['/', '\\']) {
+            bail!("output_name inválido: '{name}'");
+        }
+    }
+    Ok(BatchItem {
+        path,
+        client,
+        titulo: o.titulo,
+        registro: o.registro,
+        code: o.code,
+        output: o.output.map(PathBuf::from),
+        output_name: o.output_name,
+        formats,
+        upload,
+        distribute,
+        destinos: o.destinos,
+    })
+}
+
+// ----------------- Estado persistente -----------------
+
+/// Etapa de um vídeo do batch. `Failed` guarda o erro em `ItemState::error`;
+/// o que já tinha sido feito antes da falha continua registrado (saídas,
+/// spot_id) e é aproveitado no `--resume`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Pending,
+    Encoded,
+    Uploaded,
+    Distributed,
+    Failed,
+}
+
+impl Stage {
+    /// Se esta etapa já cobre `target` (distribuído implica enviado, que
+    /// implica encodado). `Failed` não cobre nada.
+    pub fn reached(self, target: Stage) -> bool {
+        let rank = |s: Stage| match s {
+            Stage::Pending | Stage::Failed => 0,
+            Stage::Encoded => 1,
+            Stage::Uploaded => 2,
+            Stage::Distributed => 3,
+        };
+        self != Stage::Failed && rank(self) >= rank(target)
+    }
+}
+
+/// Arquivo gerado, com tamanho e SHA-256 pra conferir no `--resume`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputFile {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Só calculado quando o estado vai pro disco; sem ele, o `verify`
+    /// confere apenas o tamanho.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sha256: String,
+}
+
+impl OutputFile {
+    /// Registra a saída; `hash` = ler o arquivo inteiro pro SHA-256.
+    pub fn record(path: &Path, hash: bool) -> Result<Self> {
+        let size = std::fs::metadata(path)
+            .with_context(|| format!("Saída não encontrada: {}", path.display()))?
+            .len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            size,
+            sha256: if hash { sha256_file(path)? } else { String::new() },
+        })
+    }
+
+    /// Confere se o arquivo ainda existe e é o mesmo (tamanho, depois hash).
+    pub fn verify(&self) -> std::result::Result<(), String> {
+        let size = match std::fs::metadata(&self.path) {
+            Ok(m) => m.len(),
+            Err(_) => return Err(format!("{} não existe mais", self.path.display())),
+        };
+        if size != self.size {
+            return Err(format!(
+                "{} mudou de tamanho ({} → {} bytes)",
+                self.path.display(),
+                self.size,
+                size
+            ));
+        }
+        if self.sha256.is_empty() {
+            return Ok(());
+        }
+        match sha256_file(&self.path) {
+            Ok(h) if h == self.sha256 => Ok(()),
+            Ok(_) => Err(format!("{} mudou (hash diferente)", self.path.display())),
+            Err(e) => Err(format!("{e:#}")),
+        }
+    }
+}
+
+/// SHA-256 (hex) do arquivo, lido em blocos.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Não foi possível abrir {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Andamento de um vídeo.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemState {
+    /// Vídeo de origem (chave junto com a posição no manifesto).
+    pub path: PathBuf,
+    pub stage: Stage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub titulo: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registro: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spot_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_envio: Option<String>,
+    /// Duração do vídeo de origem, em segundos.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+    /// IDs dos destinos pra onde foi distribuído.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub destinos: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<OutputFile>,
+    #[serde(default)]
+    pub updated: String,
+}
+
+impl ItemState {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            stage: Stage::Pending,
+            error: None,
+            titulo: None,
+            registro: None,
+            spot_id: None,
+            id_envio: None,
+            duration_secs: None,
+            destinos: Vec::new(),
+            outputs: Vec::new(),
+            updated: String::new(),
+        }
+    }
+
+    /// Confere se o encode registrado ainda vale: um arquivo por formato
+    /// pedido (`.mxf`/`.mp4`), todos com o mesmo tamanho e hash. Qualquer
+    /// divergência = re-encodar.
+    pub fn verified_outputs(&self, formats: Formats) -> std::result::Result<(), String> {
+        if self.titulo.is_none() || self.registro.is_none() {
+            return Err("encode não concluído".into());
+        }
+        for (wanted, ext) in [(formats.mxf, "mxf"), (formats.mp4, "mp4")] {
+            let found = self.outputs.iter().find(|o| {
+                o.path
+                    .extension()
+                    .map(|e| e.eq_ignore_ascii_case(ext))
+                    .unwrap_or(false)
+            });
+            match (wanted, found) {
+                (true, Some(out)) => out.verify()?,
+                (true, None) => return Err(format!("{} não foi gerado", ext.to_uppercase())),
+                (false, _) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Arquivo de estado do batch, gravado a cada mudança de etapa.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchState {
+    #[serde(default)]
+    pub items: Vec<ItemState>,
+}
+
+impl BatchState {
+    /// `lista.toml` → `lista.state.toml` (mesma pasta).
+    pub fn path_for(manifest: &Path) -> PathBuf {
+        let stem = manifest
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "batch".into());
+        manifest.with_file_name(format!("{stem}.state.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Não foi possível ler {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Erro ao parsear {}", path.display()))
+    }
+
+    /// Estado alinhado aos itens do manifesto: reaproveita a entrada da mesma
+    /// posição se for o mesmo vídeo; o resto começa como `pending`.
+    pub fn for_items(previous: Option<BatchState>, items: &[BatchItem]) -> Self {
+        let previous = previous.unwrap_or_default();
+        let items = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| match previous.items.get(i) {
+                Some(s) if s.path == item.path => s.clone(),
+                _ => ItemState::new(&item.path),
+            })
+            .collect();
+        Self { items }
+    }
+
+    /// Grava via arquivo temporário + rename, pra um crash no meio não
+    /// deixar o estado truncado.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = format!(
+            "# Estado do batch (gerado pelo encoder; usado por --resume)\n\n{}",
+            toml::to_string(self).context("falha ao serializar estado do batch")?
+        );
+        let tmp = path.with_extension("toml.tmp");
+        std::fs::write(&tmp, content)
+            .with_context(|| format!("Não foi possível gravar {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Não foi possível gravar {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_list() {
+        let m = Manifest::parse("videos = [\"a.mp4\", \"b.mp4\"]").unwrap();
+        let cli = ItemOptions {
+            client: Some("SIPOLATTI".into()),
+            ..Default::default()
+        };
+        let items = m.items(&cli).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].path, PathBuf::from("b.mp4"));
+        assert_eq!(items[0].client.as_deref(), Some("SIPOLATTI"));
+        assert_eq!(items[0].formats, Formats::default());
+        assert!(!items[0].upload && !items[0].distribute);
+    }
+
+    #[test]
+    fn test_defaults_and_overrides() {
+        let m = Manifest::parse(
+            r#"
+            [defaults]
+            client = "SIPOLATTI"
+            upload = true
+            formats = ["mxf"]
+
+            [[videos]]
+            path = "a.mp4"
+            titulo = "PROMO A"
+            profile = "NATAL"
+            destinos = ["BR1230"]
+
+            [[videos]]
+            path = "b.mp4"
+            client = "OUTRO"
+            upload = false
+            formats = ["mp4"]
+            output_name = "B_FINAL"
+            "#,
+        )
+        .unwrap();
+        let cli = ItemOptions {
+            client: Some("IGNORADO".into()),
+            output: Some("out".into()),
+            ..Default::default()
+        };
+        let items = m.items(&cli).unwrap();
+        assert_eq!(items[0].client.as_deref(), Some("SIPOLATTI/NATAL"));
+        assert_eq!(items[0].titulo.as_deref(), Some("PROMO A"));
+        assert!(items[0].upload && items[0].distribute);
+        assert_eq!(items[0].destinos.as_deref(), Some(&["BR1230".to_string()][..]));
+        assert_eq!(items[0].output, Some(PathBuf::from("out")));
+        assert_eq!(items[1].client.as_deref(), Some("OUTRO"));
+        assert!(!items[1].upload);
+        assert_eq!(items[1].formats, Formats { mxf: false, mp4: true });
+        assert_eq!(items[1].output_name.as_deref(), Some("B_FINAL"));
+    }
+
+    #[test]
+    fn test_invalid_manifests() {
+        let err = |s: &str| {
+            let e = Manifest::parse(s)
+                .and_then(|m| m.items(&ItemOptions::default()))
+                .unwrap_err();
+            format!("{e:#}")
+        };
+        assert!(err("[[videos]]\npath = \"a.mp4\"\ntitle = \"x\"").contains("`title`"));
+        assert!(err("lista = []").contains("`lista`"));
+        assert!(err("[[videos]]\npath = \"a.mp4\"\nformats = [\"mov\"]").contains("valor inválido"));
+        assert!(err("[[videos]]\npath = \"a.mp4\"\nclient = \"X\"\nupload = true\nformats = [\"mp4\"]")
+            .contains("exige o formato mxf"));
+        assert!(err("[[videos]]\npath = \"a.mp4\"\ndestinos = [\"BR1230\"]").contains("exige `client`"));
+    }
+
+    #[test]
+    fn test_state_roundtrip_and_verify() {
+        let dir = std::env::temp_dir().join(format!("encoder_test_batch_state_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("A.mxf");
+        std::fs::write(&out, b"conteudo").unwrap();
+
+        let manifest = dir.join("lista.toml");
+        let state_path = BatchState::path_for(&manifest);
+        assert_eq!(state_path, dir.join("lista.state.toml"));
+
+        let items = Manifest::parse("videos = [\"a.mp4\", \"b.mp4\"]")
+            .unwrap()
+            .items(&ItemOptions::default())
+            .unwrap();
+        let mut state = BatchState::for_items(None, &items);
+        state.items[0].stage = Stage::Encoded;
+        state.items[0].titulo = Some("A".into());
+        state.items[0].registro = Some("2024017422017-0".into());
+        state.items[0].outputs.push(OutputFile::record(&out, true).unwrap());
+        state.items[1].stage = Stage::Failed;
+        state.items[1].error = Some("FFmpeg falhou".into());
+        state.save(&state_path).unwrap();
+
+        // Manifesto mudou: b.mp4 saiu, c.mp4 entrou na posição 1
+        let items = Manifest::parse("videos = [\"a.mp4\", \"c.mp4\"]")
+            .unwrap()
+            .items(&ItemOptions::default())
+            .unwrap();
+        let state = BatchState::for_items(Some(BatchState::load(&state_path).unwrap()), &items);
+        assert_eq!(state.items[0].stage, Stage::Encoded);
+        assert_eq!(state.items[1].stage, Stage::Pending);
+        assert!(Stage::Distributed.reached(Stage::Uploaded));
+        assert!(!Stage::Failed.reached(Stage::Encoded));
+        let mxf_only = Formats { mxf: true, mp4: false };
+        assert!(state.items[0].verified_outputs(mxf_only).is_ok());
+        assert!(state.items[0].verified_outputs(Formats::default()).unwrap_err().contains("MP4"));
+
+        std::fs::write(&out, b"conteuda").unwrap();
+        assert!(state.items[0].verified_outputs(mxf_only).unwrap_err().contains("hash"));
+        std::fs::write(&out, b"x").unwrap();
+        assert!(state.items[0].verified_outputs(mxf_only).unwrap_err().contains("tamanho"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
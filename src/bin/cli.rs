@@ -1,11 +1,12 @@
 use anyhow::{bail, Context, Result};
 use chrono::Datelike;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::collections::HashMap;
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
 use std::path::{Path, PathBuf};
 
-use encoder_lib::{config, encoder, metadata, peach, slate};
+use encoder_lib::error::{self, EncoderError};
+use encoder_lib::{batch, check, config, encoder, import, json, log, metadata, onboarding, peach, slate, watch};
 
 #[derive(Parser)]
 #[command(name = "encoder", about = "Automação de claquete + encoding MXF XDCAM HD422")]
@@ -31,13 +32,140 @@ struct Cli {
     /// Verificar se FFmpeg/FFprobe estão no PATH
     #[arg(long)]
     check: bool,
+
+    /// Número de registro ANCINE do vídeo (pula a busca pelo código no nome do arquivo)
+    #[arg(long)]
+    registro: Option<String>,
+
+    /// Nunca perguntar nada no terminal (tarefas agendadas, menu de contexto).
+    /// Sem --on-missing-code, vídeo sem registro falha (ou é pulado no batch).
+    #[arg(long, global = true)]
+    non_interactive: bool,
+
+    /// O que fazer quando o código do arquivo não está no codes.toml
+    #[arg(long, global = true, value_enum)]
+    on_missing_code: Option<OnMissingCode>,
+
+    /// Override de configuração (ex: --set produto="PROMO" --set peach.campana=NATAL).
+    /// Aplicado por cima de raiz → cliente → campanha.
+    #[arg(long = "set", global = true, value_name = "CHAVE=VALOR")]
+    set: Vec<String>,
+
+    /// Só mostrar o que seria feito (config, registro, saídas, comandos
+    /// FFmpeg e campos do Peach), sem encodar, logar nem chamar o portal.
+    /// Vale pra vídeo único, batch e peach upload/send.
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Mostrar também as mensagens de depuração (comandos FFmpeg etc.) no console
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Resultado em JSON no stdout (uma linha por resultado) e log no stderr.
+    /// Vale pra vídeo único, batch, watch, probe, slate e os comandos peach.
+    #[arg(long, global = true)]
+    json: bool,
+}
+
+impl Cli {
+    /// Nome do comando no campo `command` do JSON (`None` = sem suporte a --json).
+    fn json_command(&self) -> Option<&'static str> {
+        match &self.command {
+            None => Some("video"),
+            Some(Commands::Batch { .. }) => Some("batch"),
+            Some(Commands::Watch { .. }) => Some("watch"),
+            Some(Commands::Probe { .. }) => Some("probe"),
+            Some(Commands::Slate { .. }) => Some("slate"),
+            Some(Commands::Peach { action }) => Some(match action {
+                PeachAction::Login { .. } => "peach.login",
+                PeachAction::Logout { .. } => "peach.logout",
+                PeachAction::Upload { .. } => "peach.upload",
+                PeachAction::Send { .. } => "peach.send",
+                PeachAction::Lookup { .. } => "peach.lookup",
+                PeachAction::Destinos { .. } => "peach.destinos",
+                PeachAction::Status { .. } => "peach.status",
+                PeachAction::List { .. } => "peach.list",
+            }),
+            Some(_) => None,
+        }
+    }
+}
+
+/// Comportamento quando o registro não é encontrado pelo código do arquivo.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnMissingCode {
+    /// Pula o vídeo (no batch, entra no resumo)
+    Skip,
+    /// Aborta com erro
+    Fail,
+    /// Pergunta no terminal
+    Prompt,
+}
+
+/// Como resolver o registro de cada vídeo.
+struct RegistroOptions {
+    /// Registro fixo (--registro); ignora o nome do arquivo.
+    registro: Option<String>,
+    on_missing: OnMissingCode,
+}
+
+impl RegistroOptions {
+    /// `batch` muda o default não interativo de `fail` para `skip`, pra um
+    /// arquivo sem registro não derrubar a lista inteira.
+    fn from_cli(registro: Option<String>, non_interactive: bool, on_missing: Option<OnMissingCode>, batch: bool) -> Result<Self> {
+        let interactive = !non_interactive && io::stdin().is_terminal();
+        let on_missing = match on_missing {
+            Some(OnMissingCode::Prompt) if non_interactive => {
+                bail!("--on-missing-code=prompt não combina com --non-interactive")
+            }
+            Some(m) => m,
+            None if interactive => OnMissingCode::Prompt,
+            None if batch => OnMissingCode::Skip,
+            None => OnMissingCode::Fail,
+        };
+        Ok(Self { registro, on_missing })
+    }
+}
+
+/// Ajustes por vídeo vindos do manifesto do batch. `Default` = vídeo único.
+#[derive(Default)]
+struct VideoJob {
+    titulo: Option<String>,
+    registro: Option<String>,
+    code: Option<u32>,
+    output_name: Option<String>,
+    formats: batch::Formats,
+    /// Roda o encode MXF e o MP4 agência em paralelo.
+    concurrent_formats: bool,
+    /// Só resolve e mostra o plano (`--dry-run`).
+    dry_run: bool,
+}
+
+/// Arquivos gerados por um vídeo.
+struct EncodedVideo {
+    titulo: String,
+    registro: String,
+    mxf: Option<PathBuf>,
+    mp4: Option<PathBuf>,
+    /// Duração do vídeo de origem, em segundos.
+    duration_secs: u64,
+}
+
+/// Resultado de um vídeo processado.
+enum VideoOutcome {
+    Encoded(EncodedVideo),
+    /// `--dry-run`: o que seria gerado
+    Planned(EncodedVideo),
+    /// Pulado (sem registro), com o motivo
+    Skipped(String),
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Processar múltiplos vídeos a partir de um arquivo de lista
     Batch {
-        /// Caminho do arquivo de lista (.toml)
+        /// Manifesto (.toml): lista simples `videos = [...]` ou `[defaults]` + `[[videos]]`
+        /// com overrides por vídeo (título, registro, formatos, upload, destinos...)
         lista: PathBuf,
 
         /// Diretório de saída
@@ -51,12 +179,194 @@ enum Commands {
         /// Perfil de cliente (subpasta em config/)
         #[arg(short = 'C', long)]
         client: Option<String>,
+
+        /// Quantos vídeos processar ao mesmo tempo
+        #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        jobs: u32,
+
+        /// Máximo de FFmpeg decodificando na GPU (NVDEC) ao mesmo tempo, somando todos os jobs (0 = sem limite)
+        #[arg(long, default_value_t = 2)]
+        gpu_sessions: usize,
+
+        /// Encodar o MXF e o MP4 agência de cada vídeo em paralelo
+        #[arg(long)]
+        concurrent_formats: bool,
+
+        /// Retomar de onde parou (usa `<lista>.state.toml`), pulando etapas já
+        /// concluídas cujas saídas ainda conferem
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Mostrar os metadados do vídeo e o código/registro que o nome do arquivo resolve
+    Probe {
+        /// Caminho do vídeo
+        video: PathBuf,
+
+        /// Perfil de cliente (subpasta em config/)
+        #[arg(short = 'C', long)]
+        client: Option<String>,
+
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Gerar só a claquete (PNG) de um vídeo, sem encodar
+    Slate {
+        /// Caminho do vídeo
+        video: PathBuf,
+
+        /// Arquivo PNG de saída
+        #[arg(short, long, default_value = "slate.png")]
+        output: PathBuf,
+
+        /// Perfil de cliente (subpasta em config/)
+        #[arg(short = 'C', long)]
+        client: Option<String>,
+
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Título da claquete (default: extraído do nome do arquivo)
+        #[arg(long)]
+        titulo: Option<String>,
+
+        /// Número de registro ANCINE (pula a busca pelo código no nome do arquivo)
+        #[arg(long)]
+        registro: Option<String>,
+    },
+    /// Monitorar uma pasta: processa cada vídeo novo assim que a cópia termina
+    /// e move o original pra done/ ou failed/
+    Watch {
+        /// Pasta monitorada
+        dir: PathBuf,
+
+        /// Perfil de cliente (subpasta em config/)
+        #[arg(short = 'C', long)]
+        client: Option<String>,
+
+        /// Diretório de saída
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+
+        /// Subir o MXF pro Peach depois do encode
+        #[arg(long)]
+        upload: bool,
+
+        /// Distribuir pras emissoras depois do upload (implica --upload)
+        #[arg(long)]
+        distribute: bool,
+
+        /// Lista CSV de IDs de destinos (implica --distribute). Se omitido, usa todos do [peach.destinos].
+        #[arg(long, value_delimiter = ',')]
+        destinos: Option<Vec<String>>,
+
+        /// Segundos que o arquivo precisa ficar com o mesmo tamanho antes de ser processado
+        #[arg(long, default_value_t = 10)]
+        settle: u64,
+
+        /// Intervalo entre varreduras da pasta, em segundos
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..=3600))]
+        interval: u64,
+
+        /// Processar o que estiver na pasta e sair, em vez de ficar monitorando
+        #[arg(long)]
+        once: bool,
     },
     /// Comandos de integração com a plataforma Peach
     Peach {
         #[command(subcommand)]
         action: PeachAction,
     },
+    /// Gerenciar a tabela de códigos (codes.toml) de um cliente
+    Codes {
+        #[command(subcommand)]
+        action: CodesAction,
+    },
+    /// Inspecionar a configuração
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Cadastrar clientes
+    Client {
+        #[command(subcommand)]
+        action: ClientAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ClientAction {
+    /// Criar config/<NOME>/ perguntando claquete, IDs do Peach, destinos e pasta de saída
+    New {
+        /// Nome do cliente (vira o nome da pasta em config/)
+        name: String,
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Planilha (CSV/XLSX) pra gerar o codes.toml. Sem isso, pergunta (vazio = codes.toml vazio).
+        #[arg(long)]
+        codes: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Mostrar a configuração efetiva (raiz → cliente → campanha → --set) e a origem de cada campo
+    Show {
+        /// Perfil de cliente (subpasta em config/). Aceita CLIENTE/CAMPANHA.
+        #[arg(short = 'C', long)]
+        client: Option<String>,
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Validar a configuração de todos os clientes (ou de um só) antes de rodar jobs
+    Check {
+        /// Perfil de cliente (subpasta em config/). Sem isso, verifica todos.
+        #[arg(short = 'C', long)]
+        client: Option<String>,
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CodesAction {
+    /// Importar códigos de uma planilha (CSV ou XLSX) para o codes.toml do cliente
+    Import {
+        /// Caminho da planilha (.csv ou .xlsx)
+        file: PathBuf,
+        /// Perfil de cliente (subpasta em config/)
+        #[arg(short = 'C', long)]
+        client: String,
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Aba do XLSX (default: primeira)
+        #[arg(long)]
+        sheet: Option<String>,
+        /// Nome da coluna de código (default: "Código", "Cod", "Code"...)
+        #[arg(long)]
+        col_code: Option<String>,
+        /// Nome da coluna de registro (default: "Registro", "CRT"...)
+        #[arg(long)]
+        col_registro: Option<String>,
+        /// Nome da coluna de título (opcional)
+        #[arg(long)]
+        col_titulo: Option<String>,
+        /// Nome da coluna de duração (opcional)
+        #[arg(long)]
+        col_duracao: Option<String>,
+        /// Só mostra o diff, sem gravar o codes.toml
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -66,6 +376,11 @@ enum PeachAction {
         #[arg(short, long)]
         config: Option<PathBuf>,
     },
+    /// Apagar a sessão salva da conta (o próximo comando faz login de novo)
+    Logout {
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
     /// Enviar um VT já encodado (MXF) para o Peach
     Upload {
         /// Caminho do arquivo MXF
@@ -94,37 +409,291 @@ enum PeachAction {
         /// Lista CSV de IDs de destinos pra usar (override). Ex: "BR_GLOBO_112,BR1230". Se omitido, usa todos do [peach.destinos].
         #[arg(long, value_delimiter = ',')]
         destinos: Option<Vec<String>>,
+        /// MXF que subiu como esse spot, pro SHA-256 no envios_log.csv
+        #[arg(long)]
+        mxf: Option<PathBuf>,
+    },
+    /// Buscar IDs (e CNPJs) de anunciante, marca, produto ou agência
+    Lookup {
+        /// O que buscar
+        #[arg(value_enum)]
+        kind: LookupKind,
+        /// Parte do nome (vazio = tudo que o Peach devolver na primeira página)
+        #[arg(default_value = "")]
+        term: String,
+        /// ID do anunciante (marca/produto). Default: avisador_id do cliente
+        #[arg(long)]
+        avisador: Option<String>,
+        /// ID da marca (produto). Default: id_marca do cliente
+        #[arg(long)]
+        marca: Option<u32>,
+        /// Perfil de cliente (subpasta em config/)
+        #[arg(short = 'C', long)]
+        client: Option<String>,
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Grava o resultado no [peach] do defaults.toml do cliente (exige -C; com --dry-run só mostra)
+        #[arg(long, requires = "client")]
+        write: bool,
+        /// ID a gravar quando a busca traz mais de um resultado
+        #[arg(long, requires = "write")]
+        pick: Option<String>,
+    },
+    /// [experimental] Listar as emissoras de destino (e as listas salvas) da conta
+    Destinos {
+        /// Filtro por cidade, rede ou nome (parte do texto, sem acento)
+        #[arg(default_value = "")]
+        filter: String,
+        /// Mostrar também as listas de destinos salvas (id_listas)
+        #[arg(long)]
+        listas: bool,
+        /// Perfil de cliente (subpasta em config/)
+        #[arg(short = 'C', long)]
+        client: Option<String>,
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Acrescenta emissoras ao hd do [peach.destinos] do cliente. Ex: "BR_GLOBO_112,BR1230"
+        #[arg(long, value_delimiter = ',', requires = "client")]
+        hd: Vec<String>,
+        /// Acrescenta emissoras ao sd do [peach.destinos] do cliente
+        #[arg(long, value_delimiter = ',', requires = "client")]
+        sd: Vec<String>,
+        /// Acrescenta listas salvas ao id_listas do cliente
+        #[arg(long = "lista", value_delimiter = ',', requires = "client")]
+        add_listas: Vec<u32>,
+    },
+    /// Mostrar o QC dos spots e se já podem ser distribuídos
+    Status {
+        /// spot_id, pieza ou código ANCINE (com ou sem traço)
+        #[arg(required = true)]
+        spots: Vec<peach::SpotKey>,
+        /// Perfil de cliente: restringe a busca ao anunciante dele
+        #[arg(short = 'C', long)]
+        client: Option<String>,
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Consultar de novo até nenhum spot estar aguardando o QC
+        #[arg(long)]
+        watch: bool,
+        /// Intervalo entre consultas do --watch, em segundos
+        #[arg(long, default_value_t = 15, value_parser = clap::value_parser!(u64).range(1..=3600))]
+        interval: u64,
+        /// Máximo de consultas do --watch antes de desistir (padrão: 1 h a cada 15 s)
+        #[arg(long, default_value_t = 240, value_parser = clap::value_parser!(u32).range(1..))]
+        max_rounds: u32,
+        /// Páginas da listagem do Peach percorridas em cada busca
+        #[arg(long, default_value_t = peach::status::DEFAULT_MAX_PAGES)]
+        pages: u32,
+    },
+    /// Listar os spots recentes do cliente no Peach
+    List {
+        /// Perfil de cliente (subpasta em config/) — filtra pelo anunciante e marca do [peach]
+        #[arg(short = 'C', long)]
+        client: String,
+        /// Diretório de configuração
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+        /// Spots enviados a partir dessa data (AAAA-MM-DD). Default: últimos 7 dias
+        #[arg(long)]
+        since: Option<chrono::NaiveDate>,
+        /// Busca livre do portal (parte da pieza, código...)
+        #[arg(long, default_value = "")]
+        texto: String,
+        /// Consultar de novo até nenhum spot estar aguardando o QC
+        #[arg(long)]
+        watch: bool,
+        /// Intervalo entre consultas do --watch, em segundos
+        #[arg(long, default_value_t = 15, value_parser = clap::value_parser!(u64).range(1..=3600))]
+        interval: u64,
+        /// Máximo de consultas do --watch antes de desistir (padrão: 1 h a cada 15 s)
+        #[arg(long, default_value_t = 240, value_parser = clap::value_parser!(u32).range(1..))]
+        max_rounds: u32,
+        /// Páginas da listagem do Peach percorridas
+        #[arg(long, default_value_t = peach::status::DEFAULT_MAX_PAGES)]
+        pages: u32,
     },
 }
 
+/// Tipo de busca do `peach lookup`.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum LookupKind {
+    Anunciante,
+    Marca,
+    Producto,
+    Agencia,
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if let Err(e) = log::init_app_log(&log::app_log_dir()) {
+        eprintln!("Aviso: {e:#}");
+    }
+    if cli.verbose {
+        log::set_console_level(log::Level::Debug);
+    }
     if cli.check {
-        return check_dependencies();
+        if let Err(e) = check_dependencies() {
+            std::process::exit(error::exit_code(&e));
+        }
+        return Ok(());
+    }
+    if !cli.json {
+        if let Err(e) = run(cli) {
+            eprintln!("Error: {e:?}");
+            std::process::exit(error::exit_code(&e));
+        }
+        return Ok(());
     }
 
+    // Com --json, qualquer erro que chegue aqui também sai como JSON.
+    let Some(command) = cli.json_command() else {
+        bail!("--json vale para vídeo único, batch, watch, probe, slate e peach");
+    };
+    log::set_console_stderr(true);
+    if let Err(e) = run(cli) {
+        json::print_error(command, &e);
+        std::process::exit(error::exit_code(&e));
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let json = cli.json;
+
+    let overrides = cli
+        .set
+        .iter()
+        .map(|s| config::parse_override(s))
+        .collect::<Result<Vec<_>>>()?;
+
     match cli.command {
         Some(Commands::Batch {
             lista,
             output,
             config,
             client,
+            jobs,
+            gpu_sessions,
+            concurrent_formats,
+            resume,
+        }) => {
+            let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+            if cli.registro.is_some() {
+                bail!("--registro vale para um vídeo só; no batch use `registro` no manifesto");
+            }
+            let opts = RegistroOptions::from_cli(None, cli.non_interactive, cli.on_missing_code, true)?;
+            let cli_defaults = batch::ItemOptions {
+                client,
+                output: output.map(|o| o.display().to_string()),
+                ..Default::default()
+            };
+            encoder::set_gpu_sessions(gpu_sessions);
+            let run = BatchRun {
+                jobs: jobs as usize,
+                concurrent_formats,
+                resume,
+            };
+            if cli.dry_run {
+                return plan_batch(&lista, &config_dir, &overrides, &cli_defaults, &opts, json);
+            }
+            run_batch(&lista, &config_dir, &overrides, &cli_defaults, &opts, &run, json)
+        }
+        Some(Commands::Watch {
+            dir,
+            client,
+            output,
+            config,
+            upload,
+            distribute,
+            destinos,
+            settle,
+            interval,
+            once,
+        }) => {
+            let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+            if cli.registro.is_some() {
+                bail!("--registro vale para um vídeo só; no watch o registro vem do codes.toml");
+            }
+            if cli.dry_run {
+                bail!("--dry-run não vale para watch; use `batch --dry-run` ou o vídeo único");
+            }
+            let opts = RegistroOptions::from_cli(None, true, cli.on_missing_code, true)?;
+            let options = batch::ItemOptions {
+                client,
+                output: output.map(|o| o.display().to_string()),
+                upload: upload.then_some(true),
+                distribute: distribute.then_some(true),
+                destinos,
+                ..Default::default()
+            };
+            let settings = WatchSettings {
+                settle: std::time::Duration::from_secs(settle),
+                interval: std::time::Duration::from_secs(interval),
+                once,
+            };
+            run_watch(&dir, &config_dir, &overrides, &options, &opts, &settings, json)
+        }
+        Some(Commands::Probe { video, client, config }) => {
+            let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+            probe_video(&video, &config_dir, &overrides, client.as_deref(), json)
+        }
+        Some(Commands::Slate {
+            video,
+            output,
+            client,
+            config,
+            titulo,
+            registro,
         }) => {
             let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
-            let client_ref = client.as_deref();
-            let output_dir = resolve_output_dir(output, &config_dir, None, client_ref);
-            run_batch(&lista, &config_dir, &output_dir, client_ref)
+            let opts = RegistroOptions::from_cli(registro.or(cli.registro), cli.non_interactive, cli.on_missing_code, false)?;
+            let job = VideoJob {
+                titulo,
+                ..Default::default()
+            };
+            slate_only(&video, &output, &config_dir, &overrides, client.as_deref(), &opts, &job, json)
         }
-        Some(Commands::Peach { action }) => run_peach(action),
+        Some(Commands::Peach { action }) => run_peach(action, &overrides, cli.dry_run, json),
+        Some(Commands::Codes { action }) => run_codes(action),
+        Some(Commands::Config { action }) => run_config(action, &overrides),
+        Some(Commands::Client { action }) => run_client(action),
         None => {
             let video = cli.video.context(
                 "Informe o caminho do vídeo. Uso: encoder <video.mp4> [--output <dir>]",
             )?;
             let config_dir = cli.config.unwrap_or_else(|| PathBuf::from("config"));
             let client_ref = cli.client.as_deref();
-            let output_dir = resolve_output_dir(cli.output, &config_dir, Some(&video), client_ref);
-            process_video(&video, &config_dir, &output_dir, client_ref)
+            let output_dir = resolve_output_dir(cli.output, &config_dir, &overrides, Some(&video), client_ref);
+            let opts = RegistroOptions::from_cli(cli.registro, cli.non_interactive, cli.on_missing_code, false)?;
+            let job = VideoJob {
+                dry_run: cli.dry_run,
+                ..Default::default()
+            };
+            let started = std::time::Instant::now();
+            let _job = log::begin_job(log::job_id_for(&file_stem(&video)));
+            let result = process_video(&video, &config_dir, &overrides, &output_dir, client_ref, &opts, &job);
+            if json {
+                let report = video_report(&video, &result, started.elapsed());
+                json::print("video", matches!(report.status, "encoded" | "planned"), &report);
+                if let Err(e) = &result {
+                    std::process::exit(error::exit_code(e));
+                }
+                return Ok(());
+            }
+            match result {
+                Ok(VideoOutcome::Skipped(reason)) => println!("Vídeo pulado: {reason}"),
+                Ok(_) => {}
+                // registra dentro do job, pra cair no log ao lado das saídas
+                Err(e) => {
+                    log::error(format!("{e:#}"));
+                    std::process::exit(error::exit_code(&e));
+                }
+            }
+            Ok(())
         }
     }
 }
@@ -137,28 +706,99 @@ fn check_dependencies() -> Result<()> {
         }
         Err(e) => {
             eprintln!("ERRO: {e}");
-            bail!("Dependências não satisfeitas");
+            Err(e.context("Dependências não satisfeitas"))
         }
     }
 }
 
-fn process_video(video_path: &Path, config_dir: &Path, output_dir: &Path, client: Option<&str>) -> Result<()> {
-    // 1. Verificar FFmpeg
-    metadata::check_ffmpeg().context("FFmpeg/FFprobe não encontrado no PATH")?;
+/// Resultado de um vídeo único no formato do `--json`.
+fn video_report(video: &Path, result: &Result<VideoOutcome>, elapsed: std::time::Duration) -> json::VideoReport {
+    let mut report = json::VideoReport {
+        video: video.to_path_buf(),
+        elapsed_secs: elapsed.as_secs_f64(),
+        ..Default::default()
+    };
+    match result {
+        Ok(VideoOutcome::Encoded(e) | VideoOutcome::Planned(e)) => {
+            report.status = match result {
+                Ok(VideoOutcome::Planned(_)) => "planned",
+                _ => "encoded",
+            };
+            report.titulo = Some(e.titulo.clone());
+            report.registro = Some(e.registro.clone());
+            report.duration_secs = Some(e.duration_secs);
+            report.mxf = e.mxf.clone();
+            report.mp4 = e.mp4.clone();
+        }
+        Ok(VideoOutcome::Skipped(reason)) => {
+            report.status = "skipped";
+            report.reason = Some(reason.clone());
+        }
+        Err(e) => {
+            report.status = "failed";
+            report.error = Some(json::ErrorInfo::from_error(e));
+        }
+    }
+    report
+}
+
+/// Destino do log legível no console: stdout, ou stderr com `--json`.
+fn console() -> Box<dyn Write> {
+    if log::console_stderr() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+/// Vídeo com configuração, metadados e código/título resolvidos: tudo que
+/// vem antes do encode. Base do encode, do `probe` e do `slate`.
+struct PreparedVideo {
+    defaults: config::Defaults,
+    codes: HashMap<u32, String>,
+    meta: metadata::VideoMetadata,
+    filename: String,
+    info: config::FilenameInfo,
+    code: Option<u32>,
+    titulo: String,
+}
+
+impl PreparedVideo {
+    /// Registro pela tabela de códigos (com aliases/offset), sem perguntar nada.
+    fn lookup_registro(&self) -> Option<String> {
+        self.code
+            .and_then(|c| self.defaults.extract.lookup_registro(c, &self.codes))
+    }
+
+    /// Por que o vídeo ficou sem registro.
+    fn missing_reason(&self) -> String {
+        match self.code {
+            Some(c) => format!("código {c} sem registro no codes.toml"),
+            None => "sem código no nome do arquivo".to_string(),
+        }
+    }
+}
 
-    // 2. Verificar que o vídeo existe
+fn prepare_video(
+    video_path: &Path,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    client: Option<&str>,
+    job: &VideoJob,
+) -> Result<PreparedVideo> {
+    // Verificar que o vídeo existe
     if !video_path.exists() {
         bail!("Arquivo de vídeo não encontrado: {}", video_path.display());
     }
 
-    // 3. Carregar configurações
-    let defaults = config::load_defaults_for(config_dir, client)?;
+    // Carregar configurações
+    let defaults = config::load_defaults_for(config_dir, client, overrides)?;
     let codes = config::load_codes_for(config_dir, client)?;
 
-    // 4. Ler metadados do vídeo
-    println!("Lendo metadados de {}...", video_path.display());
+    // Ler metadados do vídeo
+    log::emit(format!("Lendo metadados de {}...", video_path.display()));
     let meta = metadata::probe(video_path)?;
-    println!(
+    log::emit(format!(
         "  Resolução: {}x{} | FPS: {}/{} | Duração: {}s | Áudio: {}",
         meta.width,
         meta.height,
@@ -170,86 +810,345 @@ fn process_video(video_path: &Path, config_dir: &Path, output_dir: &Path, client
         } else {
             "sem áudio".to_string()
         }
-    );
+    ));
 
-    // 5. Extrair código do nome do arquivo
+    // Extrair código e título do nome do arquivo
     let filename = video_path
         .file_name()
         .and_then(|n| n.to_str())
-        .context("Nome de arquivo inválido")?;
-
-    let registro = resolve_registro(filename, &codes)?;
-    println!("  Registro: {registro}");
+        .context("Nome de arquivo inválido")?
+        .to_string();
+    let info = defaults.extract.extract(&filename);
+    let code = job.code.or(info.code);
+    let titulo = job
+        .titulo
+        .clone()
+        .or_else(|| info.titulo.clone())
+        .unwrap_or_else(|| {
+            Path::new(&filename)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or(&filename)
+                .to_string()
+        });
+
+    Ok(PreparedVideo {
+        defaults,
+        codes,
+        meta,
+        filename,
+        info,
+        code,
+        titulo,
+    })
+}
 
-    // 6. Gerar claquete
-    let exe_dir = std::env::current_exe()
-        .ok()
-        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-        .unwrap_or_else(|| PathBuf::from("."));
+/// `encoder probe`: metadados + código/título/registro, sem encodar nada.
+fn probe_video(
+    video_path: &Path,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    client: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let video = prepare_video(video_path, config_dir, overrides, client, &VideoJob::default())?;
+    let meta = &video.meta;
+    let registro = video.lookup_registro();
+
+    log::emit(format!("\nArquivo: {}", video_path.display()));
+    log::emit(format!(
+        "  Duração:    {:.3}s ({}s)",
+        meta.duration_raw, meta.duration_secs
+    ));
+    log::emit(format!("  Resolução:  {}x{}", meta.width, meta.height));
+    log::emit(format!(
+        "  FPS:        {}/{} ({:.3})",
+        meta.fps_num,
+        meta.fps_den,
+        meta.fps_num as f64 / meta.fps_den.max(1) as f64
+    ));
+    log::emit(format!(
+        "  Áudio:      {}",
+        if meta.has_audio {
+            format!("{} canais", meta.audio_channels)
+        } else {
+            "sem áudio".to_string()
+        }
+    ));
+    log::emit(format!(
+        "\nNome do arquivo{}:",
+        client.map(|c| format!(" (regras de {c})")).unwrap_or_default()
+    ));
+    log::emit(format!(
+        "  Código:     {}",
+        video.code.map(|c| c.to_string()).unwrap_or_else(|| "não encontrado".into())
+    ));
+    log::emit(format!("  Título:     {}", video.titulo));
+    if let Some(v) = &video.info.versao {
+        log::emit(format!("  Versão:     {v}"));
+    }
+    if let Some(i) = &video.info.idioma {
+        log::emit(format!("  Idioma:     {i}"));
+    }
+    match &registro {
+        Some(r) => log::emit(format!("  Registro:   {r}")),
+        None => log::emit(format!("  Registro:   ({})", video.missing_reason())),
+    }
 
-    let template_path = encoder_lib::find_template(&exe_dir)?;
-    let temp_slate = std::env::temp_dir().join("encoder_temp_slate.png");
+    if json {
+        let reason = registro.is_none().then(|| video.missing_reason());
+        let report = json::ProbeReport {
+            video: video_path.to_path_buf(),
+            metadata: video.meta.clone(),
+            code: video.code,
+            titulo: video.titulo.clone(),
+            versao: video.info.versao.clone(),
+            idioma: video.info.idioma.clone(),
+            registro,
+            reason,
+        };
+        json::print("probe", true, &report);
+    }
+    Ok(())
+}
 
-    let titulo = Path::new(filename)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or(filename);
-    let duracao = meta.duration_display();
+/// `encoder slate`: só a claquete, com os mesmos dados que o encode usaria.
+#[allow(clippy::too_many_arguments)]
+fn slate_only(
+    video_path: &Path,
+    output: &Path,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    client: Option<&str>,
+    opts: &RegistroOptions,
+    job: &VideoJob,
+    json: bool,
+) -> Result<()> {
+    let video = prepare_video(video_path, config_dir, overrides, client, job)?;
+    let registro = match &opts.registro {
+        Some(r) => r.clone(),
+        None => resolve_registro(&video.filename, video.code, &video.codes, &video.defaults.extract, opts.on_missing)?
+            .with_context(|| format!("Sem registro: {}", video.missing_reason()))?,
+    };
+    let template_path = encoder_lib::find_template(&exe_dir())?;
+    let duracao = video.meta.duration_display();
     let ano = chrono::Local::now().year().to_string();
+    let slate_data = slate::SlateData::new(&video.titulo, &duracao, &registro, &ano, &video.defaults);
 
-    let slate_data = slate::SlateData::new(titulo, &duracao, &registro, &ano, &defaults);
-
-    println!("Gerando claquete...");
-    slate::generate_slate(&template_path, &slate_data, &temp_slate)?;
+    if let Some(dir) = output.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Não foi possível criar diretório: {}", dir.display()))?;
+    }
+    log::emit("Gerando claquete...");
+    slate::generate_slate(&template_path, &slate_data, output)?;
+    log::emit(format!("✅ Claquete: {}", output.display()));
+    log::emit(format!("  Título: {} | Registro: {registro} | Duração: {duracao}", video.titulo));
+
+    if json {
+        let report = json::SlateReport {
+            video: video_path.to_path_buf(),
+            output: output.to_path_buf(),
+            titulo: video.titulo.clone(),
+            registro,
+            duracao,
+        };
+        json::print("slate", true, &report);
+    }
+    Ok(())
+}
 
-    // 7. Criar diretório de saída
-    std::fs::create_dir_all(output_dir)
-        .with_context(|| format!("Não foi possível criar diretório: {}", output_dir.display()))?;
+/// Diretório do executável (onde procurar `assets/`).
+fn exe_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
 
-    let output_filename = format!("{}.mxf", titulo);
-    let output_path = output_dir.join(&output_filename);
+fn process_video(
+    video_path: &Path,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    output_dir: &Path,
+    client: Option<&str>,
+    opts: &RegistroOptions,
+    job: &VideoJob,
+) -> Result<VideoOutcome> {
+    // 1. Verificar FFmpeg
+    if !job.dry_run {
+        metadata::check_ffmpeg().context("FFmpeg/FFprobe não encontrado no PATH")?;
+    }
 
-    // 8. Encodar MXF
-    encoder::encode(&temp_slate, video_path, &output_path, &meta)?;
+    // 2-5. Configuração, metadados e código do nome do arquivo
+    let video = prepare_video(video_path, config_dir, overrides, client, job)?;
+    let PreparedVideo {
+        defaults, meta, info, ..
+    } = &video;
+    let registro = match job.registro.as_ref().or(opts.registro.as_ref()) {
+        Some(r) => r.clone(),
+        None => match resolve_registro(&video.filename, video.code, &video.codes, &defaults.extract, opts.on_missing)? {
+            Some(r) => r,
+            None => return Ok(VideoOutcome::Skipped(video.missing_reason())),
+        },
+    };
+    log::emit(format!("  Registro: {registro}"));
+    if let Some(v) = &info.versao {
+        log::emit(format!("  Versão: {v}"));
+    }
+    if let Some(i) = &info.idioma {
+        log::emit(format!("  Idioma: {i}"));
+    }
+
+    // 6. Gerar claquete
+    let template_path = encoder_lib::find_template(&exe_dir())?;
+    // Nome único: no batch paralelo várias claquetes existem ao mesmo tempo.
+    static SLATE_SEQ: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let temp_slate = std::env::temp_dir().join(format!(
+        "encoder_temp_slate_{}_{}.png",
+        std::process::id(),
+        SLATE_SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ));
+
+    let titulo = video.titulo.as_str();
+    let output_name = job.output_name.as_deref().unwrap_or(titulo);
+    let duracao = meta.duration_display();
+    let ano = chrono::Local::now().year().to_string();
 
-    // 9. Encodar versão agência (MP4 sem claquete)
+    let slate_data = slate::SlateData::new(titulo, &duracao, &registro, &ano, defaults);
+
+    let mxf = job.formats.mxf.then(|| output_dir.join(format!("{output_name}.mxf")));
     let agency_dir = output_dir.join("agencia");
-    std::fs::create_dir_all(&agency_dir)
-        .with_context(|| format!("Não foi possível criar diretório: {}", agency_dir.display()))?;
-    let agency_path = agency_dir.join(format!("{}.mp4", titulo));
-    encoder::encode_agency(video_path, &agency_path, &meta)?;
+    let mp4 = job.formats.mp4.then(|| agency_dir.join(format!("{output_name}.mp4")));
+    let result = EncodedVideo {
+        titulo: titulo.to_string(),
+        registro: registro.clone(),
+        mxf: mxf.clone(),
+        mp4: mp4.clone(),
+        duration_secs: meta.duration_secs,
+    };
 
-    // 10. Limpar temporários
-    let _ = std::fs::remove_file(&temp_slate);
+    if job.dry_run {
+        log::emit("\n[dry-run] Nada será encodado.\n");
+        for line in config_lines(config_dir, overrides, client)? {
+            log::emit(line);
+        }
+        log::emit(format!(
+            "\nClaquete: título \"{titulo}\" | duração {duracao} | registro {registro} | ano {ano}"
+        ));
+        log::emit(format!("  Template: {}", template_path.display()));
+        if let Some(path) = &mxf {
+            log::emit(format!("\nMXF: {}", path.display()));
+            let cmd = encoder::encode_command(&temp_slate, video_path, path, meta);
+            log::emit(format!("  {}", encoder::command_line(&cmd)));
+        }
+        if let Some(path) = &mp4 {
+            log::emit(format!("\nAgência: {}", path.display()));
+            let cmd = encoder::agency_command(video_path, path, meta);
+            log::emit(format!("  {}", encoder::command_line(&cmd)));
+        }
+        return Ok(VideoOutcome::Planned(result));
+    }
 
-    println!("\nResultado:");
-    println!("  MXF: {}", output_path.display());
-    println!("  Agência: {}", agency_path.display());
-    println!(
-        "  Duração total: {}s (5s claquete + 2s preto + {}s vídeo)",
-        7 + meta.duration_secs,
-        meta.duration_secs
-    );
+    // 7. Criar diretório de saída
+    let dirs = std::iter::once(output_dir).chain(mp4.is_some().then_some(agency_dir.as_path()));
+    for dir in dirs {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Não foi possível criar diretório: {}", dir.display()))?;
+    }
 
-    Ok(())
-}
+    if let Err(e) = log::attach_job_file(&output_dir.join(format!("{output_name}.log"))) {
+        log::warn(format!("{e:#}"));
+    }
 
-fn resolve_registro(filename: &str, codes: &HashMap<u32, String>) -> Result<String> {
-    let code = config::extract_code_from_filename(filename);
+    // 8. Encodar MXF (com claquete) e 9. versão agência (MP4 sem claquete).
+    // Com `concurrent_formats`, os dois FFmpeg rodam ao mesmo tempo.
 
-    match code {
-        Some(c) => match config::lookup_registro(c, codes) {
-            Some(registro) => Ok(registro),
-            None => {
-                eprintln!(
-                    "Código {c} (extraído de \"{filename}\") não encontrado na tabela de registros."
-                );
-                ask_registro(c)
+    let encode_mxf = |path: &Path| -> Result<()> {
+        log::emit("Gerando claquete...");
+        slate::generate_slate(&template_path, &slate_data, &temp_slate)?;
+        let result = encoder::encode(&temp_slate, video_path, path, meta);
+        let _ = std::fs::remove_file(&temp_slate);
+        result
+    };
+    match (&mxf, &mp4) {
+        (Some(mxf_path), Some(mp4_path)) if job.concurrent_formats => {
+            let (prefix, log_job) = (log::prefix(), log::job());
+            std::thread::scope(|scope| {
+                let agency = scope.spawn(|| {
+                    log::set_prefix(prefix);
+                    log::set_job(log_job);
+                    encoder::encode_agency(video_path, mp4_path, meta)
+                });
+                let mxf_result = encode_mxf(mxf_path);
+                let agency_result = agency
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("thread do encode agência abortou")));
+                mxf_result.and(agency_result)
+            })?;
+        }
+        _ => {
+            if let Some(path) = &mxf {
+                encode_mxf(path)?;
+            }
+            if let Some(path) = &mp4 {
+                encoder::encode_agency(video_path, path, meta)?;
+            }
+        }
+    }
+
+    log::emit("\nResultado:");
+    if let Some(path) = &mxf {
+        log::emit(format!("  MXF: {}", path.display()));
+        log::emit(format!(
+            "  Duração total: {}s (5s claquete + 2s preto + {}s vídeo)",
+            7 + meta.duration_secs,
+            meta.duration_secs
+        ));
+    }
+    if let Some(path) = &mp4 {
+        log::emit(format!("  Agência: {}", path.display()));
+    }
+
+    Ok(VideoOutcome::Encoded(result))
+}
+
+/// Registro pelo código do arquivo. Sem registro: pergunta, falha ou
+/// devolve `None` (pular), conforme `on_missing`.
+fn resolve_registro(
+    filename: &str,
+    code: Option<u32>,
+    codes: &HashMap<u32, String>,
+    rules: &config::ExtractRules,
+    on_missing: OnMissingCode,
+) -> Result<Option<String>> {
+    if let Some(registro) = code.and_then(|c| rules.lookup_registro(c, codes)) {
+        return Ok(Some(registro));
+    }
+    let problem = match code {
+        Some(c) => format!("Código {c} (extraído de \"{filename}\") não encontrado na tabela de registros."),
+        None => format!("Não foi possível extrair código numérico de \"{filename}\"."),
+    };
+    match on_missing {
+        OnMissingCode::Skip => {
+            log::emit(format!("{problem} Pulando."));
+            Ok(None)
+        }
+        OnMissingCode::Fail => {
+            match code {
+                Some(c) => Err(anyhow::Error::new(EncoderError::CodeNotFound(c))
+                    .context(format!("\"{filename}\": use --registro ou atualize o codes.toml"))),
+                None => bail!("{problem} Use --registro ou atualize o codes.toml."),
+            }
+        }
+        OnMissingCode::Prompt => {
+            // No batch paralelo, uma pergunta por vez no terminal.
+            static PROMPT: std::sync::Mutex<()> = std::sync::Mutex::new(());
+            let _turn = PROMPT.lock().unwrap_or_else(|e| e.into_inner());
+            eprintln!("{}{problem}", log::prefix().unwrap_or_default());
+            match code {
+                Some(c) => ask_registro(c).map(Some),
+                None => ask_registro_manual().map(Some),
             }
-        },
-        None => {
-            eprintln!("Não foi possível extrair código numérico de \"{filename}\".");
-            ask_registro_manual()
         }
     }
 }
@@ -282,14 +1181,20 @@ fn ask_registro_manual() -> Result<String> {
 /// 1. Flag --output da linha de comando
 /// 2. Campo `output` no defaults.toml (do cliente, se informado)
 /// 3. Mesmo diretório do vídeo de entrada (ou "." se não houver vídeo)
-fn resolve_output_dir(flag: Option<PathBuf>, config_dir: &Path, video: Option<&PathBuf>, client: Option<&str>) -> PathBuf {
+fn resolve_output_dir(
+    flag: Option<PathBuf>,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    video: Option<&PathBuf>,
+    client: Option<&str>,
+) -> PathBuf {
     // 1. Flag explícita
     if let Some(dir) = flag {
         return dir;
     }
 
     // 2. Config
-    if let Ok(defaults) = config::load_defaults_for(config_dir, client) {
+    if let Ok(defaults) = config::load_defaults_for(config_dir, client, overrides) {
         if !defaults.output.is_empty() {
             return PathBuf::from(&defaults.output);
         }
@@ -307,116 +1212,1314 @@ fn resolve_output_dir(flag: Option<PathBuf>, config_dir: &Path, video: Option<&P
     PathBuf::from(".")
 }
 
-fn run_batch(lista_path: &Path, config_dir: &Path, output_dir: &Path, client: Option<&str>) -> Result<()> {
-    #[derive(serde::Deserialize)]
-    struct BatchFile {
-        videos: Vec<String>,
+/// Paralelismo do batch.
+struct BatchRun {
+    jobs: usize,
+    concurrent_formats: bool,
+    resume: bool,
+}
+
+/// Runtime e sessão do Peach de um batch/watch, criados só se algum vídeo sobe.
+struct PeachBatch {
+    rt: tokio::runtime::Runtime,
+    /// Aberta no primeiro upload e reaproveitada por todos. O lock só cobre
+    /// pegar (ou abrir) a sessão, não o envio.
+    login: tokio::sync::Mutex<Option<std::sync::Arc<PeachSession>>>,
+    /// Uma distribuição por vez: o portal guarda contexto na sessão (listas
+    /// de destinos, validação do envio). Uploads e a espera do QC correm em
+    /// paralelo.
+    sends: tokio::sync::Semaphore,
+}
+
+struct PeachSession {
+    client: peach::PeachClient,
+    creds: peach::PeachCredentials,
+}
+
+impl PeachBatch {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            rt: tokio::runtime::Runtime::new().context("falha ao iniciar runtime tokio")?,
+            login: tokio::sync::Mutex::new(None),
+            sends: tokio::sync::Semaphore::new(1),
+        })
+    }
+
+    /// Sessão logada, abrindo no primeiro uso. A sessão é compartilhada no
+    /// lote: vale o retry do primeiro cliente.
+    async fn session(&self, config_dir: &Path, retry: &peach::RetryPolicy) -> Result<std::sync::Arc<PeachSession>> {
+        let mut login = self.login.lock().await;
+        if let Some(session) = login.as_ref() {
+            return Ok(session.clone());
+        }
+        let creds = peach::PeachCredentials::load(config_dir)?;
+        log::emit(format!("Fazendo login em latam.peachvideo.com como {}...", creds.email));
+        let client = peach::PeachClient::for_account(&creds.email)?.with_retry(retry.clone());
+        let info = client.connect(&creds.email, &creds.password).await?;
+        log::emit(format!("✅ Logado como {} ({})", info.nombre_usuario_activo, info.id_empresa));
+        let session = std::sync::Arc::new(PeachSession { client, creds });
+        *login = Some(session.clone());
+        Ok(session)
     }
+}
+
+/// Estado do batch no disco, regravado a cada etapa concluída.
+struct BatchStateFile {
+    /// `None` = só em memória (watch).
+    path: Option<PathBuf>,
+    state: std::sync::Mutex<batch::BatchState>,
+}
 
-    let content = std::fs::read_to_string(lista_path)
-        .with_context(|| format!("Não foi possível ler {}", lista_path.display()))?;
-    let batch: BatchFile = toml::from_str(&content)
-        .with_context(|| format!("Erro ao parsear {}", lista_path.display()))?;
+impl BatchStateFile {
+    fn get(&self, i: usize) -> batch::ItemState {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).items[i].clone()
+    }
 
-    println!("Processando {} vídeos...\n", batch.videos.len());
+    fn update(&self, i: usize, f: impl FnOnce(&mut batch::ItemState)) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let item = &mut state.items[i];
+        f(item);
+        item.updated = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let Some(path) = &self.path else { return };
+        if let Err(e) = state.save(path) {
+            log::warn(format!("falha ao gravar estado do batch: {e:#}"));
+        }
+    }
+}
 
+/// Como terminou cada vídeo do batch.
+enum ItemResult {
+    Done(String),
+    Skipped(String),
+    Failed(anyhow::Error),
+}
+
+fn run_batch(
+    lista_path: &Path,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    cli_defaults: &batch::ItemOptions,
+    opts: &RegistroOptions,
+    run: &BatchRun,
+    json: bool,
+) -> Result<()> {
+    let started = std::time::Instant::now();
+    let manifest = batch::Manifest::load(lista_path)?;
+    let items = manifest.items(cli_defaults)?;
+    let total = items.len();
+    let jobs = run.jobs.clamp(1, total.max(1));
+
+    let state_path = batch::BatchState::path_for(lista_path);
+    let previous = if run.resume {
+        if state_path.exists() {
+            log::emit(format!("Retomando a partir de {}", state_path.display()));
+            Some(batch::BatchState::load(&state_path)?)
+        } else {
+            log::emit(format!("Nenhum estado anterior em {}; processando tudo.", state_path.display()));
+            None
+        }
+    } else {
+        if state_path.exists() {
+            log::emit(format!("Estado anterior em {} será sobrescrito (use --resume para retomar).", state_path.display()));
+        }
+        None
+    };
+    let state = BatchStateFile {
+        state: std::sync::Mutex::new(batch::BatchState::for_items(previous, &items)),
+        path: Some(state_path.clone()),
+    };
+    state
+        .state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .save(&state_path)?;
+
+    if jobs > 1 {
+        log::emit(format!("Processando {total} vídeos com {jobs} jobs em paralelo...\n"));
+    } else {
+        log::emit(format!("Processando {total} vídeos...\n"));
+    }
+
+    // Sessão do Peach aberta só se algum vídeo sobe, e reaproveitada por todos.
+    let peach = if items.iter().any(|i| i.upload) {
+        Some(PeachBatch::new()?)
+    } else {
+        None
+    };
+
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let results: std::sync::Mutex<Vec<Option<ItemResult>>> =
+        std::sync::Mutex::new((0..total).map(|_| None).collect());
+    let reports: std::sync::Mutex<Vec<Option<json::VideoReport>>> =
+        std::sync::Mutex::new((0..total).map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let Some(item) = items.get(i) else { break };
+                if jobs > 1 {
+                    log::set_prefix(Some(format!("[{}/{}] ", i + 1, total)));
+                    log::emit(format!("=== {} ===", item.path.display()));
+                } else {
+                    log::emit(format!("=== [{}/{}] {} ===", i + 1, total, item.path.display()));
+                }
+
+                let item_started = std::time::Instant::now();
+                let result = run_batch_item(i, item, config_dir, overrides, opts, run, peach.as_ref(), &state);
+                let report = item_report(&item.path, Some(&state.get(i)), &result, item_started.elapsed());
+                reports.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(report);
+                match &result {
+                    ItemResult::Done(status) if jobs > 1 => log::emit(format!("✅ {status}")),
+                    _ => {}
+                }
+                if jobs == 1 {
+                    log::emit("");
+                }
+                results.lock().unwrap_or_else(|e| e.into_inner())[i] = Some(result);
+            });
+        }
+    });
+    if jobs > 1 {
+        log::emit("");
+    }
+
+    let results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    if json {
+        let items: Vec<json::VideoReport> = reports
+            .into_inner()
+            .unwrap_or_else(|e| e.into_inner())
+            .into_iter()
+            .zip(&items)
+            .map(|(r, item)| {
+                r.unwrap_or_else(|| {
+                    let e = anyhow::anyhow!("worker abortou");
+                    item_report(&item.path, None, &ItemResult::Failed(e), std::time::Duration::ZERO)
+                })
+            })
+            .collect();
+        let count = |status: &[&str]| items.iter().filter(|r| status.contains(&r.status)).count();
+        let report = json::BatchReport {
+            manifest: lista_path.to_path_buf(),
+            total,
+            done: count(&["encoded", "uploaded", "distributed"]),
+            skipped: count(&["skipped"]),
+            failed: count(&["failed"]),
+            elapsed_secs: started.elapsed().as_secs_f64(),
+            items,
+        };
+        json::print("batch", report.failed == 0, &report);
+    }
     let mut errors = Vec::new();
-    for (i, video) in batch.videos.iter().enumerate() {
-        println!(
-            "=== [{}/{}] {} ===",
-            i + 1,
-            batch.videos.len(),
-            video
-        );
-        let path = PathBuf::from(video);
-        if let Err(e) = process_video(&path, config_dir, output_dir, client) {
-            eprintln!("ERRO: {e}");
-            errors.push((video.clone(), e));
+    let mut skipped = Vec::new();
+    let mut done = Vec::new();
+    for (item, result) in items.iter().zip(results) {
+        let video = item.path.display().to_string();
+        match result {
+            Some(ItemResult::Done(status)) => done.push((video, status)),
+            Some(ItemResult::Skipped(reason)) => skipped.push((video, reason)),
+            Some(ItemResult::Failed(e)) => errors.push((video, e)),
+            None => errors.push((video, anyhow::anyhow!("worker abortou"))),
         }
-        println!();
     }
 
-    if errors.is_empty() {
-        println!("Todos os vídeos processados com sucesso!");
+    if errors.is_empty() && skipped.is_empty() {
+        log::emit("Todos os vídeos processados com sucesso!");
     } else {
+        log::emit(format!("{} processado(s), {} pulado(s), {} erro(s)", done.len(), skipped.len(), errors.len()));
+    }
+    let resumed = done.iter().filter(|(_, status)| status == "já concluído").count();
+    if resumed > 0 {
+        log::emit(format!("{resumed} já concluído(s) em execução anterior"));
+    }
+    if done.iter().any(|(_, status)| status != "encodado" && status != "já concluído") {
+        log::emit("\nEnviados:");
+        for (video, status) in &done {
+            log::emit(format!("  - {video}: {status}"));
+        }
+    }
+    if !skipped.is_empty() {
+        log::emit("\nPulados (sem registro):");
+        for (video, reason) in &skipped {
+            log::emit(format!("  - {video}: {reason}"));
+        }
+    }
+    if !errors.is_empty() {
         eprintln!("\n{} erro(s) encontrado(s):", errors.len());
         for (video, err) in &errors {
-            eprintln!("  - {video}: {err}");
+            eprintln!("  - {video}: {err:#}");
+        }
+    }
+
+    batch_outcome(errors.len(), total, json)
+}
+
+/// Fim do batch/`watch --once`: erro `BatchFailed` se algum vídeo falhou.
+/// Com `--json` o relatório já saiu, então só define o código de saída.
+fn batch_outcome(failed: usize, total: usize, json: bool) -> Result<()> {
+    if failed == 0 {
+        return Ok(());
+    }
+    let e = EncoderError::BatchFailed { failed, total };
+    if json {
+        std::process::exit(e.exit_code());
+    }
+    Err(e.into())
+}
+
+/// `batch --dry-run`: resolve cada item (config, registro, saídas, comandos
+/// FFmpeg, campos do Peach e destinos) sem encodar, logar nem gravar estado.
+fn plan_batch(
+    lista_path: &Path,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    cli_defaults: &batch::ItemOptions,
+    opts: &RegistroOptions,
+    json: bool,
+) -> Result<()> {
+    let started = std::time::Instant::now();
+    let manifest = batch::Manifest::load(lista_path)?;
+    let items = manifest.items(cli_defaults)?;
+    let total = items.len();
+    log::emit(format!("Simulando {total} vídeos (--dry-run)...\n"));
+
+    let mut reports = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        log::emit(format!("=== [{}/{}] {} ===", i + 1, total, item.path.display()));
+        let item_started = std::time::Instant::now();
+        let result = plan_batch_item(item, config_dir, overrides, opts);
+        let mut report = video_report(&item.path, &result, item_started.elapsed());
+        if let Err(e) = &result {
+            log::error(format!("{e:#}"));
+        }
+        if let Ok(VideoOutcome::Skipped(reason)) = &result {
+            log::emit(format!("Pulado: {reason}"));
+        }
+        if report.status == "planned" && item.distribute {
+            if let Some(destinos) = item_destinos(item, config_dir, overrides) {
+                report.destinos = destinos;
+            }
+        }
+        log::emit("");
+        reports.push(report);
+    }
+
+    let count = |status: &str| reports.iter().filter(|r| r.status == status).count();
+    let (done, skipped, failed) = (count("planned"), count("skipped"), count("failed"));
+    log::emit(format!("{done} a processar, {skipped} pulado(s), {failed} erro(s)"));
+    if json {
+        let report = json::BatchReport {
+            manifest: lista_path.to_path_buf(),
+            total,
+            done,
+            skipped,
+            failed,
+            elapsed_secs: started.elapsed().as_secs_f64(),
+            items: reports,
+        };
+        json::print("batch", failed == 0, &report);
+    }
+    Ok(())
+}
+
+fn plan_batch_item(
+    item: &batch::BatchItem,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    opts: &RegistroOptions,
+) -> Result<VideoOutcome> {
+    let client = item.client.as_deref();
+    let output_dir = resolve_output_dir(item.output.clone(), config_dir, overrides, None, client);
+    let job = VideoJob {
+        titulo: item.titulo.clone(),
+        registro: item.registro.clone(),
+        code: item.code,
+        output_name: item.output_name.clone(),
+        formats: item.formats,
+        concurrent_formats: false,
+        dry_run: true,
+    };
+    let outcome = process_video(&item.path, config_dir, overrides, &output_dir, client, opts, &job)?;
+    let VideoOutcome::Planned(planned) = &outcome else {
+        return Ok(outcome);
+    };
+    if !item.upload {
+        return Ok(outcome);
+    }
+
+    let client_name = client.unwrap_or_default();
+    let peach_cfg = load_peach_config(config_dir, overrides, client_name)?;
+    let mxf = planned.mxf.as_deref().context("upload sem MXF")?;
+    let codigo = peach::registro_to_codigo(&planned.registro);
+    log::emit("");
+    print_init_upload(
+        config_dir,
+        &peach_cfg,
+        mxf,
+        &planned.titulo,
+        &codigo,
+        &UploadPlan::for_encode(planned.duration_secs),
+    )?;
+    if item.distribute {
+        let (hd_ids, sd_ids) = select_destinos(&peach_cfg, item.destinos.as_deref())?;
+        log::emit(format!("Distribuição → {} HD + {} SD destino(s):", hd_ids.len(), sd_ids.len()));
+        for id in &hd_ids {
+            log::emit(format!("  HD → {id}"));
+        }
+        for id in &sd_ids {
+            log::emit(format!("  SD → {id}"));
+        }
+    }
+    Ok(outcome)
+}
+
+/// IDs dos destinos de um item (pro relatório do `--dry-run`).
+fn item_destinos(
+    item: &batch::BatchItem,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+) -> Option<Vec<String>> {
+    let peach_cfg = load_peach_config(config_dir, overrides, item.client.as_deref().unwrap_or_default()).ok()?;
+    let (hd, sd) = select_destinos(&peach_cfg, item.destinos.as_deref()).ok()?;
+    Some(hd.into_iter().chain(sd).collect())
+}
+
+/// Resultado de um item do batch/watch no formato do `--json`, a partir do
+/// estado registrado.
+fn item_report(
+    video: &Path,
+    state: Option<&batch::ItemState>,
+    result: &ItemResult,
+    elapsed: std::time::Duration,
+) -> json::VideoReport {
+    let mut report = json::VideoReport {
+        video: video.to_path_buf(),
+        elapsed_secs: elapsed.as_secs_f64(),
+        ..Default::default()
+    };
+    if let Some(s) = state {
+        let output = |ext: &str| {
+            s.outputs
+                .iter()
+                .find(|o| o.path.extension().map(|e| e.eq_ignore_ascii_case(ext)).unwrap_or(false))
+                .map(|o| o.path.clone())
+        };
+        report.titulo = s.titulo.clone();
+        report.registro = s.registro.clone();
+        report.duration_secs = s.duration_secs;
+        report.mxf = output("mxf");
+        report.mp4 = output("mp4");
+        report.spot_id = s.spot_id;
+        report.id_envio = s.id_envio.clone();
+        report.destinos = s.destinos.clone();
+    }
+    report.status = match result {
+        ItemResult::Done(_) => match state.map(|s| s.stage) {
+            Some(batch::Stage::Distributed) => "distributed",
+            Some(batch::Stage::Uploaded) => "uploaded",
+            _ => "encoded",
+        },
+        ItemResult::Skipped(reason) => {
+            report.reason = Some(reason.clone());
+            "skipped"
+        }
+        ItemResult::Failed(e) => {
+            report.error = Some(json::ErrorInfo::from_error(e));
+            "failed"
+        }
+    };
+    report
+}
+
+/// Encode (+ upload/distribuição, se pedido) de um vídeo do batch. Cada
+/// etapa concluída vai pro arquivo de estado; com `--resume`, as etapas já
+/// registradas são puladas.
+#[allow(clippy::too_many_arguments)]
+fn run_batch_item(
+    i: usize,
+    item: &batch::BatchItem,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    opts: &RegistroOptions,
+    run: &BatchRun,
+    peach: Option<&PeachBatch>,
+    state: &BatchStateFile,
+) -> ItemResult {
+    let _job = log::begin_job(log::job_id_for(&file_stem(&item.path)));
+    let result = batch_item_steps(i, item, config_dir, overrides, opts, run, peach, state);
+    match &result {
+        ItemResult::Failed(e) => {
+            log::error(format!("{e:#}"));
+            state.update(i, |s| {
+                s.stage = batch::Stage::Failed;
+                s.error = Some(format!("{e:#}"));
+            })
+        }
+        ItemResult::Skipped(reason) => state.update(i, |s| s.error = Some(reason.clone())),
+        ItemResult::Done(_) => {}
+    }
+    result
+}
+
+/// Nome do arquivo sem extensão (id do job no log).
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "video".to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn batch_item_steps(
+    i: usize,
+    item: &batch::BatchItem,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    opts: &RegistroOptions,
+    run: &BatchRun,
+    peach: Option<&PeachBatch>,
+    state: &BatchStateFile,
+) -> ItemResult {
+    let previous = state.get(i);
+    let final_stage = if item.distribute {
+        batch::Stage::Distributed
+    } else if item.upload {
+        batch::Stage::Uploaded
+    } else {
+        batch::Stage::Encoded
+    };
+
+    // Encode: reaproveitado se as saídas registradas ainda conferem.
+    let reuse = if run.resume && previous.stage != batch::Stage::Pending {
+        match previous.verified_outputs(item.formats) {
+            Ok(()) => true,
+            Err(reason) => {
+                log::emit(format!("Encode anterior descartado ({reason}); encodando de novo."));
+                false
+            }
+        }
+    } else {
+        false
+    };
+    let encoded = if reuse {
+        let output = |ext: &str| {
+            previous
+                .outputs
+                .iter()
+                .find(|o| o.path.extension().map(|e| e.eq_ignore_ascii_case(ext)).unwrap_or(false))
+                .map(|o| o.path.clone())
+        };
+        EncodedVideo {
+            titulo: previous.titulo.clone().unwrap_or_default(),
+            registro: previous.registro.clone().unwrap_or_default(),
+            mxf: item.formats.mxf.then(|| output("mxf")).flatten(),
+            mp4: item.formats.mp4.then(|| output("mp4")).flatten(),
+            duration_secs: previous.duration_secs.unwrap_or_default(),
+        }
+    } else {
+        let client = item.client.as_deref();
+        let output_dir = resolve_output_dir(item.output.clone(), config_dir, overrides, None, client);
+        let job = VideoJob {
+            titulo: item.titulo.clone(),
+            registro: item.registro.clone(),
+            code: item.code,
+            output_name: item.output_name.clone(),
+            formats: item.formats,
+            concurrent_formats: run.concurrent_formats,
+            dry_run: false,
+        };
+        let encoded = match process_video(&item.path, config_dir, overrides, &output_dir, client, opts, &job) {
+            Ok(VideoOutcome::Encoded(e) | VideoOutcome::Planned(e)) => e,
+            Ok(VideoOutcome::Skipped(reason)) => return ItemResult::Skipped(reason),
+            Err(e) => return ItemResult::Failed(e),
+        };
+        // Hash só vale a leitura extra se o estado vai pro disco (`--resume`).
+        let mut outputs = Vec::new();
+        for path in encoded.mxf.iter().chain(encoded.mp4.iter()) {
+            match batch::OutputFile::record(path, state.path.is_some()) {
+                Ok(out) => outputs.push(out),
+                Err(e) => return ItemResult::Failed(e),
+            }
+        }
+        // Arquivo novo = spot novo no Peach.
+        state.update(i, |s| {
+            *s = batch::ItemState::new(&item.path);
+            s.stage = batch::Stage::Encoded;
+            s.titulo = Some(encoded.titulo.clone());
+            s.registro = Some(encoded.registro.clone());
+            s.duration_secs = Some(encoded.duration_secs);
+            s.outputs = outputs;
+        });
+        encoded
+    };
+    if reuse && previous.stage.reached(final_stage) {
+        log::emit("Já concluído em execução anterior; pulando.");
+        return ItemResult::Done("já concluído".to_string());
+    }
+    if !item.upload {
+        if reuse {
+            state.update(i, |s| {
+                s.stage = batch::Stage::Encoded;
+                s.error = None;
+            });
+        }
+        return ItemResult::Done("encodado".to_string());
+    }
+    if reuse {
+        log::emit(format!("Encode anterior reaproveitado: {}", encoded.titulo));
+    }
+
+    let Some(peach) = peach else {
+        return ItemResult::Failed(anyhow::anyhow!("upload pedido sem sessão do Peach aberta"));
+    };
+    let result = peach.rt.block_on(async {
+        let client_name = item.client.as_deref().unwrap_or_default();
+        let peach_cfg = load_peach_config(config_dir, overrides, client_name)?;
+        let session = peach.session(config_dir, &peach_cfg.retry).await?;
+        let (pclient, creds) = (&session.client, &session.creds);
+        let mxf = encoded.mxf.as_deref().context("upload sem MXF")?;
+        let codigo = peach::registro_to_codigo(&encoded.registro);
+
+        // Upload: pulado se o spot já subiu numa execução anterior.
+        let uploaded = match (reuse, previous.spot_id) {
+            (true, Some(spot_id)) => {
+                log::emit(format!("Upload já feito (spot_id={spot_id}); pulando."));
+                let sha256 = previous
+                    .outputs
+                    .iter()
+                    .find(|o| o.path == mxf)
+                    .map(|o| o.sha256.clone())
+                    .unwrap_or_default();
+                (spot_id, previous.id_envio.clone().unwrap_or_default(), sha256)
+            }
+            _ => {
+                let uploaded = upload_mxf(pclient, creds, &peach_cfg, mxf, &encoded.titulo, &codigo).await?;
+                let sts = uploaded.sts;
+                let spot_id = sts.spot_id().with_context(|| {
+                    format!("Não foi possível extrair spot_id do destination '{}'", sts.destination)
+                })?;
+                state.update(i, |s| {
+                    s.stage = batch::Stage::Uploaded;
+                    s.spot_id = Some(spot_id);
+                    s.id_envio = Some(sts.id_envio.clone());
+                    s.error = None;
+                });
+                (spot_id, sts.id_envio, uploaded.sha256)
+            }
+        };
+        let (spot_id, id_envio, sha256) = uploaded;
+        if !item.distribute {
+            state.update(i, |s| {
+                s.stage = batch::Stage::Uploaded;
+                s.error = None;
+            });
+            return Ok(format!("upload OK, spot_id={spot_id}, id_envio={id_envio}"));
+        }
+
+        let (status, destinos) =
+            batch_distribute(pclient, &peach.sends, &peach_cfg, item, &encoded, spot_id, &id_envio, &sha256).await?;
+        state.update(i, |s| {
+            s.stage = batch::Stage::Distributed;
+            s.destinos = destinos;
+            s.error = None;
+        });
+        Ok(status)
+    });
+    match result {
+        Ok(status) => ItemResult::Done(status),
+        Err(e) => ItemResult::Failed(e),
+    }
+}
+
+/// Distribuição de um spot do batch já enviado. Devolve o status pro resumo
+/// e os IDs dos destinos.
+#[allow(clippy::too_many_arguments)]
+async fn batch_distribute(
+    pclient: &peach::PeachClient,
+    turn: &tokio::sync::Semaphore,
+    peach_cfg: &peach::PeachConfig,
+    item: &batch::BatchItem,
+    encoded: &EncodedVideo,
+    spot_id: u64,
+    id_envio: &str,
+    sha256: &str,
+) -> Result<(String, Vec<String>)> {
+    let (hd_ids, sd_ids) = select_destinos(peach_cfg, item.destinos.as_deref())?;
+    log::emit(format!(
+        "\nDistribuindo spot {spot_id} → {} HD + {} SD destino(s)...",
+        hd_ids.len(),
+        sd_ids.len()
+    ));
+    let req = peach::SendRequest {
+        spot_ids: &[spot_id],
+        destinos_hd: &hd_ids,
+        destinos_sd: &sd_ids,
+    };
+    let summary = pclient.send_spots_queued(&req, peach_cfg, Some(turn)).await?;
+    log::emit(format!("✅ {summary}"));
+
+    let labels: Vec<&str> = peach_cfg
+        .destinos
+        .hd
+        .iter()
+        .chain(peach_cfg.destinos.sd.iter())
+        .filter(|d| hd_ids.iter().chain(sd_ids.iter()).any(|id| id == d.id()))
+        .map(|d| d.label())
+        .collect();
+    let log_entry = peach::send::SendLogEntry {
+        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        pieza: encoded.titulo.clone(),
+        codigo: peach::registro_to_codigo(&encoded.registro),
+        spot_id,
+        destinos: labels.join("; "),
+        id_envio: id_envio.to_string(),
+        agencia_url: String::new(),
+        sha256: sha256.to_string(),
+    };
+    let log_dir = encoded
+        .mxf
+        .as_deref()
+        .and_then(Path::parent)
+        .unwrap_or(Path::new("."));
+    if let Err(e) = peach::send::append_send_log(log_dir, &log_entry) {
+        log::warn(format!("falha ao gravar log CSV: {e}"));
+    }
+    peach::send::post_webhook(&peach_cfg.webhook_url, &log_entry, &peach_cfg.avisador_id).await;
+
+    let status = format!(
+        "spot_id={spot_id} distribuído para {} destino(s)",
+        hd_ids.len() + sd_ids.len()
+    );
+    Ok((status, hd_ids.into_iter().chain(sd_ids).collect()))
+}
+
+// ----------------- Watch -----------------
+
+struct WatchSettings {
+    settle: std::time::Duration,
+    interval: std::time::Duration,
+    once: bool,
+}
+
+fn run_watch(
+    dir: &Path,
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    options: &batch::ItemOptions,
+    opts: &RegistroOptions,
+    settings: &WatchSettings,
+    json: bool,
+) -> Result<()> {
+    if !dir.is_dir() {
+        bail!("Pasta não encontrada: {}", dir.display());
+    }
+    // Valida as opções antes de esperar o primeiro arquivo.
+    let resolved = batch::resolve(dir.join("_.mp4"), options.clone())?;
+
+    // Saída na própria pasta faria o MXF gerado entrar na fila. Sem saída
+    // configurada, usa <pasta>/output (subpastas não são monitoradas).
+    let mut options = options.clone();
+    let output_dir = resolve_output_dir(resolved.output, config_dir, overrides, None, resolved.client.as_deref());
+    if output_dir == Path::new(".") {
+        options.output = Some(dir.join("output").display().to_string());
+    } else if std::fs::canonicalize(&output_dir).ok() == std::fs::canonicalize(dir).ok() {
+        bail!("A saída não pode ser a própria pasta monitorada ({})", dir.display());
+    }
+    let options = &options;
+
+    let peach = if options.upload == Some(true) || options.distribute == Some(true) || options.destinos.is_some() {
+        Some(PeachBatch::new()?)
+    } else {
+        None
+    };
+    let run = BatchRun {
+        jobs: 1,
+        concurrent_formats: false,
+        resume: false,
+    };
+
+    log::emit(format!(
+        "Monitorando {} (arquivo estável após {}s; Ctrl+C para sair)...",
+        dir.display(),
+        settings.settle.as_secs()
+    ));
+    let mut files = watch::StableFiles::new(settings.settle);
+    let (mut processed, mut failed) = (0, 0);
+    loop {
+        let stable = match files.scan(dir) {
+            Ok(stable) => stable,
+            Err(e) if settings.once => return Err(e),
+            // Pasta de rede fora do ar etc.: tenta de novo na próxima volta.
+            Err(e) => {
+                log::warn(format!("{e:#}"));
+                Vec::new()
+            }
+        };
+        for video in stable {
+            log::emit(format!("\n=== {} ===", video.display()));
+            let started = std::time::Instant::now();
+            let (result, state) = match batch::resolve(video.clone(), options.clone()) {
+                Ok(item) => {
+                    let state = BatchStateFile {
+                        path: None,
+                        state: std::sync::Mutex::new(batch::BatchState::for_items(None, std::slice::from_ref(&item))),
+                    };
+                    let result = run_batch_item(0, &item, config_dir, overrides, opts, &run, peach.as_ref(), &state);
+                    (result, Some(state.get(0)))
+                }
+                Err(e) => (ItemResult::Failed(e), None),
+            };
+            if json {
+                let report = item_report(&video, state.as_ref(), &result, started.elapsed());
+                let ok = !matches!(report.status, "failed" | "skipped");
+                json::print("watch", ok, &report);
+            }
+            processed += 1;
+            if matches!(result, ItemResult::Failed(_)) {
+                failed += 1;
+            }
+            if !watch_finish(dir, &video, &result) {
+                log::warn(format!(
+                    "{} fica na pasta e não será processado de novo enquanto não mudar.",
+                    video.display()
+                ));
+                files.ignore(&video);
+            }
+        }
+        if settings.once && files.pending() == 0 {
+            return batch_outcome(failed, processed, json);
         }
+        std::thread::sleep(settings.interval);
     }
+}
+
+/// Move o original pra done/ ou failed/ e registra no watch.log. Devolve se
+/// conseguiu tirar o original da pasta.
+fn watch_finish(dir: &Path, video: &Path, result: &ItemResult) -> bool {
+    let (sub, status, detail) = match result {
+        ItemResult::Done(status) => (watch::DONE_DIR, "OK", status.clone()),
+        ItemResult::Skipped(reason) => (watch::FAILED_DIR, "PULADO", reason.clone()),
+        ItemResult::Failed(e) => (watch::FAILED_DIR, "ERRO", format!("{e:#}")),
+    };
+    match result {
+        ItemResult::Done(status) => log::emit(format!("✅ {status}")),
+        ItemResult::Skipped(reason) => log::emit(format!("Pulado: {reason}")),
+        // já registrado no log do job por run_batch_item
+        ItemResult::Failed(_) => {}
+    }
+    let moved = match watch::move_to(video, sub) {
+        Ok(moved) => {
+            log::emit(format!("Original movido para {}", moved.display()));
+            true
+        }
+        Err(e) => {
+            log::warn(format!("{e:#}"));
+            false
+        }
+    };
+    if let Err(e) = watch::append_log(dir, status, video, &detail) {
+        log::warn(format!("falha ao gravar {}: {e:#}", watch::LOG_FILE));
+    }
+    moved
+}
+
+// ----------------- Config -----------------
+
+fn run_config(action: ConfigAction, overrides: &[(String, String)]) -> Result<()> {
+    match action {
+        ConfigAction::Show { client, config } => {
+            let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+            config_show(&config_dir, overrides, client.as_deref())
+        }
+        ConfigAction::Check { client, config } => {
+            let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+            config_check(&config_dir, client.as_deref())
+        }
+    }
+}
+
+fn config_check(config_dir: &Path, client: Option<&str>) -> Result<()> {
+    let clients: Vec<Option<String>> = match client {
+        Some(c) => vec![Some(c.to_string())],
+        None => std::iter::once(None)
+            .chain(config::list_clients(config_dir).into_iter().map(Some))
+            .collect(),
+    };
 
+    let mut report = check::Report::default();
+    let mut needs_peach = false;
+    for c in &clients {
+        let before = report.diagnostics.len();
+        check::check_client(config_dir, c.as_deref(), &mut report);
+        let found = report.diagnostics.len() - before;
+        let status = if found == 0 { "✅" } else { "⚠️ " };
+        println!("{status} {}", c.as_deref().unwrap_or("(Padrão)"));
+        for d in &report.diagnostics[before..] {
+            println!("   {d}");
+        }
+        needs_peach |= peach::config::DefaultsWithPeach::load(config_dir, c.as_deref(), &[])
+            .map(|d| d.peach.is_some())
+            .unwrap_or(false);
+    }
+
+    let before = report.diagnostics.len();
+    check::check_environment(config_dir, &exe_dir(), needs_peach, &mut report);
+    for d in &report.diagnostics[before..] {
+        println!("   {d}");
+    }
+
+    println!(
+        "\n{} erro(s), {} aviso(s) em {} perfil(is)",
+        report.errors(),
+        report.warnings(),
+        clients.len()
+    );
+    if report.errors() > 0 {
+        bail!("Configuração inválida");
+    }
+    Ok(())
+}
+
+fn config_show(config_dir: &Path, overrides: &[(String, String)], client: Option<&str>) -> Result<()> {
+    for line in config_lines(config_dir, overrides, client)? {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// Configuração efetiva com a origem de cada campo (`config show` e `--dry-run`).
+fn config_lines(config_dir: &Path, overrides: &[(String, String)], client: Option<&str>) -> Result<Vec<String>> {
+    let layered = config::LayeredConfig::load_with(config_dir, client, overrides)?;
+    let mut lines = vec![format!(
+        "Configuração efetiva de {}:\n",
+        client.unwrap_or("(Padrão)")
+    )];
+    let rows = layered.describe();
+    let width = rows.iter().map(|(_, _, s)| s.len()).max().unwrap_or(0);
+    for (key, value, source) in &rows {
+        lines.push(format!("  {source:<width$}  {key} = {value}"));
+    }
+    lines.push(format!(
+        "\ncodes.toml: {}",
+        config::codes_path_for(config_dir, client).display()
+    ));
+    Ok(lines)
+}
+
+// ----------------- Client -----------------
+
+fn run_client(action: ClientAction) -> Result<()> {
+    match action {
+        ClientAction::New { name, config, codes } => {
+            let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+            client_new(&config_dir, &name, codes)
+        }
+    }
+}
+
+/// Pergunta um valor no terminal; Enter mantém o default.
+fn prompt(label: &str, default: &str) -> Result<String> {
+    if default.is_empty() {
+        eprint!("{label}: ");
+    } else {
+        eprint!("{label} [{default}]: ");
+    }
+    io::stderr().flush()?;
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        bail!("Entrada encerrada");
+    }
+    let input = input.trim();
+    Ok(if input.is_empty() { default } else { input }.to_string())
+}
+
+/// `prompt` repetido até o valor passar em `validate`.
+fn prompt_valid<T>(label: &str, default: &str, validate: impl Fn(&str) -> Result<T, String>) -> Result<T> {
+    loop {
+        let value = prompt(label, default)?;
+        match validate(&value) {
+            Ok(v) => return Ok(v),
+            Err(e) => eprintln!("  {e}"),
+        }
+    }
+}
+
+fn prompt_destinos(codec: &str) -> Result<Vec<(String, String)>> {
+    let mut list = Vec::new();
+    loop {
+        let entry = prompt_valid(
+            &format!("Destino {codec} (ID=nome, vazio para terminar)"),
+            "",
+            |v| {
+                if v.is_empty() {
+                    return Ok(None);
+                }
+                let (id, nome) = v.split_once('=').unwrap_or((v, ""));
+                let id = id.trim().to_string();
+                check::validate_peach_field("destino", &id)?;
+                Ok(Some((id, nome.trim().to_string())))
+            },
+        )?;
+        match entry {
+            Some(e) => list.push(e),
+            None => return Ok(list),
+        }
+    }
+}
+
+fn client_new(config_dir: &Path, name: &str, codes: Option<PathBuf>) -> Result<()> {
+    onboarding::validate_name(name)?;
+    if config_dir.join(name).join("defaults.toml").exists() {
+        bail!("Cliente já existe: {}", config_dir.join(name).display());
+    }
+    let mut client = onboarding::NewClient::from_root(config_dir, name);
+
+    eprintln!("Novo cliente {name}. Enter mantém o valor entre colchetes.\n");
+    eprintln!("Claquete:");
+    client.produto = prompt("  Produto", &client.produto)?;
+    client.produtora = prompt("  Produtora", &client.produtora)?;
+    client.agencia = prompt("  Agência", &client.agencia)?;
+    client.anunciante = prompt("  Anunciante", name)?;
+    client.diretor = prompt("  Diretor", &client.diretor)?;
+    client.output = prompt("Pasta de saída (vazio = junto do vídeo)", "")?;
+
+    let use_peach = prompt("\nConfigurar envio pro Peach? (s/N)", "")?;
+    if use_peach.eq_ignore_ascii_case("s") {
+        let field = |key: &'static str| move |v: &str| check::validate_peach_field(key, v).map(|_| v.to_string());
+        let number = |v: &str| v.parse::<u32>().ok().filter(|n| *n > 0).ok_or_else(|| format!("número inválido: '{v}'"));
+        let p = onboarding::NewClientPeach {
+            avisador_id: prompt_valid("  avisador_id (ex: BRA0743)", "", field("avisador_id"))?,
+            cnpj_avisador: prompt_valid("  CNPJ do anunciante (só dígitos)", "", field("cnpj_avisador"))?,
+            id_marca: prompt_valid("  id_marca", "", number)?,
+            id_producto: prompt_valid("  id_producto", "", number)?,
+            agencia_id: prompt_valid("  agencia_id (ex: BR0741)", "", field("agencia_id"))?,
+            cnpj_agencia: prompt_valid("  CNPJ da agência (só dígitos)", "", field("cnpj_agencia"))?,
+            id_listas: prompt_valid("  id_listas (separados por vírgula, opcional)", "", |v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.parse::<u32>().map_err(|_| format!("número inválido: '{x}'")))
+                    .collect()
+            })?,
+            hd: prompt_destinos("HD")?,
+            sd: prompt_destinos("SD")?,
+        };
+        client.peach = Some(p);
+    }
+
+    let codes = match codes {
+        Some(path) => Some(path),
+        None => {
+            let path = prompt("\nPlanilha de códigos pra importar (vazio = codes.toml vazio)", "")?;
+            (!path.is_empty()).then(|| PathBuf::from(path))
+        }
+    };
+    let rows = match codes {
+        Some(path) => {
+            let result = import::read_spreadsheet(&path, &import::ImportColumns::default(), None)?;
+            if !result.errors.is_empty() {
+                for e in &result.errors {
+                    eprintln!("  - {e}");
+                }
+                bail!("Planilha com {} linha(s) inválida(s). Nada foi gravado.", result.errors.len());
+            }
+            result.rows
+        }
+        None => Vec::new(),
+    };
+
+    let (dir, warnings) = onboarding::create_client(config_dir, &client, &rows)?;
+    for w in &warnings {
+        println!("  {w}");
+    }
+    println!("✅ Cliente criado: {} ({} código(s))", dir.display(), rows.len());
+    if client.peach.is_some() {
+        println!("   Confira com: encoder config check --client {name}");
+    }
+    Ok(())
+}
+
+// ----------------- Codes -----------------
+
+fn run_codes(action: CodesAction) -> Result<()> {
+    match action {
+        CodesAction::Import {
+            file,
+            client,
+            config,
+            sheet,
+            col_code,
+            col_registro,
+            col_titulo,
+            col_duracao,
+            dry_run,
+        } => {
+            let columns = import::ImportColumns {
+                code: col_code,
+                registro: col_registro,
+                titulo: col_titulo,
+                duracao: col_duracao,
+            };
+            let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+            codes_import(&file, &config_dir, &client, &columns, sheet.as_deref(), dry_run)
+        }
+    }
+}
+
+fn codes_import(
+    file: &Path,
+    config_dir: &Path,
+    client: &str,
+    columns: &import::ImportColumns,
+    sheet: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let client_dir = config_dir.join(client);
+    if !client_dir.is_dir() {
+        bail!("Cliente não encontrado: {}", client_dir.display());
+    }
+    let codes_path = client_dir.join("codes.toml");
+
+    println!("Lendo {}...", file.display());
+    let result = import::read_spreadsheet(file, columns, sheet)?;
+
+    if !result.errors.is_empty() {
+        eprintln!("{} linha(s) inválida(s):", result.errors.len());
+        for e in &result.errors {
+            eprintln!("  - {e}");
+        }
+        bail!("Corrija a planilha e rode de novo. Nada foi gravado.");
+    }
+    if result.rows.is_empty() {
+        bail!("Nenhuma linha com código encontrada em {}", file.display());
+    }
+
+    let (existing_content, existing) = if codes_path.exists() {
+        let content = std::fs::read_to_string(&codes_path)
+            .with_context(|| format!("Não foi possível ler {}", codes_path.display()))?;
+        (content, config::load_codes_for(config_dir, Some(client))?)
+    } else {
+        (String::new(), HashMap::new())
+    };
+
+    let diff = import::diff(&existing, &result.rows);
+    println!(
+        "\n{} linha(s) lidas: {} nova(s), {} alterada(s), {} igual(is)",
+        result.rows.len(),
+        diff.added.len(),
+        diff.changed.len(),
+        diff.unchanged
+    );
+    for (code, registro) in &diff.added {
+        println!("  + {code} = \"{registro}\"");
+    }
+    for (code, old, new) in &diff.changed {
+        println!("  ~ {code} = \"{old}\" → \"{new}\"");
+    }
+
+    if diff.is_empty() {
+        println!("\nNenhuma mudança em {}.", codes_path.display());
+        return Ok(());
+    }
+    if dry_run {
+        println!("\n(--dry-run) Nada foi gravado.");
+        return Ok(());
+    }
+
+    let merged = import::merge_codes_toml(&existing_content, &result.rows)?;
+    std::fs::write(&codes_path, merged)
+        .with_context(|| format!("Falha ao gravar {}", codes_path.display()))?;
+    println!("\n✅ {} atualizado.", codes_path.display());
     Ok(())
 }
 
 // ----------------- Peach -----------------
 
-fn run_peach(action: PeachAction) -> Result<()> {
+fn run_peach(action: PeachAction, overrides: &[(String, String)], dry_run: bool, json: bool) -> Result<()> {
     let rt = tokio::runtime::Runtime::new().context("falha ao iniciar runtime tokio")?;
     rt.block_on(async {
         match action {
-            PeachAction::Login { config } => peach_login(config).await,
+            PeachAction::Login { config } => peach_login(config, json).await,
+            PeachAction::Logout { config } => peach_logout(config, json),
             PeachAction::Upload {
                 video,
                 client,
                 config,
                 codigo,
-            } => peach_upload(video, client, config, codigo).await,
+            } => peach_upload(video, client, config, overrides, codigo, dry_run, json).await,
             PeachAction::Send {
                 spots,
                 client,
                 config,
-                destinos,
-            } => peach_send(spots, client, config, destinos).await,
+                destinos,
+                mxf,
+            } => peach_send(spots, client, config, overrides, destinos, mxf, dry_run, json).await,
+            PeachAction::Lookup {
+                kind,
+                term,
+                avisador,
+                marca,
+                client,
+                config,
+                write,
+                pick,
+            } => {
+                let args = LookupArgs {
+                    kind,
+                    term,
+                    avisador,
+                    marca,
+                    client,
+                    config,
+                    write,
+                    pick,
+                };
+                peach_lookup(args, overrides, dry_run, json).await
+            }
+            PeachAction::Destinos {
+                filter,
+                listas,
+                client,
+                config,
+                hd,
+                sd,
+                add_listas,
+            } => {
+                let args = DestinosArgs {
+                    filter,
+                    listas,
+                    client,
+                    config,
+                    hd,
+                    sd,
+                    add_listas,
+                };
+                peach_destinos(args, overrides, dry_run, json).await
+            }
+            PeachAction::Status {
+                spots,
+                client,
+                config,
+                watch,
+                interval,
+                max_rounds,
+                pages,
+            } => {
+                let poll = SpotPoll {
+                    watch,
+                    interval,
+                    max_rounds,
+                    pages,
+                };
+                peach_status(spots, client, config, overrides, poll, json).await
+            }
+            PeachAction::List {
+                client,
+                config,
+                since,
+                texto,
+                watch,
+                interval,
+                max_rounds,
+                pages,
+            } => {
+                let poll = SpotPoll {
+                    watch,
+                    interval,
+                    max_rounds,
+                    pages,
+                };
+                peach_list(client, config, overrides, since, texto, poll, json).await
+            }
         }
     })
 }
 
-async fn peach_login(config: Option<PathBuf>) -> Result<()> {
+async fn peach_login(config: Option<PathBuf>, json: bool) -> Result<()> {
     let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
     let creds = peach::PeachCredentials::load(&config_dir)?;
 
-    println!("Fazendo login em latam.peachvideo.com como {}...", creds.email);
-    let client = peach::PeachClient::new()?;
+    log::emit(format!("Fazendo login em latam.peachvideo.com como {}...", creds.email));
+    let client = peach::PeachClient::for_account(&creds.email)?;
     let session = client.login(&creds.email, &creds.password).await?;
 
-    println!("\n✅ Login OK");
-    println!("  Usuário: {} <{}>", session.nombre_usuario_activo, session.id_email);
-    println!("  Empresa: {} ({})", session.empresa_nombre, session.id_empresa);
-    println!("  Privilégios: {:?}", session.privilegios);
-    println!("  Extensões permitidas: {:?}", session.extension_permitida);
+    log::emit("\n✅ Login OK");
+    log::emit(format!("  Usuário: {} <{}>", session.nombre_usuario_activo, session.id_email));
+    log::emit(format!("  Empresa: {} ({})", session.empresa_nombre, session.id_empresa));
+    log::emit(format!("  Privilégios: {:?}", session.privilegios));
+    log::emit(format!("  Extensões permitidas: {:?}", session.extension_permitida));
+    if json {
+        let report = json::LoginReport {
+            usuario: session.nombre_usuario_activo,
+            email: session.id_email,
+            empresa: session.empresa_nombre,
+            id_empresa: session.id_empresa,
+        };
+        json::print("peach.login", true, &report);
+    }
+    Ok(())
+}
+
+fn peach_logout(config: Option<PathBuf>, json: bool) -> Result<()> {
+    let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+    let creds = peach::PeachCredentials::load(&config_dir)?;
+    let removed = peach::auth::forget_session(&creds.email)?;
+    if json {
+        json::print("peach.logout", true, &serde_json::json!({ "removed": removed }));
+    } else if removed {
+        log::emit(format!("Sessão salva de {} apagada.", creds.email));
+    } else {
+        log::emit(format!("Nenhuma sessão salva para {}.", creds.email));
+    }
     Ok(())
 }
 
+/// Carrega o bloco `[peach]` do cliente (erro se não houver).
+fn load_peach_config(
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    client_name: &str,
+) -> Result<peach::PeachConfig> {
+    let defaults_full = peach::config::DefaultsWithPeach::load(config_dir, Some(client_name), overrides)?;
+    defaults_full.peach.ok_or_else(|| {
+        EncoderError::ConfigError(format!(
+            "cliente '{}' não tem bloco [peach] configurado em defaults.toml",
+            client_name
+        ))
+        .into()
+    })
+}
+
 async fn peach_upload(
     video: PathBuf,
     client_name: String,
     config: Option<PathBuf>,
+    overrides: &[(String, String)],
     codigo_override: Option<String>,
+    dry_run: bool,
+    json: bool,
 ) -> Result<()> {
+    let started = std::time::Instant::now();
     if !video.exists() {
         bail!("Arquivo não encontrado: {}", video.display());
     }
+    let _job = log::begin_job(log::job_id_for(&file_stem(&video)));
+    if !dry_run {
+        if let Err(e) = log::attach_job_file(&video.with_extension("log")) {
+            log::warn(format!("{e:#}"));
+        }
+    }
 
     let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
-    let creds = peach::PeachCredentials::load(&config_dir)?;
 
     // Carrega defaults com bloco [peach]
     let defaults_full =
-        peach::config::DefaultsWithPeach::load(&config_dir, Some(&client_name))?;
-    let peach_cfg = defaults_full.peach.ok_or_else(|| {
-        anyhow::anyhow!(
-            "Cliente '{}' não tem bloco [peach] configurado em defaults.toml",
-            client_name
-        )
-    })?;
+        peach::config::DefaultsWithPeach::load(&config_dir, Some(&client_name), overrides)?;
+    let extract = defaults_full.base.extract.clone();
+    let peach_cfg = load_peach_config(&config_dir, overrides, &client_name)?;
 
     // Carrega tabela de códigos do cliente
     let codes = config::load_codes_for(&config_dir, Some(&client_name))?;
 
-    // Probe metadata do vídeo
-    println!("Lendo metadados de {}...", video.display());
-    let meta = metadata::probe(&video)?;
-
     let filename = video
         .file_name()
         .and_then(|n| n.to_str())
@@ -429,15 +2532,90 @@ async fn peach_upload(
     // Resolve código ANCINE
     let codigo = match codigo_override {
         Some(c) => c,
-        None => peach::resolve_codigo_from_filename(filename, &codes).ok_or_else(|| {
-            anyhow::anyhow!(
-                "Não foi possível extrair código de '{}' a partir da tabela de códigos do cliente",
-                filename
-            )
-        })?,
+        None => peach::resolve_codigo_from_filename(filename, &codes, &extract)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Não foi possível extrair código de '{}' a partir da tabela de códigos do cliente",
+                    filename
+                )
+            })?,
     };
 
-    let framerate_str = format!("{:.2}", meta.fps_num as f64 / meta.fps_den as f64);
+    if dry_run {
+        let plan = upload_plan(&video)?;
+        let fields = print_init_upload(&config_dir, &peach_cfg, &video, pieza, &codigo, &plan)?;
+        if json {
+            json::print("peach.upload", true, &dry_run_report(&fields));
+        }
+        return Ok(());
+    }
+    let creds = peach::PeachCredentials::load(&config_dir)?;
+
+    // Login
+    log::emit("Fazendo login...");
+    let pclient = peach::PeachClient::for_account(&creds.email)?.with_retry(peach_cfg.retry.clone());
+    let session = pclient.connect(&creds.email, &creds.password).await?;
+    log::emit(format!("✅ Logado como {} ({})\n", session.nombre_usuario_activo, session.id_empresa));
+
+    let uploaded = upload_mxf(&pclient, &creds, &peach_cfg, &video, pieza, &codigo).await?;
+    let sts = &uploaded.sts;
+    if let Some(sid) = sts.spot_id() {
+        log::emit(format!(
+            "  spot_id: {sid}  (use em `encoder peach send {sid} --client {client_name}`)"
+        ));
+    }
+    log::emit("  Verifique no portal latam.peachvideo.com em 'Subir'.");
+    if json {
+        let report = json::UploadReport {
+            video: video.clone(),
+            pieza: pieza.to_string(),
+            codigo,
+            duration_secs: uploaded.duration_secs,
+            size: uploaded.size,
+            sha256: uploaded.sha256.clone(),
+            spot_id: sts.spot_id(),
+            id_envio: sts.id_envio.clone(),
+            destination: sts.destination.clone(),
+            elapsed_secs: started.elapsed().as_secs_f64(),
+        };
+        json::print("peach.upload", true, &report);
+    }
+
+    Ok(())
+}
+
+/// Vídeo enviado pro S3 do Peach.
+struct UploadedMxf {
+    sts: peach::StsCredentials,
+    /// Duração comercial informada no init_upload.
+    duration_secs: u64,
+    size: u64,
+    sha256: String,
+}
+
+/// Framerate e duração que o `init_upload` informa ao Peach.
+struct UploadPlan {
+    framerate: String,
+    /// Duração comercial (sem claquete).
+    duration_secs: u64,
+    /// Duração do arquivo.
+    total_secs: u64,
+}
+
+impl UploadPlan {
+    /// MXF que o encode ainda vai gerar (`--dry-run`): 29.97 fps e a duração
+    /// do vídeo de origem.
+    fn for_encode(source_secs: u64) -> Self {
+        Self {
+            framerate: "29.97".to_string(),
+            duration_secs: source_secs,
+            total_secs: source_secs + encoder::SLATE_BLACK_TOTAL_SECS,
+        }
+    }
+}
+
+fn upload_plan(video: &Path) -> Result<UploadPlan> {
+    let meta = metadata::probe(video)?;
 
     // Se for MXF gerado pelo nosso encoder, desconta a claquete (5s slate + 2s preto)
     // pra obter a duração comercial — que é o que o Peach espera no campo `segundos`.
@@ -446,171 +2624,265 @@ async fn peach_upload(
         .and_then(|e| e.to_str())
         .map(|e| e.eq_ignore_ascii_case("mxf"))
         .unwrap_or(false);
-    let commercial_secs = if is_mxf {
+    let duration_secs = if is_mxf {
         meta.duration_secs
             .saturating_sub(encoder::SLATE_BLACK_TOTAL_SECS)
     } else {
         meta.duration_secs
     };
+    Ok(UploadPlan {
+        framerate: format!("{:.2}", meta.fps_num as f64 / meta.fps_den as f64),
+        duration_secs,
+        total_secs: meta.duration_secs,
+    })
+}
+
+/// `--dry-run`: mostra os campos que o `init_upload` mandaria, sem login nem
+/// HTTP. A produtora vem das credenciais, se houver.
+fn print_init_upload(
+    config_dir: &Path,
+    peach_cfg: &peach::PeachConfig,
+    video: &Path,
+    pieza: &str,
+    codigo: &str,
+    plan: &UploadPlan,
+) -> Result<Vec<(&'static str, String)>> {
+    let productora_id = match peach::PeachCredentials::load(config_dir) {
+        Ok(creds) => creds.productora_id,
+        Err(_) => "(credenciais ausentes)".to_string(),
+    };
+    let params = peach::UploadParams {
+        video_path: video,
+        pieza,
+        codigo,
+        framerate: &plan.framerate,
+        duration_secs: plan.duration_secs,
+    };
+    let fields = peach::upload::init_upload_query(&params, peach_cfg, &productora_id)?;
+    log::emit(format!("init_upload: GET {}{}", peach::auth::BASE, peach::upload::INIT_UPLOAD_PATH));
+    for (k, v) in &fields {
+        log::emit(format!("  {k} = {v}"));
+    }
+    Ok(fields)
+}
+
+/// `--dry-run`: mostra os campos do POST que dispararia o envio.
+fn print_execute_send(req: &peach::SendRequest<'_>) -> Vec<(&'static str, String)> {
+    let fields = peach::send::execute_send_form(req);
+    log::emit("execute_send:");
+    for (k, v) in &fields {
+        log::emit(format!("  {k} = {v}"));
+    }
+    fields
+}
+
+/// Corpo do `--json` de um `peach upload|send --dry-run`.
+fn dry_run_report(fields: &[(&'static str, String)]) -> serde_json::Value {
+    let fields: serde_json::Map<String, serde_json::Value> = fields
+        .iter()
+        .map(|(k, v)| (k.to_string(), serde_json::Value::String(v.clone())))
+        .collect();
+    serde_json::json!({ "dry_run": true, "fields": fields })
+}
+
+/// init_upload + multipart S3 de um vídeo (sessão já logada). Usado pelo
+/// `peach upload` e pelo batch.
+async fn upload_mxf(
+    pclient: &peach::PeachClient,
+    creds: &peach::PeachCredentials,
+    peach_cfg: &peach::PeachConfig,
+    video: &Path,
+    pieza: &str,
+    codigo: &str,
+) -> Result<UploadedMxf> {
+    // Probe metadata do vídeo
+    log::emit(format!("Lendo metadados de {}...", video.display()));
+    let plan = upload_plan(video)?;
+    let commercial_secs = plan.duration_secs;
 
     let params = peach::UploadParams {
-        video_path: &video,
+        video_path: video,
         pieza,
-        codigo: &codigo,
-        framerate: &framerate_str,
+        codigo,
+        framerate: &plan.framerate,
         duration_secs: commercial_secs,
     };
 
-    println!("Iniciando upload no Peach:");
-    println!("  pieza:    {}", pieza);
-    println!("  codigo:   {}", codigo);
-    if is_mxf {
-        println!(
+    log::emit("Iniciando upload no Peach:");
+    log::emit(format!("  pieza:    {}", pieza));
+    log::emit(format!("  codigo:   {}", codigo));
+    if plan.total_secs != commercial_secs {
+        log::emit(format!(
             "  duração:  {}s (total {}s - {}s claquete)",
             commercial_secs,
-            meta.duration_secs,
+            plan.total_secs,
             encoder::SLATE_BLACK_TOTAL_SECS
-        );
+        ));
     } else {
-        println!("  duração:  {}s", commercial_secs);
+        log::emit(format!("  duração:  {}s", commercial_secs));
     }
-    println!("  fps:      {}", framerate_str);
-    println!("  cliente:  {}", client_name);
-
-    // Login
-    println!("\nFazendo login...");
-    let pclient = peach::PeachClient::new()?;
-    let session = pclient.login(&creds.email, &creds.password).await?;
-    println!("✅ Logado como {} ({})", session.nombre_usuario_activo, session.id_empresa);
+    log::emit(format!("  fps:      {}", plan.framerate));
 
     // Init upload (obtém STS)
-    println!("\nObtendo credenciais STS...");
+    log::emit("\nObtendo credenciais STS...");
     let sts = pclient
-        .init_upload(&params, &peach_cfg, &creds.productora_id)
+        .init_or_resume_upload(&params, peach_cfg, &creds.productora_id)
         .await?;
-    println!("✅ id_envio: {}", sts.id_envio);
-    println!("   destination: {}", sts.destination);
+    log::emit(format!("✅ id_envio: {}", sts.id_envio));
+    log::emit(format!("   destination: {}", sts.destination));
 
     // Upload S3
-    println!("\nUpload S3 multipart...");
-    let file_size = std::fs::metadata(&video)?.len();
+    log::emit("\nUpload S3 multipart...");
+    let file_size = std::fs::metadata(video)?.len();
     use std::sync::atomic::{AtomicU64, Ordering};
     use std::sync::Arc;
     let last_pct = Arc::new(AtomicU64::new(0));
     let last_pct_cb = Arc::clone(&last_pct);
 
-    peach::upload::s3_multipart_upload(&video, &sts, move |sent, total| {
+    // Com prefixo (batch paralelo) o progresso sai em linhas; sem, reescreve a mesma linha.
+    let prefix = log::prefix();
+    let report = peach::upload::s3_multipart_upload(video, &sts, &peach_cfg.upload, move |sent, total| {
         let pct = sent * 100 / total;
         let prev = last_pct_cb.load(Ordering::Relaxed);
-        if pct >= prev + 5 || sent == total {
-            print!("\r  {} / {} bytes ({}%)", sent, total, pct);
-            use std::io::Write;
-            let _ = std::io::stdout().flush();
+        if let Some(p) = &prefix {
+            if pct >= prev + 10 || sent == total {
+                let _ = writeln!(console(), "{p}  {} / {} bytes ({}%)", sent, total, pct);
+                last_pct_cb.store(pct, Ordering::Relaxed);
+            }
+        } else if pct >= prev + 5 || sent == total {
+            let mut out = console();
+            let _ = write!(out, "\r  {} / {} bytes ({}%)", sent, total, pct);
+            let _ = out.flush();
             last_pct_cb.store(pct, Ordering::Relaxed);
         }
     })
     .await?;
 
-    println!("\n\n✅ Upload concluído!");
-    println!("  Arquivo: {} ({} bytes)", video.display(), file_size);
-    if let Some(sid) = sts.spot_id() {
-        println!(
-            "  spot_id: {sid}  (use em `encoder peach send {sid} --client {client_name}`)"
-        );
+    if log::prefix().is_none() {
+        let _ = writeln!(console());
     }
-    println!("  Verifique no portal latam.peachvideo.com em 'Subir'.");
-
-    Ok(())
+    log::emit("\n✅ Upload concluído!");
+    log::emit(format!("  Arquivo: {} ({} bytes)", video.display(), file_size));
+    log::emit(format!("  Conferido: ETag {} | SHA-256 {}", report.etag, report.sha256));
+    Ok(UploadedMxf {
+        sts,
+        duration_secs: commercial_secs,
+        size: file_size,
+        sha256: report.sha256,
+    })
 }
 
-async fn peach_send(
-    spots: Vec<u64>,
-    client_name: String,
-    config: Option<PathBuf>,
-    destinos_filter: Option<Vec<String>>,
-) -> Result<()> {
-    let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
-    let creds = peach::PeachCredentials::load(&config_dir)?;
-
-    let defaults_full =
-        peach::config::DefaultsWithPeach::load(&config_dir, Some(&client_name))?;
-    let peach_cfg = defaults_full.peach.ok_or_else(|| {
-        anyhow::anyhow!(
-            "Cliente '{}' não tem bloco [peach] configurado em defaults.toml",
-            client_name
-        )
-    })?;
-
+/// Destinos HD/SD do cliente, filtrados pelos IDs de `filter` (se houver).
+fn select_destinos(
+    peach_cfg: &peach::PeachConfig,
+    filter: Option<&[String]>,
+) -> Result<(Vec<String>, Vec<String>)> {
     if peach_cfg.destinos.is_empty() {
-        bail!(
-            "Cliente '{}' não tem destinos configurados em [peach.destinos]. Adicione `hd = [...]` no defaults.toml.",
-            client_name
-        );
+        bail!("Cliente não tem destinos configurados em [peach.destinos]. Adicione `hd = [...]` no defaults.toml.");
     }
 
-    // Filtra os destinos: se --destinos foi passado, usa apenas os IDs dessa lista
-    let filter_set: Option<std::collections::HashSet<String>> = destinos_filter.map(|v| {
-        v.into_iter().map(|s| s.trim().to_string()).collect()
-    });
+    let filter_set: Option<std::collections::HashSet<&str>> =
+        filter.map(|v| v.iter().map(|s| s.trim()).collect());
+    if let Some(set) = &filter_set {
+        let all = peach_cfg.destinos.all_ids();
+        for id in set {
+            if !all.iter().any(|a| a == id) {
+                bail!("Destino {id} não está no [peach.destinos] do cliente. IDs disponíveis: {all:?}");
+            }
+        }
+    }
 
-    let select_destinos = |list: &Vec<peach::DestinoEntry>| -> Vec<String> {
+    let select = |list: &Vec<peach::DestinoEntry>| -> Vec<String> {
         list.iter()
-            .filter_map(|d| {
-                let id = d.id();
-                if let Some(set) = &filter_set {
-                    if !set.contains(id) {
-                        return None;
-                    }
-                }
-                Some(id.to_string())
-            })
+            .map(|d| d.id())
+            .filter(|id| filter_set.as_ref().map(|set| set.contains(id)).unwrap_or(true))
+            .map(str::to_string)
             .collect()
     };
-
-    let hd_ids = select_destinos(&peach_cfg.destinos.hd);
-    let sd_ids = select_destinos(&peach_cfg.destinos.sd);
+    let hd_ids = select(&peach_cfg.destinos.hd);
+    let sd_ids = select(&peach_cfg.destinos.sd);
 
     if hd_ids.is_empty() && sd_ids.is_empty() {
-        bail!("Nenhum destino selecionado após o filtro --destinos. IDs disponíveis: {:?}", peach_cfg.destinos.all_ids());
+        bail!("Nenhum destino selecionado após o filtro. IDs disponíveis: {:?}", peach_cfg.destinos.all_ids());
     }
+    Ok((hd_ids, sd_ids))
+}
 
-    println!(
+#[allow(clippy::too_many_arguments)]
+async fn peach_send(
+    spots: Vec<u64>,
+    client_name: String,
+    config: Option<PathBuf>,
+    overrides: &[(String, String)],
+    destinos_filter: Option<Vec<String>>,
+    mxf: Option<PathBuf>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let started = std::time::Instant::now();
+    let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+
+    let peach_cfg = load_peach_config(&config_dir, overrides, &client_name)?;
+    let (hd_ids, sd_ids) = select_destinos(&peach_cfg, destinos_filter.as_deref())
+        .with_context(|| format!("cliente '{client_name}'"))?;
+
+    log::emit(format!(
         "Distribuindo {} spot(s) → {} HD + {} SD destino(s):",
         spots.len(),
         hd_ids.len(),
         sd_ids.len(),
-    );
+    ));
     for sid in &spots {
-        println!("  spot_id: {sid}");
+        log::emit(format!("  spot_id: {sid}"));
     }
     for d in &peach_cfg.destinos.hd {
         if hd_ids.contains(&d.id().to_string()) {
-            println!("  HD → {} ({})", d.id(), d.label());
+            log::emit(format!("  HD → {} ({})", d.id(), d.label()));
         }
     }
     for d in &peach_cfg.destinos.sd {
         if sd_ids.contains(&d.id().to_string()) {
-            println!("  SD → {} ({})", d.id(), d.label());
+            log::emit(format!("  SD → {} ({})", d.id(), d.label()));
         }
     }
 
-    println!("\nFazendo login...");
-    let client = peach::PeachClient::new()?;
-    let session = client.login(&creds.email, &creds.password).await?;
-    println!(
-        "✅ Logado como {} ({})",
-        session.nombre_usuario_activo, session.id_empresa
-    );
-
     let req = peach::SendRequest {
         spot_ids: &spots,
         destinos_hd: &hd_ids,
         destinos_sd: &sd_ids,
     };
+    if dry_run {
+        req.check()?;
+        let fields = print_execute_send(&req);
+        if json {
+            json::print("peach.send", true, &dry_run_report(&fields));
+        }
+        return Ok(());
+    }
+    // Hash antes do envio: MXF errado/ausente falha sem distribuir nada.
+    let sha256 = match &mxf {
+        Some(_) if spots.len() != 1 => {
+            bail!(EncoderError::ConfigError("--mxf vale só para um spot por vez".into()))
+        }
+        Some(path) => {
+            log::emit(format!("Calculando SHA-256 de {}...", path.display()));
+            batch::sha256_file(path)?
+        }
+        None => String::new(),
+    };
+    let creds = peach::PeachCredentials::load(&config_dir)?;
+
+    log::emit("\nFazendo login...");
+    let client = peach::PeachClient::for_account(&creds.email)?.with_retry(peach_cfg.retry.clone());
+    let session = client.connect(&creds.email, &creds.password).await?;
+    log::emit(format!(
+        "✅ Logado como {} ({})",
+        session.nombre_usuario_activo, session.id_empresa
+    ));
 
-    println!("\nDistribuindo...");
+    log::emit("\nDistribuindo...");
     let summary = client.send_spots(&req, &peach_cfg).await?;
-    println!("\n✅ {summary}");
+    log::emit(format!("\n✅ {summary}"));
 
     // Log CSV
     let all_destinos: Vec<&str> = hd_ids.iter().chain(sd_ids.iter()).map(|s| s.as_str()).collect();
@@ -622,10 +2894,512 @@ async fn peach_send(
         destinos: all_destinos.join(";"),
         id_envio: String::new(),
         agencia_url: String::new(),
+        sha256,
     };
     let output_dir = config_dir.join(&client_name);
     let _ = peach::send::append_send_log(&output_dir, &log_entry);
 
-    println!("\nVerifique no portal latam.peachvideo.com → Reportes.");
+    log::emit("\nVerifique no portal latam.peachvideo.com → Reportes.");
+    if json {
+        let report = json::SendReport {
+            spot_ids: spots,
+            destinos_hd: hd_ids,
+            destinos_sd: sd_ids,
+            summary,
+            elapsed_secs: started.elapsed().as_secs_f64(),
+        };
+        json::print("peach.send", true, &report);
+    }
+    Ok(())
+}
+
+/// Argumentos do `peach lookup`.
+struct LookupArgs {
+    kind: LookupKind,
+    term: String,
+    avisador: Option<String>,
+    marca: Option<u32>,
+    client: Option<String>,
+    config: Option<PathBuf>,
+    write: bool,
+    pick: Option<String>,
+}
+
+async fn peach_lookup(
+    args: LookupArgs,
+    overrides: &[(String, String)],
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let config_dir = args.config.unwrap_or_else(|| PathBuf::from("config"));
+
+    let peach_table = partial_peach_table(&config_dir, overrides, args.client.as_deref())?;
+    let retry = partial_retry(&peach_table)?;
+    let avisador = args.avisador.or_else(|| {
+        peach_table
+            .get("avisador_id")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    });
+    let marca = args.marca.or_else(|| {
+        peach_table
+            .get("id_marca")
+            .and_then(|v| v.as_integer())
+            .and_then(|n| u32::try_from(n).ok())
+    });
+    if matches!(args.kind, LookupKind::Marca | LookupKind::Producto) && avisador.is_none() {
+        bail!("informe --avisador (ou -C de um cliente com avisador_id no [peach])");
+    }
+    if args.kind == LookupKind::Producto && marca.is_none() {
+        bail!("informe --marca (ou -C de um cliente com id_marca no [peach])");
+    }
+    let avisador = avisador.unwrap_or_default();
+
+    let creds = peach::PeachCredentials::load(&config_dir)?;
+    let client = peach::PeachClient::for_account(&creds.email)?.with_retry(retry);
+    client.connect(&creds.email, &creds.password).await?;
+
+    let (label, mut entries) = match args.kind {
+        LookupKind::Anunciante => ("anunciante", client.lookup_avisadores(&args.term).await?),
+        LookupKind::Agencia => ("agência", client.lookup_agencias(&args.term).await?),
+        LookupKind::Marca => ("marca", client.lookup_marcas(&avisador, &args.term).await?),
+        LookupKind::Producto => {
+            let entries = client
+                .lookup_productos(&avisador, marca.unwrap_or_default(), &args.term)
+                .await?;
+            ("produto", entries)
+        }
+    };
+    if matches!(args.kind, LookupKind::Anunciante | LookupKind::Agencia) {
+        for e in &mut entries {
+            e.cnpj = client.lookup_cnpj(&e.id).await?;
+        }
+    }
+
+    if entries.is_empty() {
+        log::emit(format!("Nenhum(a) {label} encontrado(a) para '{}'.", args.term));
+    } else {
+        log::emit(format!("{} {label}(s):", entries.len()));
+        for e in &entries {
+            match &e.cnpj {
+                Some(cnpj) => log::emit(format!("  {:<12} {:<16} {}", e.id, cnpj, e.nome)),
+                None => log::emit(format!("  {:<12} {}", e.id, e.nome)),
+            }
+        }
+    }
+
+    let mut written = None;
+    if args.write {
+        let client_name = args.client.as_deref().unwrap_or_default();
+        let chosen = match &args.pick {
+            Some(id) => entries
+                .iter()
+                .find(|e| &e.id == id)
+                .ok_or_else(|| anyhow::anyhow!("--pick {id} não está entre os resultados"))?,
+            None => match entries.as_slice() {
+                [only] => only,
+                [] => bail!("nada para gravar: a busca não trouxe resultados"),
+                _ => bail!("a busca trouxe {} resultados; escolha um com --pick <ID>", entries.len()),
+            },
+        };
+        let fields = lookup_fields(args.kind, chosen)?;
+        for (k, v) in &fields {
+            log::emit(format!("  [peach] {k} = {v}"));
+        }
+        if dry_run {
+            log::emit("(--dry-run) Nada foi gravado.");
+        } else {
+            let path = peach::config::write_peach_fields(&config_dir, client_name, &fields)?;
+            log::emit(format!("✅ {} atualizado.", path.display()));
+            written = Some(path);
+        }
+    }
+
+    if json {
+        json::print(
+            "peach.lookup",
+            true,
+            &serde_json::json!({ "entries": entries, "written": written }),
+        );
+    }
+    Ok(())
+}
+
+/// O `[peach]` do cliente como tabela solta: pode estar pela metade (o
+/// lookup e o destinos servem justamente pra preenchê-lo), então quem chama
+/// lê só as chaves que interessam.
+fn partial_peach_table(
+    config_dir: &Path,
+    overrides: &[(String, String)],
+    client: Option<&str>,
+) -> Result<toml::Table> {
+    let Some(client) = client else {
+        return Ok(toml::Table::new());
+    };
+    Ok(config::LayeredConfig::load_with(config_dir, Some(client), overrides)?
+        .table
+        .get("peach")
+        .and_then(|p| p.as_table())
+        .cloned()
+        .unwrap_or_default())
+}
+
+fn partial_retry(peach_table: &toml::Table) -> Result<peach::RetryPolicy> {
+    match peach_table.get("retry") {
+        Some(v) => v.clone().try_into().context("[peach.retry] inválido"),
+        None => Ok(peach::RetryPolicy::default()),
+    }
+}
+
+/// Argumentos do `peach destinos`.
+struct DestinosArgs {
+    filter: String,
+    listas: bool,
+    client: Option<String>,
+    config: Option<PathBuf>,
+    hd: Vec<String>,
+    sd: Vec<String>,
+    add_listas: Vec<u32>,
+}
+
+async fn peach_destinos(
+    args: DestinosArgs,
+    overrides: &[(String, String)],
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let config_dir = args.config.unwrap_or_else(|| PathBuf::from("config"));
+    let retry = partial_retry(&partial_peach_table(&config_dir, overrides, args.client.as_deref())?)?;
+
+    log::warn("peach destinos é experimental: confira no portal os IDs gravados antes do primeiro envio.");
+    let creds = peach::PeachCredentials::load(&config_dir)?;
+    let client = peach::PeachClient::for_account(&creds.email)?.with_retry(retry);
+    client.connect(&creds.email, &creds.password).await?;
+
+    let all = client.list_destinos().await?;
+    let listas = if args.listas || !args.add_listas.is_empty() {
+        client.list_destinos_listas().await?
+    } else {
+        Vec::new()
+    };
+
+    let found: Vec<&peach::DestinoEmpresa> = all.iter().filter(|d| d.matches(&args.filter)).collect();
+    log::emit(format!("{} emissora(s):", found.len()));
+    for d in &found {
+        log::emit(format!("  {:<16} {:<10} {:<20} {}", d.id, d.rede, d.cidade, d.nome));
+        for sub in &d.sub_emisoras {
+            log::emit(format!("      └ {:<12} {:<20} {}", sub.id, sub.cidade, sub.nome));
+        }
+    }
+    if args.listas {
+        log::emit(format!("\n{} lista(s) salva(s):", listas.len()));
+        for l in &listas {
+            log::emit(format!("  {:<8} {}", l.id, l.nome));
+        }
+    }
+
+    let pick = |ids: &[String]| -> Result<Vec<peach::DestinoEntry>> {
+        ids.iter()
+            .map(|id| {
+                if let Some(d) = all.iter().find(|d| &d.id == id) {
+                    return Ok(d.to_entry());
+                }
+                match all.iter().find(|d| d.sub_emisoras.iter().any(|s| &s.id == id)) {
+                    Some(parent) => bail!(EncoderError::ConfigError(format!(
+                        "{id} é sub-emisora de {}; o envio só para sub-emisora ainda não é suportado, acrescente {}",
+                        parent.label(),
+                        parent.id
+                    ))),
+                    None => bail!("{id} não é uma emissora de destino da conta"),
+                }
+            })
+            .collect()
+    };
+    let hd = pick(&args.hd)?;
+    let sd = pick(&args.sd)?;
+    for id in &args.add_listas {
+        if !listas.iter().any(|l| l.id == *id) {
+            bail!("lista {id} não está entre as listas salvas da conta");
+        }
+    }
+
+    let mut added = 0;
+    let mut written = None;
+    if let Some(client_name) = args.client.as_deref() {
+        if !hd.is_empty() || !sd.is_empty() || !args.add_listas.is_empty() {
+            for (codec, list) in [("hd", &hd), ("sd", &sd)] {
+                for d in list {
+                    log::emit(format!("  [peach.destinos] {codec} += {} ({})", d.id(), d.label()));
+                }
+            }
+            for id in &args.add_listas {
+                log::emit(format!("  [peach.destinos] id_listas += {id}"));
+            }
+            if dry_run {
+                log::emit("(--dry-run) Nada foi gravado.");
+            } else {
+                let (path, n) =
+                    peach::config::add_peach_destinos(&config_dir, client_name, &hd, &sd, &args.add_listas)?;
+                log::emit(format!("✅ {} atualizado ({n} novo(s)).", path.display()));
+                added = n;
+                written = Some(path);
+            }
+        }
+    }
+
+    if json {
+        json::print(
+            "peach.destinos",
+            true,
+            &serde_json::json!({
+                "destinos": found,
+                "listas": listas,
+                "added": added,
+                "written": written,
+            }),
+        );
+    }
+    Ok(())
+}
+
+/// Como consultar os spots no `peach status`/`peach list`.
+struct SpotPoll {
+    watch: bool,
+    interval: u64,
+    /// Consultas do `--watch` antes de desistir com erro.
+    max_rounds: u32,
+    pages: u32,
+}
+
+/// Consultas seguidas em que um spot pedido não aparece na listagem antes
+/// do `--watch` parar de esperar por ele.
+const MISSING_GIVE_UP_ROUNDS: u32 = 4;
+
+type SpotRound = (Vec<peach::SpotStatus>, Vec<String>);
+
+async fn peach_status(
+    keys: Vec<peach::SpotKey>,
+    client_name: Option<String>,
+    config: Option<PathBuf>,
+    overrides: &[(String, String)],
+    poll: SpotPoll,
+    json: bool,
+) -> Result<()> {
+    let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+    let peach_table = partial_peach_table(&config_dir, overrides, client_name.as_deref())?;
+    let filter = peach::SpotFilter {
+        id_avisador: peach_table
+            .get("avisador_id")
+            .and_then(|v| v.as_str())
+            .filter(|a| !a.is_empty())
+            .map(String::from),
+        ..peach::SpotFilter::default()
+    };
+
+    let creds = peach::PeachCredentials::load(&config_dir)?;
+    let client = peach::PeachClient::for_account(&creds.email)?.with_retry(partial_retry(&peach_table)?);
+    client.connect(&creds.email, &creds.password).await?;
+
+    let (client, keys, filter, pages) = (&client, &keys, &filter, poll.pages);
+    let (spots, missing) = watch_spots(&poll, move || async move {
+        let mut found: Vec<peach::SpotStatus> = Vec::new();
+        let mut missing = Vec::new();
+        for key in keys {
+            match client.find_spot(key, filter, pages).await? {
+                Some(s) if found.iter().any(|f| f.id_spot == s.id_spot) => {}
+                Some(s) => found.push(s),
+                None => missing.push(key.to_string()),
+            }
+        }
+        Ok((found, missing))
+    })
+    .await?;
+    if json {
+        json::print("peach.status", true, &spots_report(&spots, missing));
+    }
+    Ok(())
+}
+
+async fn peach_list(
+    client_name: String,
+    config: Option<PathBuf>,
+    overrides: &[(String, String)],
+    since: Option<chrono::NaiveDate>,
+    texto: String,
+    poll: SpotPoll,
+    json: bool,
+) -> Result<()> {
+    let config_dir = config.unwrap_or_else(|| PathBuf::from("config"));
+    let peach_table = partial_peach_table(&config_dir, overrides, Some(&client_name))?;
+    let Some(avisador) = peach_table.get("avisador_id").and_then(|v| v.as_str()).filter(|a| !a.is_empty()) else {
+        bail!(EncoderError::ConfigError(format!(
+            "cliente '{client_name}' não tem avisador_id no [peach] (veja `encoder peach lookup anunciante`)"
+        )));
+    };
+    let since = since.unwrap_or_else(|| chrono::Local::now().date_naive() - chrono::Days::new(7));
+    let filter = peach::SpotFilter {
+        fec_ini: Some(since),
+        id_avisador: Some(avisador.to_string()),
+        id_marca: peach_table
+            .get("id_marca")
+            .and_then(|v| v.as_integer())
+            .and_then(|n| u32::try_from(n).ok()),
+        texto,
+        ..peach::SpotFilter::default()
+    };
+
+    let creds = peach::PeachCredentials::load(&config_dir)?;
+    let client = peach::PeachClient::for_account(&creds.email)?.with_retry(partial_retry(&peach_table)?);
+    client.connect(&creds.email, &creds.password).await?;
+
+    log::emit(format!("Spots de {client_name} desde {}:", since.format("%d/%m/%Y")));
+    let (client, filter, pages) = (&client, &filter, poll.pages);
+    let (spots, _) = watch_spots(&poll, move || async move {
+        Ok((client.list_spots(filter, pages).await?, Vec::new()))
+    })
+    .await?;
+    if json {
+        json::print("peach.list", true, &spots_report(&spots, Vec::new()));
+    }
     Ok(())
 }
+
+/// Consulta os spots com `fetch` e, com `--watch`, repete até nenhum estar
+/// aguardando o QC. Mostra a tabela na primeira consulta e depois só o que
+/// mudou. No fim do `--watch`, spot rejeitado vira erro (`qc_rejected`), e
+/// spot ainda aguardando após `max_rounds` consultas, `validate_failed`. Spot
+/// que não aparece em [`MISSING_GIVE_UP_ROUNDS`] consultas deixa de ser
+/// esperado e só consta como não encontrado.
+async fn watch_spots<F, Fut>(poll: &SpotPoll, mut fetch: F) -> Result<SpotRound>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<SpotRound>>,
+{
+    let state = |s: &peach::SpotStatus| {
+        format!("{}|{}|{}", s.verificacion, s.verificacion_manual, s.spot_se_puede_enviar)
+    };
+    let mut last: Option<HashMap<u64, String>> = None;
+    let mut missing_rounds: HashMap<String, u32> = HashMap::new();
+    let mut round = 0;
+    loop {
+        let (spots, missing) = fetch().await?;
+        round += 1;
+        match &last {
+            None => {
+                log::emit(format!(
+                    "  {:<9} {:<30} {:<15} {:<13} {:<14} ENVIO",
+                    "SPOT_ID", "PIEZA", "CODIGO", "QC", "QC MANUAL"
+                ));
+                for s in &spots {
+                    log::emit(spot_line(s));
+                }
+                if spots.is_empty() && missing.is_empty() {
+                    log::emit("  (nenhum spot)");
+                }
+                for key in &missing {
+                    log::emit(format!("  {key}: não encontrado na listagem"));
+                }
+            }
+            Some(prev) => {
+                for s in spots.iter().filter(|s| prev.get(&s.id_spot) != Some(&state(s))) {
+                    log::emit(format!("[{}]{}", chrono::Local::now().format("%H:%M:%S"), spot_line(s)));
+                }
+            }
+        }
+        last = Some(spots.iter().map(|s| (s.id_spot, state(s))).collect());
+        missing_rounds.retain(|key, _| missing.contains(key));
+        for key in &missing {
+            let n = missing_rounds.entry(key.clone()).or_default();
+            *n += 1;
+            if poll.watch && *n == MISSING_GIVE_UP_ROUNDS {
+                log::warn(format!("{key}: não apareceu em {n} consultas; deixando de esperar."));
+            }
+        }
+
+        let waiting = spots
+            .iter()
+            .filter(|s| !s.spot_se_puede_enviar && !s.is_rejected())
+            .count()
+            + missing_rounds.values().filter(|n| **n < MISSING_GIVE_UP_ROUNDS).count();
+        let exhausted = round >= poll.max_rounds;
+        if !poll.watch || waiting == 0 || exhausted {
+            let rejected: Vec<String> = spots
+                .iter()
+                .filter(|s| s.is_rejected())
+                .map(|s| format!("{} ({})", s.id_spot, s.pieza))
+                .collect();
+            let ready = spots.iter().filter(|s| s.spot_se_puede_enviar).count();
+            log::emit(format!(
+                "\n{ready} pronto(s) para envio, {waiting} aguardando QC, {} rejeitado(s).",
+                rejected.len()
+            ));
+            if !missing.is_empty() {
+                log::emit(format!("Não encontrado(s) na listagem: {}", missing.join(", ")));
+            }
+            if poll.watch && !rejected.is_empty() {
+                bail!(EncoderError::QcRejected(format!(
+                    "spot(s) rejeitado(s) pelo QC do Peach: {}",
+                    rejected.join(", ")
+                )));
+            }
+            if poll.watch && waiting > 0 {
+                bail!(EncoderError::ValidateFailed(format!(
+                    "{waiting} spot(s) ainda aguardando o QC após {round} consultas"
+                )));
+            }
+            return Ok((spots, missing));
+        }
+        log::debug(format!("{waiting} spot(s) aguardando; nova consulta em {}s", poll.interval));
+        tokio::time::sleep(std::time::Duration::from_secs(poll.interval)).await;
+    }
+}
+
+fn spot_line(s: &peach::SpotStatus) -> String {
+    let envio = if s.spot_se_puede_enviar {
+        "sim"
+    } else if s.is_rejected() {
+        "rejeitado"
+    } else {
+        "aguardando"
+    };
+    format!(
+        "  {:<9} {:<30} {:<15} {:<13} {:<14} {envio}",
+        s.id_spot, s.pieza, s.codigo, s.verificacion, s.verificacion_manual
+    )
+}
+
+fn spots_report(spots: &[peach::SpotStatus], missing: Vec<String>) -> json::SpotsReport {
+    json::SpotsReport {
+        all_sendable: missing.is_empty() && spots.iter().all(|s| s.spot_se_puede_enviar),
+        spots: spots.iter().map(json::SpotReport::from).collect(),
+        missing,
+    }
+}
+
+/// Campos do `[peach]` que o resultado escolhido preenche.
+fn lookup_fields(
+    kind: LookupKind,
+    entry: &peach::LookupEntry,
+) -> Result<Vec<(&'static str, toml::Value)>> {
+    let numeric = || -> Result<toml::Value> {
+        let n: u32 = entry
+            .id
+            .parse()
+            .with_context(|| format!("ID não numérico: {}", entry.id))?;
+        Ok(toml::Value::from(n))
+    };
+    let company = |id_key: &'static str, cnpj_key: &'static str| {
+        let mut fields = vec![(id_key, toml::Value::from(entry.id.as_str()))];
+        match &entry.cnpj {
+            Some(cnpj) => fields.push((cnpj_key, toml::Value::from(cnpj.as_str()))),
+            None => log::warn(format!("{} sem CNPJ no Peach; {cnpj_key} não foi alterado", entry.id)),
+        }
+        fields
+    };
+    Ok(match kind {
+        LookupKind::Anunciante => company("avisador_id", "cnpj_avisador"),
+        LookupKind::Agencia => company("agencia_id", "cnpj_agencia"),
+        LookupKind::Marca => vec![("id_marca", numeric()?)],
+        LookupKind::Producto => vec![("id_producto", numeric()?)],
+    })
+}
@@ -8,7 +8,8 @@ use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 
-use encoder_lib::{config, encoder, metadata, peach, slate};
+use encoder_lib::error::{self, EncoderError};
+use encoder_lib::{check, config, encoder, import, log, metadata, onboarding, peach, slate};
 
 const MAX_LOG_LINES: usize = 500;
 
@@ -88,16 +89,139 @@ struct GuiArgs {
 // --- Messages from background thread ---
 
 enum EncoderMessage {
-    /// Linha de log pra exibir no painel
-    Log(String),
+    /// Registro de log pra exibir no painel
+    Log(log::Record),
     /// Status textual (ex: "Encodando MXF...", "Enviando para Peach...")
     Status(String),
     /// Progresso do upload (sent, total, label)
     UploadProgress(u64, u64),
     /// Concluído com sucesso
     Finished(String),
-    /// Erro
-    Error(String),
+    /// Erro, com o tipo (quando conhecido) pra reagir conforme o caso
+    Error(String, Option<EncoderError>),
+}
+
+// --- Cadastro de cliente novo ---
+
+/// Campos do diálogo "Novo cliente". Números e destinos ficam como texto
+/// até o "Criar", pra poder editar livremente.
+#[derive(Default)]
+struct NewClientForm {
+    client: onboarding::NewClient,
+    use_peach: bool,
+    peach: onboarding::NewClientPeach,
+    id_marca: String,
+    id_producto: String,
+    id_listas: String,
+    /// Um destino por linha: `ID` ou `ID = nome`.
+    hd: String,
+    sd: String,
+    codes_file: String,
+    error: Option<String>,
+}
+
+impl NewClientForm {
+    fn new(config_dir: &Path) -> Self {
+        Self {
+            client: onboarding::NewClient::from_root(config_dir, ""),
+            ..Self::default()
+        }
+    }
+
+    /// Valida os campos de texto e monta o `NewClient` + linhas do codes.toml.
+    fn build(&self) -> anyhow::Result<(onboarding::NewClient, Vec<import::CodeRow>)> {
+        let mut client = self.client.clone();
+        client.name = client.name.trim().to_string();
+        onboarding::validate_name(&client.name)?;
+        if client.anunciante.is_empty() {
+            client.anunciante = client.name.clone();
+        }
+        if self.use_peach {
+            let number = |label: &str, v: &str| {
+                v.trim()
+                    .parse::<u32>()
+                    .with_context(|| format!("{label} inválido: '{v}'"))
+            };
+            let destinos = |text: &str| -> anyhow::Result<Vec<(String, String)>> {
+                text.lines()
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .map(|l| {
+                        let (id, nome) = l.split_once('=').unwrap_or((l, ""));
+                        let id = id.trim().to_string();
+                        check::validate_peach_field("destino", &id).map_err(anyhow::Error::msg)?;
+                        Ok((id, nome.trim().to_string()))
+                    })
+                    .collect()
+            };
+            let mut p = self.peach.clone();
+            for (key, value) in [
+                ("avisador_id", &p.avisador_id),
+                ("cnpj_avisador", &p.cnpj_avisador),
+                ("agencia_id", &p.agencia_id),
+                ("cnpj_agencia", &p.cnpj_agencia),
+            ] {
+                check::validate_peach_field(key, value.trim()).map_err(anyhow::Error::msg)?;
+            }
+            p.id_marca = number("id_marca", &self.id_marca)?;
+            p.id_producto = number("id_producto", &self.id_producto)?;
+            p.id_listas = self
+                .id_listas
+                .split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(|x| number("id_listas", x))
+                .collect::<anyhow::Result<_>>()?;
+            p.hd = destinos(&self.hd)?;
+            p.sd = destinos(&self.sd)?;
+            client.peach = Some(p);
+        }
+        let rows = if self.codes_file.trim().is_empty() {
+            Vec::new()
+        } else {
+            let result = import::read_spreadsheet(
+                Path::new(self.codes_file.trim()),
+                &import::ImportColumns::default(),
+                None,
+            )?;
+            if !result.errors.is_empty() {
+                anyhow::bail!("Planilha com linhas inválidas:\n{}", result.errors.join("\n"));
+            }
+            result.rows
+        };
+        Ok((client, rows))
+    }
+}
+
+// --- Destinos do Peach ---
+
+type DestinosResult = anyhow::Result<(Vec<peach::DestinoEmpresa>, Vec<peach::DestinoLista>)>;
+
+/// Janela "Destinos do Peach": lista as emissoras e listas salvas da conta
+/// e acrescenta as marcadas ao `[peach.destinos]` do cliente atual.
+#[derive(Default)]
+struct DestinosPicker {
+    /// Cidade, rede ou nome.
+    filter: String,
+    /// Busca em andamento (thread com login + listagem).
+    rx: Option<mpsc::Receiver<DestinosResult>>,
+    empresas: Vec<peach::DestinoEmpresa>,
+    listas: Vec<peach::DestinoLista>,
+    selected: std::collections::HashSet<String>,
+    selected_listas: std::collections::HashSet<u32>,
+    error: Option<String>,
+}
+
+/// Checkbox de uma emissora (ou sub-emisora) do picker.
+fn destino_checkbox(ui: &mut egui::Ui, selected: &mut std::collections::HashSet<String>, id: &str, text: String) {
+    let mut sel = selected.contains(id);
+    if ui.checkbox(&mut sel, text).changed() {
+        if sel {
+            selected.insert(id.to_string());
+        } else {
+            selected.remove(id);
+        }
+    }
 }
 
 // --- App State ---
@@ -110,6 +234,7 @@ struct EncoderApp {
 
     // Config
     codes: HashMap<u32, String>,
+    extract_rules: config::ExtractRules,
     config_error: Option<String>,
     peach_cfg: Option<peach::PeachConfig>,
     peach_creds_error: Option<String>,
@@ -157,8 +282,15 @@ struct EncoderApp {
     rx: Option<mpsc::Receiver<EncoderMessage>>,
 
     // Log panel
-    log_lines: VecDeque<String>,
+    log_lines: VecDeque<log::Record>,
     show_log: bool,
+    /// Nível mínimo mostrado no painel.
+    log_level: log::Level,
+
+    /// Diálogo "Novo cliente" aberto.
+    new_client: Option<NewClientForm>,
+    /// Janela "Destinos do Peach" aberta.
+    destinos_picker: Option<DestinosPicker>,
 }
 
 impl EncoderApp {
@@ -191,6 +323,7 @@ impl EncoderApp {
             available_clients,
             selected_client: selected_client.clone(),
             codes: HashMap::new(),
+            extract_rules: config::ExtractRules::default(),
             config_error: None,
             peach_cfg: None,
             peach_creds_error: None,
@@ -223,6 +356,9 @@ impl EncoderApp {
             rx: None,
             log_lines: VecDeque::with_capacity(MAX_LOG_LINES),
             show_log: true,
+            log_level: log::Level::Info,
+            new_client: None,
+            destinos_picker: None,
         };
 
         app.reload_config();
@@ -234,12 +370,38 @@ impl EncoderApp {
         app
     }
 
-    /// Adiciona uma linha ao painel de log (com cap circular).
-    fn push_log(&mut self, line: String) {
+    /// Adiciona um registro ao painel de log (com cap circular).
+    fn push_record(&mut self, record: log::Record) {
         if self.log_lines.len() >= MAX_LOG_LINES {
             self.log_lines.pop_front();
         }
-        self.log_lines.push_back(line);
+        self.log_lines.push_back(record);
+    }
+
+    /// Linha da própria GUI (status, resultado) no painel de log.
+    fn push_log(&mut self, level: log::Level, message: String) {
+        self.push_record(log::Record {
+            level,
+            time: chrono::Local::now(),
+            job: None,
+            message,
+        });
+    }
+
+    /// Efeitos de um erro além da mensagem: problemas de ambiente/configuração
+    /// viram banner fixo, e login recusado desabilita o Peach até recarregar
+    /// o cliente (evita repetir o login errado a cada envio).
+    fn react_to_error(&mut self, kind: Option<&EncoderError>) {
+        match kind {
+            Some(e @ EncoderError::PeachAuth(_)) => self.peach_creds_error = Some(e.to_string()),
+            Some(
+                e @ (EncoderError::FfmpegNotFound
+                | EncoderError::TemplateNotFound(_)
+                | EncoderError::ConfigError(_)
+                | EncoderError::CodeNotFound(_)),
+            ) => self.config_error = Some(format!("{e}. {}", e.hint())),
+            _ => {}
+        }
     }
 
     /// Persiste o state atual em disco.
@@ -306,11 +468,13 @@ impl EncoderApp {
 
         let mut defaults: Option<config::Defaults> = None;
         self.codes = HashMap::new();
+        self.extract_rules = config::ExtractRules::default();
 
         // Carrega defaults com bloco [peach] opcional
-        match peach::config::DefaultsWithPeach::load(&self.config_dir, client_ref) {
+        match peach::config::DefaultsWithPeach::load(&self.config_dir, client_ref, &[]) {
             Ok(d) => {
                 self.peach_cfg = d.peach.clone();
+                self.extract_rules = d.base.extract.clone();
                 defaults = Some(d.base);
             }
             Err(e) => self.config_error = Some(format!("Erro ao carregar defaults.toml: {e}")),
@@ -403,9 +567,9 @@ impl EncoderApp {
         if filename.is_empty() {
             return;
         }
-        let code = config::extract_code_from_filename(filename);
+        let code = self.extract_rules.extract(filename).code;
         match code {
-            Some(c) => match config::lookup_registro(c, &self.codes) {
+            Some(c) => match self.extract_rules.lookup_registro(c, &self.codes) {
                 Some(reg) => self.registro = reg,
                 None => {
                     self.registro.clear();
@@ -451,33 +615,17 @@ impl EncoderApp {
                     .file_stem()
                     .and_then(|s| s.to_str())
                     .unwrap_or(filename);
-                self.titulo = stem.to_string();
+                let info = self.extract_rules.extract(filename);
+                self.titulo = info.titulo.unwrap_or_else(|| stem.to_string());
                 self.duracao = meta.duration_display();
 
-                // Resolve registro from codes table
-                let code = config::extract_code_from_filename(filename);
-                match code {
-                    Some(c) => match config::lookup_registro(c, &self.codes) {
-                        Some(reg) => self.registro = reg,
-                        None => {
-                            self.registro.clear();
-                            self.registro_warning = Some(format!(
-                                "Código {c} não encontrado na tabela de registros"
-                            ));
-                        }
-                    },
-                    None => {
-                        self.registro.clear();
-                        self.registro_warning = Some(
-                            "Não foi possível extrair código do nome do arquivo".to_string(),
-                        );
-                    }
-                }
-
                 self.video_meta = Some(meta);
             }
             Err(e) => {
-                self.probe_error = Some(format!("Erro ao ler metadados: {e}"));
+                self.probe_error = Some(match error::find(&e) {
+                    Some(kind) => format!("Erro ao ler metadados: {e}\n{}", kind.hint()),
+                    None => format!("Erro ao ler metadados: {e}"),
+                });
                 self.video_meta = None;
             }
         }
@@ -493,6 +641,11 @@ impl EncoderApp {
         }
 
         self.video_path = Some(path);
+
+        // Resolve registro from codes table
+        if self.video_meta.is_some() {
+            self.resolve_current_registro();
+        }
     }
 
     /// Inicia encoding (e opcionalmente upload pro Peach depois).
@@ -564,6 +717,7 @@ impl EncoderApp {
         std::thread::spawn(move || {
             // Configura o log thread-local desta worker thread
             encoder_lib::log::set_sender(Some(log_tx));
+            let _job = log::begin_job(log::job_id_for(&titulo));
 
             let result = run_encode(
                 &video_path,
@@ -615,10 +769,13 @@ impl EncoderApp {
                             Err(e) => {
                                 // Erro no upload/distribuição: MANTÉM o MXF
                                 // pra você poder tentar enviar de novo depois sem re-encodar.
-                                let _ = tx_thread.send(EncoderMessage::Error(format!(
-                                    "Encoding OK, mas upload falhou: {e}\nMXF preservado em {} pra retry.",
-                                    mxf_path.display()
-                                )));
+                                let _ = tx_thread.send(EncoderMessage::Error(
+                                    format!(
+                                        "Encoding OK, mas upload falhou: {e}\nMXF preservado em {} pra retry.",
+                                        mxf_path.display()
+                                    ),
+                                    error::find(&e).cloned(),
+                                ));
                             }
                         }
                     } else {
@@ -647,7 +804,7 @@ impl EncoderApp {
                     if !keep_mxf && mxf_path.exists() {
                         cleanup_mxf(&mxf_path);
                     }
-                    let _ = tx_thread.send(EncoderMessage::Error(format!("{e}")));
+                    let _ = tx_thread.send(EncoderMessage::Error(format!("{e}"), error::find(&e).cloned()));
                 }
             }
             ctx.request_repaint();
@@ -701,6 +858,10 @@ impl EncoderApp {
 
         std::thread::spawn(move || {
             encoder_lib::log::set_sender(Some(log_tx));
+            let _job = log::begin_job(log::job_id_for(&titulo));
+            if let Err(e) = log::attach_job_file(&mxf_path.with_extension("log")) {
+                log::warn(format!("{e:#}"));
+            }
 
             let result = run_upload(&mxf_path, &titulo, &uctx, tx_thread.clone(), &ctx);
 
@@ -718,10 +879,10 @@ impl EncoderApp {
                 }
                 Err(e) => {
                     // Mantém o MXF em caso de erro (você pode tentar enviar de novo)
-                    let _ = tx_thread.send(EncoderMessage::Error(format!(
-                        "{e}\nMXF preservado em {} pra retry.",
-                        mxf_path.display()
-                    )));
+                    let _ = tx_thread.send(EncoderMessage::Error(
+                        format!("{e}\nMXF preservado em {} pra retry.", mxf_path.display()),
+                        error::find(&e).cloned(),
+                    ));
                 }
             }
             ctx.request_repaint();
@@ -733,7 +894,8 @@ impl EncoderApp {
         let cfg = self.peach_cfg.clone()?;
         let creds = peach::PeachCredentials::load(&self.config_dir).ok()?;
         let filename = self.video_path.as_ref()?.file_name()?.to_str()?.to_string();
-        let codigo = peach::resolve_codigo_from_filename(&filename, &self.codes)?;
+        let codigo =
+            peach::resolve_codigo_from_filename(&filename, &self.codes, &self.extract_rules)?;
 
         // Filtra destinos do cfg pelos selecionados na GUI (apenas IDs)
         let destinos_hd: Vec<String> = cfg
@@ -797,11 +959,11 @@ fn cleanup_mxf(mxf_path: &Path) {
 fn make_log_forwarder(
     tx: mpsc::Sender<EncoderMessage>,
     ctx: egui::Context,
-) -> mpsc::Sender<String> {
-    let (log_tx, log_rx) = mpsc::channel::<String>();
+) -> mpsc::Sender<log::Record> {
+    let (log_tx, log_rx) = mpsc::channel::<log::Record>();
     std::thread::spawn(move || {
-        while let Ok(line) = log_rx.recv() {
-            if tx.send(EncoderMessage::Log(line)).is_err() {
+        while let Ok(record) = log_rx.recv() {
+            if tx.send(EncoderMessage::Log(record)).is_err() {
                 break;
             }
             ctx.request_repaint();
@@ -851,10 +1013,9 @@ fn run_upload(
         let _ = tx.send(EncoderMessage::Status("Logando no Peach...".into()));
         ctx.request_repaint();
 
-        let client = peach::PeachClient::new()?;
-        let session = client
-            .login(&uctx.creds.email, &uctx.creds.password)
-            .await?;
+        let client =
+            peach::PeachClient::for_account(&uctx.creds.email)?.with_retry(uctx.cfg.retry.clone());
+        let session = client.connect(&uctx.creds.email, &uctx.creds.password).await?;
         encoder_lib::log::emit(format!(
             "[peach] Login OK: {} ({})",
             session.nombre_usuario_activo, session.id_empresa
@@ -876,7 +1037,7 @@ fn run_upload(
         ctx.request_repaint();
 
         let sts = client
-            .init_upload(&params, &uctx.cfg, &uctx.creds.productora_id)
+            .init_or_resume_upload(&params, &uctx.cfg, &uctx.creds.productora_id)
             .await?;
         encoder_lib::log::emit(format!(
             "[peach] STS OK: id_envio={} bucket={} key={}",
@@ -892,7 +1053,7 @@ fn run_upload(
         encoder_lib::log::emit("[peach] Iniciando S3 multipart upload...");
         let tx_progress = tx.clone();
         let ctx_progress = ctx.clone();
-        peach::upload::s3_multipart_upload(mxf_path, &sts, move |sent, total| {
+        let report = peach::upload::s3_multipart_upload(mxf_path, &sts, &uctx.cfg.upload, move |sent, total| {
             let pct = sent * 100 / total;
             encoder_lib::log::emit(format!("[peach] {}/{} bytes ({}%)", sent, total, pct));
             let _ = tx_progress.send(EncoderMessage::UploadProgress(sent, total));
@@ -901,8 +1062,8 @@ fn run_upload(
         .await?;
 
         encoder_lib::log::emit(format!(
-            "[peach] Upload concluído. id_envio={}",
-            sts.id_envio
+            "[peach] Upload concluído e conferido. id_envio={} sha256={}",
+            sts.id_envio, report.sha256
         ));
 
         let mut summary = format!("Upload Peach OK | id_envio={}", sts.id_envio);
@@ -1014,10 +1175,11 @@ fn run_upload(
                 destinos: destinos_labels.join("; "),
                 id_envio: sts.id_envio.clone(),
                 agencia_url: agencia_url.clone(),
+                sha256: report.sha256.clone(),
             };
             let output_dir = mxf_path.parent().unwrap_or(std::path::Path::new("."));
             if let Err(e) = peach::send::append_send_log(output_dir, &log_entry) {
-                encoder_lib::log::emit(format!("[peach] Aviso: falha ao gravar log CSV: {e}"));
+                encoder_lib::log::warn(format!("[peach] falha ao gravar log CSV: {e}"));
             }
 
             let client_name = uctx.cfg.avisador_id.clone();
@@ -1065,7 +1227,7 @@ async fn await_drive(
             result.url
         }
         Ok(Err(e)) => {
-            encoder_lib::log::emit(format!("[drive] Upload falhou: {e}"));
+            encoder_lib::log::warn(format!("[drive] Upload falhou: {e}"));
             String::new()
         }
         Err(e) => {
@@ -1075,6 +1237,7 @@ async fn await_drive(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_encode(
     video_path: &Path,
     meta: &metadata::VideoMetadata,
@@ -1093,6 +1256,9 @@ fn run_encode(
 ) -> anyhow::Result<String> {
     // Create output dir
     std::fs::create_dir_all(output_dir)?;
+    if let Err(e) = log::attach_job_file(&output_dir.join(format!("{titulo}.log"))) {
+        log::warn(format!("{e:#}"));
+    }
 
     let mut results = Vec::new();
 
@@ -1113,6 +1279,7 @@ fn run_encode(
             anunciante: anunciante.to_string(),
             diretor: diretor.to_string(),
             output: String::new(),
+            extract: config::ExtractRules::default(),
         };
 
         let slate_data = slate::SlateData::new(titulo, duracao, registro, data, &defaults);
@@ -1152,27 +1319,31 @@ impl eframe::App for EncoderApp {
             };
             for msg in msgs {
                 match msg {
-                    EncoderMessage::Log(line) => {
-                        self.push_log(line);
+                    EncoderMessage::Log(record) => {
+                        self.push_record(record);
                     }
                     EncoderMessage::Status(s) => {
-                        self.push_log(format!("[status] {s}"));
+                        self.push_log(log::Level::Info, format!("[status] {s}"));
                         self.status_text = s;
                     }
                     EncoderMessage::UploadProgress(sent, total) => {
                         self.upload_progress = Some((sent, total));
                     }
                     EncoderMessage::Finished(path) => {
-                        self.push_log(format!("[OK] {}", path.replace('\n', " | ")));
+                        self.push_log(log::Level::Info, format!("[OK] {}", path.replace('\n', " | ")));
                         self.result_message = Some((true, format!("Concluído:\n{path}")));
                         self.encoding = false;
                         self.upload_progress = None;
                         self.status_text.clear();
                         self.rx = None;
                     }
-                    EncoderMessage::Error(err) => {
-                        self.push_log(format!("[ERRO] {err}"));
-                        self.result_message = Some((false, format!("Erro: {err}")));
+                    EncoderMessage::Error(err, kind) => {
+                        self.push_log(log::Level::Error, err.clone());
+                        self.result_message = Some((false, match &kind {
+                            Some(k) => format!("Erro: {err}\n{}", k.hint()),
+                            None => format!("Erro: {err}"),
+                        }));
+                        self.react_to_error(kind.as_ref());
                         self.encoding = false;
                         self.upload_progress = None;
                         self.status_text.clear();
@@ -1186,9 +1357,9 @@ impl eframe::App for EncoderApp {
             ui.heading("Encoder - Claquete + MXF XDCAM HD422");
             ui.add_space(8.0);
 
-            // --- Client selector (only if clients exist) ---
-            if !self.available_clients.is_empty() {
-                ui.horizontal(|ui| {
+            // --- Client selector (combo only if clients exist) ---
+            ui.horizontal(|ui| {
+                if !self.available_clients.is_empty() {
                     ui.label("Cliente:");
                     let current_label = self
                         .selected_client
@@ -1221,9 +1392,15 @@ impl eframe::App for EncoderApp {
                                 self.save_state();
                             }
                         });
-                });
-                ui.add_space(4.0);
-            }
+                }
+                if ui
+                    .add_enabled(!self.encoding, egui::Button::new("Novo cliente…"))
+                    .clicked()
+                {
+                    self.new_client = Some(NewClientForm::new(&self.config_dir));
+                }
+            });
+            ui.add_space(4.0);
 
             // Config error banner
             if let Some(err) = &self.config_error {
@@ -1382,6 +1559,15 @@ impl eframe::App for EncoderApp {
                 .on_disabled_hover_text(
                     "Cliente atual não tem [peach.destinos] configurado em defaults.toml.",
                 );
+                let can_pick = self.selected_client.is_some() && self.peach_creds_error.is_none();
+                if ui
+                    .add_enabled(can_pick, egui::Button::new("Destinos…").small())
+                    .on_hover_text("Buscar as emissoras da conta no Peach e acrescentar ao cliente (experimental).")
+                    .on_disabled_hover_text("Escolha um cliente e configure as credenciais do Peach.")
+                    .clicked()
+                {
+                    self.open_destinos_picker(ctx);
+                }
 
                 let drive_ready = self
                     .peach_cfg
@@ -1563,6 +1749,14 @@ impl eframe::App for EncoderApp {
                     self.log_lines.clear();
                 }
                 ui.label(format!("({} linhas)", self.log_lines.len()));
+                ui.label("Nível:");
+                egui::ComboBox::from_id_salt("log_level")
+                    .selected_text(self.log_level.as_str())
+                    .show_ui(ui, |ui| {
+                        for level in log::Level::ALL {
+                            ui.selectable_value(&mut self.log_level, level, level.as_str());
+                        }
+                    });
             });
 
             if self.show_log {
@@ -1574,7 +1768,8 @@ impl eframe::App for EncoderApp {
                         let text = self
                             .log_lines
                             .iter()
-                            .cloned()
+                            .filter(|r| r.level >= self.log_level)
+                            .map(log::Record::short)
                             .collect::<Vec<_>>()
                             .join("\n");
                         ui.add(
@@ -1586,6 +1781,309 @@ impl eframe::App for EncoderApp {
                     });
             }
         });
+
+        self.show_new_client_dialog(ctx);
+        self.show_destinos_picker(ctx);
+    }
+}
+
+impl EncoderApp {
+    /// Janela "Novo cliente": gera config/<NOME>/ e já seleciona o cliente.
+    fn show_new_client_dialog(&mut self, ctx: &egui::Context) {
+        let Some(form) = self.new_client.as_mut() else {
+            return;
+        };
+        let mut open = true;
+        let mut create = false;
+        let mut cancel = false;
+        egui::Window::new("Novo cliente")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().max_height(600.0).show(ui, |ui| {
+                    egui::Grid::new("new_client_grid").num_columns(2).show(ui, |ui| {
+                        let c = &mut form.client;
+                        for (label, value) in [
+                            ("Nome (pasta):", &mut c.name),
+                            ("Produto:", &mut c.produto),
+                            ("Produtora:", &mut c.produtora),
+                            ("Agência:", &mut c.agencia),
+                            ("Anunciante:", &mut c.anunciante),
+                            ("Diretor:", &mut c.diretor),
+                        ] {
+                            ui.label(label);
+                            ui.text_edit_singleline(value);
+                            ui.end_row();
+                        }
+                        ui.label("Pasta de saída:");
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut c.output);
+                            if ui.button("…").clicked() {
+                                if let Some(dir) = rfd::FileDialog::new().pick_folder() {
+                                    c.output = dir.display().to_string();
+                                }
+                            }
+                        });
+                        ui.end_row();
+                        ui.label("Planilha de códigos:");
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut form.codes_file)
+                                .on_hover_text("CSV/XLSX da agência. Vazio = codes.toml vazio");
+                            if ui.button("…").clicked() {
+                                if let Some(f) = rfd::FileDialog::new()
+                                    .add_filter("Planilha", &["csv", "xlsx", "xls", "ods"])
+                                    .pick_file()
+                                {
+                                    form.codes_file = f.display().to_string();
+                                }
+                            }
+                        });
+                        ui.end_row();
+                    });
+
+                    ui.separator();
+                    ui.checkbox(&mut form.use_peach, "Configurar envio pro Peach");
+                    if form.use_peach {
+                        egui::Grid::new("new_client_peach_grid").num_columns(2).show(ui, |ui| {
+                            let p = &mut form.peach;
+                            for (label, value) in [
+                                ("avisador_id:", &mut p.avisador_id),
+                                ("CNPJ anunciante:", &mut p.cnpj_avisador),
+                                ("id_marca:", &mut form.id_marca),
+                                ("id_producto:", &mut form.id_producto),
+                                ("agencia_id:", &mut p.agencia_id),
+                                ("CNPJ agência:", &mut p.cnpj_agencia),
+                                ("id_listas:", &mut form.id_listas),
+                            ] {
+                                ui.label(label);
+                                ui.text_edit_singleline(value);
+                                ui.end_row();
+                            }
+                        });
+                        ui.label("Destinos HD (um por linha: ID ou ID = nome):");
+                        ui.add(egui::TextEdit::multiline(&mut form.hd).desired_rows(3).desired_width(f32::INFINITY));
+                        ui.label("Destinos SD:");
+                        ui.add(egui::TextEdit::multiline(&mut form.sd).desired_rows(2).desired_width(f32::INFINITY));
+                    }
+
+                    if let Some(err) = &form.error {
+                        ui.colored_label(egui::Color32::RED, err);
+                    }
+                    ui.horizontal(|ui| {
+                        create = ui.button("Criar").clicked();
+                        cancel = ui.button("Cancelar").clicked();
+                    });
+                });
+            });
+
+        if create {
+            let result = form
+                .build()
+                .and_then(|(client, rows)| onboarding::create_client(&self.config_dir, &client, &rows).map(|r| (client.name, r)));
+            match result {
+                Ok((name, (dir, warnings))) => {
+                    self.push_log(log::Level::Info, format!("[config] Cliente criado em {}", dir.display()));
+                    for w in warnings {
+                        self.push_log(log::Level::Warn, format!("[config] {w}"));
+                    }
+                    self.new_client = None;
+                    self.available_clients = config::list_clients(&self.config_dir);
+                    self.selected_client = Some(name);
+                    self.reload_config();
+                    self.save_state();
+                }
+                Err(e) => form.error = Some(format!("{e:#}")),
+            }
+        } else if cancel || !open {
+            self.new_client = None;
+        }
+    }
+}
+
+impl EncoderApp {
+    fn open_destinos_picker(&mut self, ctx: &egui::Context) {
+        self.destinos_picker = Some(DestinosPicker {
+            rx: Some(self.fetch_destinos(ctx)),
+            ..DestinosPicker::default()
+        });
+    }
+
+    /// Faz login e lista emissoras + listas salvas numa thread separada.
+    fn fetch_destinos(&self, ctx: &egui::Context) -> mpsc::Receiver<DestinosResult> {
+        let (tx, rx) = mpsc::channel();
+        let config_dir = self.config_dir.clone();
+        let retry = self
+            .peach_cfg
+            .as_ref()
+            .map(|c| c.retry.clone())
+            .unwrap_or_default();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let result = (|| -> DestinosResult {
+                let creds = peach::PeachCredentials::load(&config_dir)?;
+                let rt = tokio::runtime::Runtime::new()?;
+                rt.block_on(async {
+                    let client = peach::PeachClient::for_account(&creds.email)?.with_retry(retry);
+                    client.connect(&creds.email, &creds.password).await?;
+                    Ok((client.list_destinos().await?, client.list_destinos_listas().await?))
+                })
+            })();
+            let _ = tx.send(result);
+            ctx.request_repaint();
+        });
+        rx
+    }
+
+    fn show_destinos_picker(&mut self, ctx: &egui::Context) {
+        let Some(picker) = self.destinos_picker.as_mut() else {
+            return;
+        };
+        if let Some(result) = picker.rx.as_ref().and_then(|rx| rx.try_recv().ok()) {
+            match result {
+                Ok((empresas, listas)) => {
+                    picker.empresas = empresas;
+                    picker.listas = listas;
+                }
+                Err(e) => picker.error = Some(format!("{e:#}")),
+            }
+            picker.rx = None;
+        }
+
+        let client = self.selected_client.clone().unwrap_or_default();
+        let mut open = true;
+        let mut refresh = false;
+        let mut add_hd = None;
+        egui::Window::new(format!("Destinos do Peach (experimental) — {client}"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .default_width(560.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Buscar:");
+                    ui.text_edit_singleline(&mut picker.filter)
+                        .on_hover_text("Cidade, rede ou nome da emissora");
+                    if picker.rx.is_some() {
+                        ui.spinner();
+                    } else if ui.button("Atualizar").clicked() {
+                        refresh = true;
+                    }
+                });
+                ui.weak("Experimental: confira no portal os IDs gravados antes do primeiro envio.");
+                if let Some(err) = &picker.error {
+                    ui.colored_label(egui::Color32::RED, err);
+                }
+                egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    let selected = &mut picker.selected;
+                    for d in picker.empresas.iter().filter(|d| d.matches(&picker.filter)) {
+                        let text = if d.rede.is_empty() {
+                            format!("{}  ({})", d.label(), d.id)
+                        } else {
+                            format!("{}  ({}, {})", d.label(), d.id, d.rede)
+                        };
+                        destino_checkbox(ui, selected, &d.id, text);
+                        // Sub-emisora só aparece: o envio direto pra ela não foi conferido.
+                        for sub in &d.sub_emisoras {
+                            ui.indent(&sub.id, |ui| {
+                                ui.add_enabled(false, egui::Checkbox::new(&mut false, format!("└ {}  ({})", sub.label(), sub.id)))
+                                    .on_disabled_hover_text(format!("Sub-emisora de {}: adicione a emissora mãe.", d.label()));
+                            });
+                        }
+                    }
+                });
+                if !picker.listas.is_empty() {
+                    ui.collapsing("Listas salvas (id_listas)", |ui| {
+                        for l in &picker.listas {
+                            let mut sel = picker.selected_listas.contains(&l.id);
+                            if ui.checkbox(&mut sel, format!("{}  ({})", l.nome, l.id)).changed() {
+                                if sel {
+                                    picker.selected_listas.insert(l.id);
+                                } else {
+                                    picker.selected_listas.remove(&l.id);
+                                }
+                            }
+                        }
+                    });
+                }
+                ui.separator();
+                let any = !picker.selected.is_empty() || !picker.selected_listas.is_empty();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(any, egui::Button::new("Adicionar em HD")).clicked() {
+                        add_hd = Some(true);
+                    }
+                    if ui.add_enabled(any, egui::Button::new("Adicionar em SD")).clicked() {
+                        add_hd = Some(false);
+                    }
+                });
+            });
+
+        if refresh {
+            let rx = self.fetch_destinos(ctx);
+            if let Some(picker) = self.destinos_picker.as_mut() {
+                picker.rx = Some(rx);
+                picker.error = None;
+            }
+        }
+        if let Some(hd) = add_hd {
+            self.add_picked_destinos(hd);
+        }
+        if !open {
+            self.destinos_picker = None;
+        }
+    }
+
+    /// Grava as emissoras/listas marcadas no `[peach.destinos]` do cliente e
+    /// já deixa as novas marcadas pra distribuição.
+    fn add_picked_destinos(&mut self, hd: bool) {
+        let (Some(client), Some(picker)) = (self.selected_client.clone(), self.destinos_picker.as_ref()) else {
+            return;
+        };
+        let entries: Vec<peach::DestinoEntry> = picker
+            .empresas
+            .iter()
+            .filter(|d| picker.selected.contains(&d.id))
+            .map(|d| d.to_entry())
+            .collect();
+        let listas: Vec<u32> = picker
+            .listas
+            .iter()
+            .map(|l| l.id)
+            .filter(|id| picker.selected_listas.contains(id))
+            .collect();
+        let (hd_list, sd_list) = if hd {
+            (entries.as_slice(), &[][..])
+        } else {
+            (&[][..], entries.as_slice())
+        };
+        let result = peach::config::add_peach_destinos(&self.config_dir, &client, hd_list, sd_list, &listas);
+        match result {
+            Ok((path, added)) => {
+                self.push_log(
+                    log::Level::Info,
+                    format!(
+                        "[config] {added} destino(s) acrescentado(s) em {} ({})",
+                        path.display(),
+                        if hd { "HD" } else { "SD" }
+                    ),
+                );
+                self.reload_config();
+                for d in &entries {
+                    self.selected_destinos.insert(d.id().to_string());
+                }
+                self.save_state();
+                if let Some(picker) = self.destinos_picker.as_mut() {
+                    picker.selected.clear();
+                    picker.selected_listas.clear();
+                }
+            }
+            Err(e) => {
+                if let Some(picker) = self.destinos_picker.as_mut() {
+                    picker.error = Some(format!("{e:#}"));
+                }
+            }
+        }
     }
 }
 
@@ -1610,6 +2108,10 @@ fn find_config_dir() -> PathBuf {
 }
 
 fn main() -> eframe::Result {
+    if let Err(e) = log::init_app_log(&log::app_log_dir()) {
+        eprintln!("Aviso: {e:#}");
+    }
+
     // Check ffmpeg upfront
     if let Err(e) = metadata::check_ffmpeg() {
         eprintln!("Aviso: {e}");
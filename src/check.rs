@@ -0,0 +1,691 @@
+//! Validação da configuração (`encoder config check`).
+//!
+//! Roda antes de qualquer job pra pegar erros que hoje só aparecem no meio do
+//! envio (depois do login): campos obrigatórios do `[peach]`, CNPJ com dígito
+//! verificador errado, IDs fora do formato, códigos duplicados, chaves com
+//! typo, pasta de saída sem permissão, template e credenciais ausentes.
+//!
+//! Cada problema vira um `Diagnostic` com `arquivo:linha` quando possível.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, LayeredConfig};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Um problema encontrado na configuração.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "erro",
+            Severity::Warning => "aviso",
+        };
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{line}: {level}: {}", file.display(), self.message),
+            (Some(file), None) => write!(f, "{}: {level}: {}", file.display(), self.message),
+            _ => write!(f, "{level}: {}", self.message),
+        }
+    }
+}
+
+/// Diagnósticos acumulados de uma verificação.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn errors(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+    pub fn warnings(&self) -> usize {
+        self.diagnostics.len() - self.errors()
+    }
+
+    fn push(&mut self, severity: Severity, file: Option<&Path>, line: Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: file.map(|p| p.to_path_buf()),
+            line,
+            message,
+        });
+    }
+    fn error(&mut self, file: Option<&Path>, line: Option<usize>, message: impl Into<String>) {
+        self.push(Severity::Error, file, line, message.into());
+    }
+    fn warn(&mut self, file: Option<&Path>, line: Option<usize>, message: impl Into<String>) {
+        self.push(Severity::Warning, file, line, message.into());
+    }
+}
+
+// ----------------- Chaves conhecidas -----------------
+
+/// Chaves aceitas em cada tabela do `defaults.toml`, tiradas dos próprios
+/// structs: um campo novo (ex: `[peach.retry]`) não vira "chave
+/// desconhecida". `None` pra tabelas de chave livre (ex: `extract.aliases`).
+fn known_keys(table: &str) -> Option<Vec<&'static str>> {
+    use crate::peach::{PeachConfig, PeachDestinos, RetryPolicy, UploadOptions};
+    let fields = match table {
+        // `[peach]` entra pelo `DefaultsWithPeach` (flatten), fora do `Defaults`
+        "" => return Some([struct_fields::<config::Defaults>(), &["peach"]].concat()),
+        "extract" => struct_fields::<config::ExtractRules>(),
+        "extract.offset" => struct_fields::<config::CodeOffset>(),
+        "peach" => struct_fields::<PeachConfig>(),
+        "peach.destinos" => struct_fields::<PeachDestinos>(),
+        "peach.retry" => struct_fields::<RetryPolicy>(),
+        "peach.upload" => struct_fields::<UploadOptions>(),
+        _ => return None,
+    };
+    Some(fields.to_vec())
+}
+
+/// Nomes dos campos que o `Deserialize` derivado de `T` aceita. Só vale pra
+/// struct com campos nomeados (sem `flatten`, que vira mapa).
+fn struct_fields<T: serde::de::DeserializeOwned>() -> &'static [&'static str] {
+    use serde::de::{Error, Visitor};
+
+    struct Fields<'a>(&'a mut &'static [&'static str]);
+    impl<'de> serde::Deserializer<'de> for Fields<'_> {
+        type Error = serde::de::value::Error;
+        fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+            Err(Error::custom("não é struct"))
+        }
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = fields;
+            Err(Error::custom("só os campos"))
+        }
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map enum identifier ignored_any
+        }
+    }
+
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(Fields(&mut fields));
+    fields
+}
+
+const DESTINO_ENTRY_KEYS: &[&str] = &["id", "nome"];
+
+const REQUIRED_TOP: &[&str] = &["produto", "produtora", "agencia", "anunciante", "diretor"];
+const REQUIRED_PEACH: &[&str] = &[
+    "avisador_id",
+    "cnpj_avisador",
+    "id_marca",
+    "id_producto",
+    "agencia_id",
+    "cnpj_agencia",
+];
+
+/// Valida a configuração de um cliente (ou da raiz, se `client` for `None`).
+pub fn check_client(config_dir: &Path, client: Option<&str>, report: &mut Report) {
+    let client_dir = match client {
+        Some(name) => config_dir.join(name),
+        None => config_dir.to_path_buf(),
+    };
+    let defaults_path = client_dir.join("defaults.toml");
+
+    // 1. Cada camada isoladamente: sintaxe + chaves desconhecidas
+    let layer_files: Vec<PathBuf> = config::layer_dirs(config_dir, client)
+        .iter()
+        .map(|d| d.join("defaults.toml"))
+        .collect();
+    let mut syntax_ok = true;
+    for file in &layer_files {
+        if !file.exists() {
+            if *file == defaults_path {
+                report.error(Some(file), None, "arquivo não encontrado");
+                syntax_ok = false;
+            }
+            continue;
+        }
+        let content = match std::fs::read_to_string(file) {
+            Ok(c) => c,
+            Err(e) => {
+                report.error(Some(file), None, format!("não foi possível ler: {e}"));
+                syntax_ok = false;
+                continue;
+            }
+        };
+        match toml::from_str::<toml::Table>(&content) {
+            Ok(table) => check_unknown_keys(&table, &content, file, report),
+            Err(e) => {
+                report.error(
+                    Some(file),
+                    toml_error_line(&content, &e),
+                    format!("TOML inválido: {}", e.message()),
+                );
+                syntax_ok = false;
+            }
+        }
+    }
+    if !syntax_ok {
+        return;
+    }
+
+    // 2. Configuração efetiva (com herança)
+    let layered = match LayeredConfig::load_with(config_dir, client, &[]) {
+        Ok(l) => l,
+        Err(e) => {
+            report.error(Some(&defaults_path), None, format!("{e:#}"));
+            return;
+        }
+    };
+    let locate = |key: &str| -> (Option<PathBuf>, Option<usize>) {
+        match layered.sources.get(key) {
+            Some(src) => {
+                let path = PathBuf::from(src);
+                let line = std::fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|c| find_key_line(&c, key));
+                (Some(path), line)
+            }
+            None => (Some(defaults_path.clone()), None),
+        }
+    };
+
+    for key in REQUIRED_TOP {
+        match layered.table.get(*key).and_then(|v| v.as_str()) {
+            None => report.error(Some(&defaults_path), None, format!("campo obrigatório ausente: {key}")),
+            Some("") => {
+                let (f, l) = locate(key);
+                report.warn(f.as_deref(), l, format!("campo vazio: {key}"))
+            }
+            Some(_) => {}
+        }
+    }
+
+    match layered.deserialize::<config::Defaults>() {
+        Ok(defaults) => {
+            if let Err(e) = defaults.extract.validate() {
+                let (f, l) = locate("extract.pattern");
+                report.error(f.as_deref(), l, format!("{e:#}"));
+            }
+            check_output_dir(&defaults.output, &locate("output"), report);
+        }
+        Err(e) => report.error(Some(&defaults_path), None, format!("{e:#}")),
+    }
+
+    if let Some(peach) = layered.table.get("peach").and_then(|v| v.as_table()) {
+        check_peach(peach, &locate, &defaults_path, report);
+    }
+
+    // 3. Tabela de códigos
+    check_codes(&config::codes_path_for(config_dir, client), report);
+}
+
+/// Checagens globais (não dependem do cliente): template, credenciais, FFmpeg.
+pub fn check_environment(config_dir: &Path, exe_dir: &Path, needs_peach: bool, report: &mut Report) {
+    if let Err(e) = crate::find_template(exe_dir) {
+        report.error(None, None, format!("{e}"));
+    }
+    if needs_peach {
+        if let Err(e) = crate::peach::PeachCredentials::load(config_dir) {
+            report.error(
+                Some(&crate::peach::credentials_path(config_dir)),
+                None,
+                format!("{e:#}"),
+            );
+        }
+    }
+    if let Err(e) = crate::metadata::check_ffmpeg() {
+        report.warn(None, None, format!("{e}"));
+    }
+}
+
+fn check_unknown_keys(table: &toml::Table, content: &str, file: &Path, report: &mut Report) {
+    check_table_keys(table, "", content, file, report);
+}
+
+fn check_table_keys(table: &toml::Table, prefix: &str, content: &str, file: &Path, report: &mut Report) {
+    let Some(known) = known_keys(prefix) else { return };
+    let mut unknown = |key: &str, known: &[&str]| {
+        let name = key.rsplit('.').next().unwrap_or(key);
+        let hint = closest(name, known)
+            .map(|k| format!(" (quis dizer `{k}`?)"))
+            .unwrap_or_default();
+        report.error(
+            Some(file),
+            find_key_line(content, key),
+            format!("chave desconhecida `{key}`{hint}"),
+        );
+    };
+
+    let mut subtables = Vec::new();
+    for (k, v) in table {
+        let key = if prefix.is_empty() { k.clone() } else { format!("{prefix}.{k}") };
+        if !known.contains(&k.as_str()) {
+            unknown(&key, &known);
+            continue;
+        }
+        match v {
+            toml::Value::Table(sub) => subtables.push((sub, key)),
+            // Entradas `{ id, nome }` do hd/sd
+            toml::Value::Array(entries) if prefix == "peach.destinos" => {
+                for entry in entries {
+                    for k2 in entry.as_table().into_iter().flat_map(|t| t.keys()) {
+                        if !DESTINO_ENTRY_KEYS.contains(&k2.as_str()) {
+                            unknown(&format!("{key}.{k2}"), DESTINO_ENTRY_KEYS);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for (sub, key) in subtables {
+        check_table_keys(sub, &key, content, file, report);
+    }
+}
+
+fn check_peach<F>(peach: &toml::Table, locate: &F, defaults_path: &Path, report: &mut Report)
+where
+    F: Fn(&str) -> (Option<PathBuf>, Option<usize>),
+{
+    for key in REQUIRED_PEACH {
+        if !peach.contains_key(*key) {
+            report.error(
+                Some(defaults_path),
+                None,
+                format!("[peach] sem campo obrigatório: {key}"),
+            );
+        }
+    }
+    let get = |k: &str| peach.get(k).and_then(|v| v.as_str()).unwrap_or("");
+    let at = |k: &str| locate(&format!("peach.{k}"));
+
+    for (key, extranjero) in [("cnpj_avisador", "avisador_extranjero"), ("cnpj_agencia", "agencia_extranjero")] {
+        if peach.contains_key(key) && get(extranjero) != "1" {
+            if let Err(e) = validate_peach_field(key, get(key)) {
+                let (f, l) = at(key);
+                report.error(f.as_deref(), l, e);
+            }
+        }
+    }
+
+    for (key, extranjero) in [("avisador_id", "avisador_extranjero"), ("agencia_id", "agencia_extranjero")] {
+        if peach.contains_key(key) && get(extranjero) != "1" {
+            if let Err(e) = validate_peach_field(key, get(key)) {
+                let (f, l) = at(key);
+                report.error(f.as_deref(), l, e);
+            }
+        }
+    }
+
+    for key in ["id_marca", "id_producto"] {
+        if let Some(v) = peach.get(key) {
+            if v.as_integer().map(|n| n <= 0).unwrap_or(true) {
+                let (f, l) = at(key);
+                report.error(f.as_deref(), l, format!("{key} precisa ser um número positivo: {v}"));
+            }
+        }
+    }
+
+    let webhook = get("webhook_url");
+    if !webhook.is_empty() && !webhook.starts_with("https://script.google.com/") {
+        let (f, l) = at("webhook_url");
+        report.warn(f.as_deref(), l, format!("webhook_url não parece um Apps Script: {webhook}"));
+    }
+    let folder = get("drive_folder_id");
+    if !folder.is_empty() {
+        let (f, l) = at("drive_folder_id");
+        if folder.contains('/') {
+            report.error(f.as_deref(), l, "drive_folder_id deve ser só o ID, não a URL da pasta");
+        } else if !Regex::new(r"^[A-Za-z0-9_-]{10,}$").unwrap().is_match(folder) {
+            report.error(f.as_deref(), l, format!("drive_folder_id inválido: '{folder}'"));
+        }
+        if webhook.is_empty() {
+            report.warn(f.as_deref(), l, "drive_folder_id sem webhook_url: compartilhamento no Drive fica desabilitado");
+        }
+    }
+
+    if let Some(destinos) = peach.get("destinos").and_then(|v| v.as_table()) {
+        check_destinos(destinos, locate, report);
+    }
+}
+
+fn check_destinos<F>(destinos: &toml::Table, locate: &F, report: &mut Report)
+where
+    F: Fn(&str) -> (Option<PathBuf>, Option<usize>),
+{
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for codec in ["hd", "sd"] {
+        let Some(list) = destinos.get(codec).and_then(|v| v.as_array()) else {
+            continue;
+        };
+        let (f, l) = locate(&format!("peach.destinos.{codec}"));
+        for entry in list {
+            let id = match entry {
+                toml::Value::String(s) => s.as_str(),
+                toml::Value::Table(t) => t.get("id").and_then(|v| v.as_str()).unwrap_or(""),
+                other => {
+                    report.error(f.as_deref(), l, format!("destino {codec} malformado: {other}"));
+                    continue;
+                }
+            };
+            if id.is_empty() {
+                report.error(f.as_deref(), l, format!("destino {codec} sem `id`"));
+            } else if let Err(e) = validate_peach_field("destino", id) {
+                report.error(f.as_deref(), l, format!("{codec}: {e}"));
+            } else if let Some(prev) = seen.insert(id.to_string(), codec) {
+                report.warn(f.as_deref(), l, format!("destino {id} repetido (já em {prev})"));
+            }
+        }
+    }
+    if let Some(listas) = destinos.get("id_listas") {
+        let ok = listas
+            .as_array()
+            .map(|a| a.iter().all(|v| v.as_integer().map(|n| n > 0).unwrap_or(false)))
+            .unwrap_or(false);
+        if !ok {
+            let (f, l) = locate("peach.destinos.id_listas");
+            report.error(f.as_deref(), l, format!("id_listas deve ser lista de números: {listas}"));
+        }
+    }
+}
+
+fn check_output_dir(output: &str, at: &(Option<PathBuf>, Option<usize>), report: &mut Report) {
+    if output.is_empty() {
+        return;
+    }
+    let dir = Path::new(output);
+    let (f, l) = at;
+    if !dir.exists() {
+        let parent_ok = dir.parent().map(|p| p.as_os_str().is_empty() || p.is_dir()).unwrap_or(false);
+        if parent_ok {
+            report.warn(f.as_deref(), *l, format!("output {output} não existe (será criado)"));
+        } else {
+            report.error(f.as_deref(), *l, format!("output {output} não existe e não pode ser criado"));
+        }
+        return;
+    }
+    let probe = dir.join(format!(".encoder_check_{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+        }
+        Err(e) => report.error(f.as_deref(), *l, format!("sem permissão de escrita em {output}: {e}")),
+    }
+}
+
+fn check_codes(path: &Path, report: &mut Report) {
+    let content = match std::fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            report.error(Some(path), None, format!("não foi possível ler: {e}"));
+            return;
+        }
+    };
+    let table = match toml::from_str::<toml::Table>(&content) {
+        Ok(t) => t,
+        Err(e) => {
+            report.error(
+                Some(path),
+                toml_error_line(&content, &e),
+                format!("TOML inválido: {}", e.message()),
+            );
+            return;
+        }
+    };
+    let Some(codes) = table.get("codes").and_then(|v| v.as_table()) else {
+        report.error(Some(path), None, "bloco [codes] ausente");
+        return;
+    };
+
+    // Chaves diferentes com o mesmo número ("17" e "017") colidem na tabela carregada.
+    let mut by_code: HashMap<u32, &str> = HashMap::new();
+    let mut by_registro: HashMap<&str, &str> = HashMap::new();
+    for (key, value) in codes {
+        let line = find_key_line(&content, &format!("codes.{key}"));
+        let Ok(code) = key.parse::<u32>() else {
+            report.error(Some(path), line, format!("código não numérico: `{key}`"));
+            continue;
+        };
+        if let Some(prev) = by_code.insert(code, key) {
+            report.error(Some(path), line, format!("código {code} duplicado (`{prev}` e `{key}`)"));
+        }
+        let Some(registro) = value.as_str() else {
+            report.error(Some(path), line, format!("registro do código {code} deve ser string"));
+            continue;
+        };
+        if let Err(e) = crate::import::validate_registro(registro) {
+            report.error(Some(path), line, format!("código {code}: {e}"));
+        }
+        if let Some(prev) = by_registro.insert(registro, key) {
+            report.warn(Some(path), line, format!("registro {registro} usado pelos códigos {prev} e {key}"));
+        }
+    }
+}
+
+// ----------------- Helpers -----------------
+
+/// Valida o formato de um campo do `[peach]` que tem formato fixo
+/// (`cnpj_*`, `avisador_id`, `agencia_id` e IDs de `destino`). Outros campos
+/// passam direto. Usado também pelo cadastro de cliente novo.
+pub fn validate_peach_field(key: &str, value: &str) -> Result<(), String> {
+    let (pattern, example) = match key {
+        "cnpj_avisador" | "cnpj_agencia" => {
+            if is_valid_cnpj(value) {
+                return Ok(());
+            }
+            return Err(format!("{key} inválido: '{value}' (14 dígitos, DV conferido)"));
+        }
+        "avisador_id" => (r"^BRA\d{3,}$", "BRA0743"),
+        "agencia_id" => (r"^BR\d{3,}$", "BR0741"),
+        "destino" => (r"^BR[A-Z0-9_]+$", "BR_GLOBO_112, BR1230"),
+        _ => return Ok(()),
+    };
+    if Regex::new(pattern).unwrap().is_match(value) {
+        Ok(())
+    } else {
+        Err(format!("{key} fora do formato (ex: {example}): '{value}'"))
+    }
+}
+
+/// Valida CNPJ (14 dígitos + dígitos verificadores).
+pub fn is_valid_cnpj(cnpj: &str) -> bool {
+    let digits: Vec<u32> = cnpj.chars().filter_map(|c| c.to_digit(10)).collect();
+    if digits.len() != 14 || cnpj.len() != 14 || digits.iter().all(|&d| d == digits[0]) {
+        return false;
+    }
+    let dv = |len: usize| {
+        let weights = [6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2];
+        let offset = 13 - len;
+        let sum: u32 = digits[..len]
+            .iter()
+            .zip(&weights[offset..])
+            .map(|(d, w)| d * w)
+            .sum();
+        let r = sum % 11;
+        if r < 2 {
+            0
+        } else {
+            11 - r
+        }
+    };
+    dv(12) == digits[12] && dv(13) == digits[13]
+}
+
+/// Linha (1-based) onde `chave.com.pontos` é definida no TOML. Reconhece
+/// `[tabela]`, `[tabela.sub]`, chaves simples e inline tables no nível pai.
+pub fn find_key_line(content: &str, dotted: &str) -> Option<usize> {
+    let (table, key) = match dotted.rsplit_once('.') {
+        Some((t, k)) => (t, k),
+        None => ("", dotted),
+    };
+    let mut current = String::new();
+    let mut fallback = None;
+    for (i, line) in content.lines().enumerate() {
+        let t = line.trim();
+        if t.starts_with('#') {
+            continue;
+        }
+        if t.starts_with('[') && !t.starts_with("[[") {
+            current = t.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            if current == dotted {
+                return Some(i + 1);
+            }
+            continue;
+        }
+        let Some((k, _)) = t.split_once('=') else { continue };
+        let k = k.trim().trim_matches('"');
+        if current == table && k == key {
+            return Some(i + 1);
+        }
+        // Chave dentro de inline table ou dotted key no pai (ex: `offset = { above = .. }`)
+        if fallback.is_none() && table.starts_with(current.as_str()) {
+            let rest = table[current.len()..].trim_start_matches('.');
+            let first = rest.split('.').next().unwrap_or("");
+            if !first.is_empty() && k == first {
+                fallback = Some(i + 1);
+            }
+        }
+    }
+    fallback
+}
+
+fn toml_error_line(content: &str, e: &toml::de::Error) -> Option<usize> {
+    let span = e.span()?;
+    Some(content[..span.start.min(content.len())].lines().count().max(1))
+}
+
+/// Chave conhecida mais parecida (distância de edição ≤ 3).
+fn closest<'a>(name: &str, known: &[&'a str]) -> Option<&'a str> {
+    known
+        .iter()
+        .map(|k| (levenshtein(name, k), *k))
+        .filter(|(d, _)| *d <= 3)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, k)| k)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cnpj() {
+        assert!(is_valid_cnpj("30689848000130"));
+        assert!(is_valid_cnpj("01936260000136"));
+        assert!(!is_valid_cnpj("30689848000131"));
+        assert!(!is_valid_cnpj("3068984800013"));
+        assert!(!is_valid_cnpj("30.689.848/0001-30"));
+        assert!(!is_valid_cnpj("11111111111111"));
+    }
+
+    #[test]
+    fn test_find_key_line() {
+        let content = "produto = \"X\"\n\n[extract]\noffset = { above = 50, subtract = 40 }\n\n[peach]\n# comentário\navisador_id = \"BRA0743\"\n\n[peach.destinos]\nhd = []\n";
+        assert_eq!(find_key_line(content, "produto"), Some(1));
+        assert_eq!(find_key_line(content, "extract.offset.above"), Some(4));
+        assert_eq!(find_key_line(content, "peach.avisador_id"), Some(8));
+        assert_eq!(find_key_line(content, "peach.destinos"), Some(10));
+        assert_eq!(find_key_line(content, "peach.destinos.hd"), Some(11));
+        assert_eq!(find_key_line(content, "peach.campana"), None);
+    }
+
+    #[test]
+    fn test_check_client() {
+        let dir = std::env::temp_dir().join(format!("encoder_test_check_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("CLI")).unwrap();
+        std::fs::write(
+            dir.join("defaults.toml"),
+            "produto = \"P\"\nprodutora = \"POST.E\"\nagencia = \"A\"\nanunciante = \"B\"\ndiretor = \"D\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("CLI/defaults.toml"),
+            "produtoo = \"typo\"\n[peach]\navisador_id = \"XX1\"\ncnpj_avisador = \"30689848000131\"\nid_marca = 9758\nagencia_id = \"BR0741\"\ncnpj_agencia = \"01936260000136\"\ndrive_folder_id = \"https://drive.google.com/drive/folders/abc\"\n[peach.destinos]\nhd = [\"BR1230\", { id = \"tv x\" }]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("CLI/codes.toml"),
+            "[codes]\n1 = \"2024017422001-4\"\n\"01\" = \"2024017422001-9\"\n2 = \"123\"\n",
+        )
+        .unwrap();
+
+        let mut report = Report::default();
+        check_client(&dir, Some("CLI"), &mut report);
+        let lines: Vec<String> = report.diagnostics.iter().map(|d| d.to_string()).collect();
+        let has = |needle: &str| lines.iter().any(|l| l.contains(needle));
+
+        assert!(has("defaults.toml:1: erro: chave desconhecida `produtoo` (quis dizer `produto`?)"), "{lines:#?}");
+        assert!(has("[peach] sem campo obrigatório: id_producto"));
+        assert!(has("defaults.toml:3: erro: avisador_id fora do formato"));
+        assert!(has("defaults.toml:4: erro: cnpj_avisador inválido"));
+        assert!(!has("cnpj_agencia inválido"));
+        assert!(has("drive_folder_id deve ser só o ID"));
+        assert!(has("hd: destino fora do formato (ex: BR_GLOBO_112, BR1230): 'tv x'"));
+        assert!(has("erro: código 1 duplicado"));
+        assert!(has("codes.toml:4: erro: código 2: registro '123' fora do formato"));
+        assert!(report.errors() >= 8);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_exemplo_is_clean() {
+        // O _EXEMPLO com os blocos opcionais descomentados tem que passar limpo
+        let repo = Path::new(env!("CARGO_MANIFEST_DIR")).join("config");
+        let example = std::fs::read_to_string(repo.join("_EXEMPLO/defaults.toml")).unwrap();
+        let commented = Regex::new(r"^# (\[peach|[a-z_]+ = | *\{ id|\])").unwrap();
+        let defaults: Vec<&str> = example
+            .lines()
+            .map(|l| if commented.is_match(l) { &l[2..] } else { l })
+            .collect();
+
+        let dir = std::env::temp_dir().join(format!("encoder_test_exemplo_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("_EXEMPLO")).unwrap();
+        std::fs::copy(repo.join("defaults.toml"), dir.join("defaults.toml")).unwrap();
+        std::fs::copy(repo.join("_EXEMPLO/codes.toml"), dir.join("_EXEMPLO/codes.toml")).unwrap();
+        std::fs::write(dir.join("_EXEMPLO/defaults.toml"), defaults.join("\n")).unwrap();
+
+        let mut report = Report::default();
+        check_client(&dir, Some("_EXEMPLO"), &mut report);
+        let lines: Vec<String> = report.diagnostics.iter().map(|d| d.to_string()).collect();
+        assert!(lines.is_empty(), "{lines:#?}");
+        assert!(known_keys("peach").unwrap().contains(&"retry"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,7 +1,11 @@
 use anyhow::{Context, Result};
+
+use crate::error::EncoderError;
+use regex::Regex;
 use serde::Deserialize;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Defaults {
@@ -12,6 +16,146 @@ pub struct Defaults {
     pub diretor: String,
     #[serde(default)]
     pub output: String,
+    /// Regras de extração de código/título a partir do nome do arquivo (`[extract]`).
+    #[serde(default)]
+    pub extract: ExtractRules,
+}
+
+/// Regras por cliente para interpretar o nome do arquivo. Bloco `[extract]`
+/// do `defaults.toml`. Sem o bloco, vale o padrão: código = último segmento
+/// após `_`, sem aliases.
+///
+/// ```toml
+/// [extract]
+/// # Grupo nomeado `code` obrigatório; `titulo`, `versao` e `idioma` opcionais.
+/// pattern = '^(?P<titulo>.+?)_(?P<idioma>PT|ES)_(?P<code>\d+)$'
+/// # Alias aritmético: códigos acima de 50 usam o registro de (código - 40).
+/// offset = { above = 50, subtract = 40 }
+/// # Alias por tabela (tem prioridade sobre o offset).
+/// aliases = { 99 = 17 }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExtractRules {
+    /// Regex aplicada ao nome do arquivo sem extensão.
+    #[serde(default)]
+    pub pattern: String,
+    #[serde(default)]
+    pub offset: Option<CodeOffset>,
+    /// Código do arquivo → código da tabela `codes.toml`.
+    #[serde(default)]
+    pub aliases: HashMap<String, u32>,
+    /// `pattern` compilada na primeira vez que é usada (ou o erro dela).
+    #[serde(skip)]
+    compiled: OnceLock<std::result::Result<Option<Regex>, String>>,
+}
+
+/// Alias aritmético: `code > above` busca o registro de `code - subtract`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CodeOffset {
+    pub above: u32,
+    pub subtract: u32,
+}
+
+/// Campos extraídos do nome do arquivo.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilenameInfo {
+    pub code: Option<u32>,
+    pub titulo: Option<String>,
+    pub versao: Option<String>,
+    pub idioma: Option<String>,
+}
+
+impl ExtractRules {
+    /// Regex configurada, compilada uma vez só. `Ok(None)` quando não há `pattern`.
+    pub fn regex(&self) -> Result<Option<&Regex>> {
+        self.compiled
+            .get_or_init(|| compile_pattern(&self.pattern))
+            .as_ref()
+            .map(Option::as_ref)
+            .map_err(|e| EncoderError::ConfigError(e.clone()).into())
+    }
+
+    /// Valida a regex e as chaves da tabela de aliases.
+    pub fn validate(&self) -> Result<()> {
+        self.regex()?;
+        for key in self.aliases.keys() {
+            key.parse::<u32>()
+                .with_context(|| format!("Chave inválida em [extract].aliases: {key}"))?;
+        }
+        Ok(())
+    }
+
+    /// Extrai código (e opcionalmente título, versão e idioma) do nome do arquivo.
+    pub fn extract(&self, filename: &str) -> FilenameInfo {
+        let stem = Path::new(filename)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(filename);
+
+        let re = match self.regex() {
+            Ok(Some(re)) => re,
+            // Último segmento após '_'
+            Ok(None) => {
+                return FilenameInfo {
+                    code: stem.rsplit('_').next().and_then(|s| s.parse::<u32>().ok()),
+                    ..Default::default()
+                }
+            }
+            // Regex inválida é recusada na carga (`validate`); se chegar
+            // aqui, não extrai nada em vez de cair na regra padrão.
+            Err(_) => return FilenameInfo::default(),
+        };
+
+        let Some(caps) = re.captures(stem) else {
+            return FilenameInfo::default();
+        };
+        let group = |name: &str| {
+            caps.name(name)
+                .map(|m| m.as_str().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        FilenameInfo {
+            code: caps.name("code").and_then(|m| m.as_str().parse::<u32>().ok()),
+            titulo: group("titulo"),
+            versao: group("versao"),
+            idioma: group("idioma"),
+        }
+    }
+
+    /// Busca o registro do código: tabela direta, depois `aliases`, depois `offset`.
+    pub fn lookup_registro(&self, code: u32, codes: &HashMap<u32, String>) -> Option<String> {
+        if let Some(reg) = codes.get(&code) {
+            return Some(reg.clone());
+        }
+        if let Some(&target) = self.aliases.get(&code.to_string()) {
+            return codes.get(&target).cloned();
+        }
+        if let Some(off) = &self.offset {
+            if code > off.above {
+                return codes.get(&code.checked_sub(off.subtract)?).cloned();
+            }
+        }
+        None
+    }
+
+    /// Atalho: extrai o código do nome do arquivo e busca o registro.
+    pub fn resolve_registro(&self, filename: &str, codes: &HashMap<u32, String>) -> Option<String> {
+        self.lookup_registro(self.extract(filename).code?, codes)
+    }
+}
+
+fn compile_pattern(pattern: &str) -> std::result::Result<Option<Regex>, String> {
+    if pattern.is_empty() {
+        return Ok(None);
+    }
+    let re = Regex::new(pattern)
+        .map_err(|e| format!("Regex inválida em [extract].pattern: {pattern}: {e}"))?;
+    if !re.capture_names().any(|n| n == Some("code")) {
+        return Err(format!(
+            "[extract].pattern precisa de um grupo nomeado `code`: {pattern}"
+        ));
+    }
+    Ok(Some(re))
 }
 
 #[derive(Debug, Deserialize)]
@@ -21,24 +165,231 @@ struct CodesFileRaw {
 
 /// Resolve o diretório de configuração efetivo.
 /// Se `client` for informado, retorna `config_dir/client/`.
-fn resolve_config_path(config_dir: &Path, client: Option<&str>) -> std::path::PathBuf {
+/// `client` pode incluir a campanha: `"SIPOLATTI/NATAL"`.
+fn resolve_config_path(config_dir: &Path, client: Option<&str>) -> PathBuf {
     match client {
         Some(name) => config_dir.join(name),
         None => config_dir.to_path_buf(),
     }
 }
 
+/// Diretórios que compõem a configuração, do mais geral ao mais específico:
+/// `config/`, `config/CLIENTE/`, `config/CLIENTE/CAMPANHA/`.
+pub(crate) fn layer_dirs(config_dir: &Path, client: Option<&str>) -> Vec<PathBuf> {
+    let mut dirs = vec![config_dir.to_path_buf()];
+    if let Some(name) = client {
+        let mut dir = config_dir.to_path_buf();
+        for comp in Path::new(name).components() {
+            if let std::path::Component::Normal(part) = comp {
+                dir = dir.join(part);
+                dirs.push(dir.clone());
+            }
+        }
+    }
+    dirs
+}
+
+/// Parseia `chave=valor` (formato do `--set`).
+pub fn parse_override(s: &str) -> Result<(String, String)> {
+    let (k, v) = s
+        .split_once('=')
+        .ok_or_else(|| EncoderError::ConfigError(format!("Override inválido '{s}', use chave=valor")))?;
+    let k = k.trim();
+    if k.is_empty() {
+        anyhow::bail!(EncoderError::ConfigError(format!("Override inválido '{s}': chave vazia")));
+    }
+    Ok((k.to_string(), v.trim().to_string()))
+}
+
+/// `defaults.toml` efetivo após mesclar as camadas campo a campo.
+/// `sources` guarda, pra cada chave (com pontos), de onde veio o valor final.
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+    pub table: toml::Table,
+    pub sources: BTreeMap<String, String>,
+}
+
+impl LayeredConfig {
+    /// Carrega raiz → cliente → campanha → `overrides` (os `--set chave=valor`
+    /// da linha de comando; a GUI passa vazio). O `defaults.toml` da pasta
+    /// mais específica é obrigatório.
+    pub fn load_with(
+        config_dir: &Path,
+        client: Option<&str>,
+        overrides: &[(String, String)],
+    ) -> Result<Self> {
+        let dirs = layer_dirs(config_dir, client);
+        let mut layered = Self::default();
+        for (i, dir) in dirs.iter().enumerate() {
+            let path = dir.join("defaults.toml");
+            let is_target = i == dirs.len() - 1;
+            if !is_target && !path.exists() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path).map_err(|e| {
+                EncoderError::ConfigError(format!("não foi possível ler {}: {e}", path.display()))
+            })?;
+            let table: toml::Table = toml::from_str(&content).map_err(|e| {
+                EncoderError::ConfigError(format!("erro ao parsear {}: {e}", path.display()))
+            })?;
+            layered.merge(table, &path.display().to_string(), "");
+        }
+        for (key, value) in overrides {
+            layered.set(key, value, "--set")?;
+        }
+        Ok(layered)
+    }
+
+    /// Mescla `layer` por cima da configuração atual. Tabelas são mescladas
+    /// recursivamente; qualquer outro valor (inclusive arrays) substitui.
+    fn merge(&mut self, layer: toml::Table, source: &str, prefix: &str) {
+        fn merge_into(
+            dst: &mut toml::Table,
+            src: toml::Table,
+            sources: &mut BTreeMap<String, String>,
+            source: &str,
+            prefix: &str,
+        ) {
+            for (k, v) in src {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{prefix}.{k}")
+                };
+                match (dst.get_mut(&k), v) {
+                    (Some(toml::Value::Table(d)), toml::Value::Table(t)) => {
+                        merge_into(d, t, sources, source, &key);
+                    }
+                    (_, v) => {
+                        let sub = format!("{key}.");
+                        sources.retain(|s, _| !s.starts_with(&sub));
+                        record_sources(&v, &key, source, sources);
+                        dst.insert(k, v);
+                    }
+                }
+            }
+        }
+        fn record_sources(
+            v: &toml::Value,
+            key: &str,
+            source: &str,
+            sources: &mut BTreeMap<String, String>,
+        ) {
+            match v {
+                toml::Value::Table(t) => {
+                    for (k, v) in t {
+                        record_sources(v, &format!("{key}.{k}"), source, sources);
+                    }
+                }
+                _ => {
+                    sources.insert(key.to_string(), source.to_string());
+                }
+            }
+        }
+        merge_into(&mut self.table, layer, &mut self.sources, source, prefix);
+    }
+
+    /// Aplica um override `chave.com.pontos = valor`. O valor é lido como TOML
+    /// (`42`, `true`, `["a"]`) e, se não parsear, como string.
+    pub fn set(&mut self, key: &str, value: &str, source: &str) -> Result<()> {
+        let parsed = toml::from_str::<toml::Table>(&format!("v = {value}"))
+            .ok()
+            .and_then(|mut t| t.remove("v"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+
+        let mut parts: Vec<&str> = key.split('.').collect();
+        let last = parts.pop().unwrap_or_default();
+        let mut layer = toml::Table::new();
+        layer.insert(last.to_string(), parsed);
+        for part in parts.iter().rev() {
+            let mut outer = toml::Table::new();
+            outer.insert(part.to_string(), toml::Value::Table(layer));
+            layer = outer;
+        }
+        self.merge(layer, source, "");
+        Ok(())
+    }
+
+    /// Desserializa a configuração efetiva (ex: `Defaults`, `DefaultsWithPeach`).
+    /// O erro aponta o arquivo (ou `--set`) que definiu a chave com problema.
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        toml::Value::Table(self.table.clone())
+            .try_into()
+            .map_err(|e: toml::de::Error| {
+                let message = e.to_string().trim().replace('\n', " ");
+                // O toml fecha a mensagem com "in `chave.com.pontos`"
+                let origin = message
+                    .rsplit_once("in `")
+                    .and_then(|(_, key)| key.strip_suffix('`'))
+                    .and_then(|key| self.source_of(key));
+                let origin = origin.unwrap_or("configuração efetiva");
+                EncoderError::ConfigError(format!("{origin}: {message}")).into()
+            })
+    }
+
+    /// Valida o `[extract]` já desserializado, apontando o arquivo de origem.
+    pub fn validate_extract(&self, rules: &ExtractRules) -> Result<()> {
+        rules.validate().map_err(|e| {
+            let key = if rules.regex().is_err() { "extract.pattern" } else { "extract.aliases" };
+            let origin = self.source_of(key).unwrap_or("configuração efetiva");
+            EncoderError::ConfigError(format!("{origin}: {e:#}")).into()
+        })
+    }
+
+    /// Arquivo (ou `--set`) que definiu `key`. Pra uma tabela, o de uma das
+    /// chaves dentro dela.
+    pub fn source_of(&self, key: &str) -> Option<&str> {
+        let prefix = format!("{key}.");
+        self.sources
+            .get(key)
+            .or_else(|| {
+                self.sources
+                    .iter()
+                    .find(|(k, _)| k.starts_with(&prefix))
+                    .map(|(_, source)| source)
+            })
+            .map(String::as_str)
+    }
+
+    /// Linhas `chave = valor  (origem)`, ordenadas por chave.
+    pub fn describe(&self) -> Vec<(String, String, String)> {
+        self.sources
+            .iter()
+            .map(|(key, source)| {
+                let value = lookup(&self.table, key).unwrap_or_default();
+                (key.clone(), value, source.clone())
+            })
+            .collect()
+    }
+}
+
+/// Busca um valor folha por chave com pontos e formata como TOML.
+fn lookup(table: &toml::Table, key: &str) -> Option<String> {
+    let mut parts = key.split('.').peekable();
+    let mut current = table;
+    while let Some(part) = parts.next() {
+        let v = current.get(part)?;
+        if parts.peek().is_none() {
+            return Some(v.to_string());
+        }
+        current = v.as_table()?;
+    }
+    None
+}
+
 pub fn load_defaults(config_dir: &Path) -> Result<Defaults> {
-    load_defaults_for(config_dir, None)
+    load_defaults_for(config_dir, None, &[])
 }
 
-pub fn load_defaults_for(config_dir: &Path, client: Option<&str>) -> Result<Defaults> {
-    let dir = resolve_config_path(config_dir, client);
-    let path = dir.join("defaults.toml");
-    let content = std::fs::read_to_string(&path)
-        .with_context(|| format!("Não foi possível ler {}", path.display()))?;
-    let defaults: Defaults =
-        toml::from_str(&content).with_context(|| format!("Erro ao parsear {}", path.display()))?;
+/// Carrega o `defaults.toml` efetivo (raiz + cliente + campanha + `overrides`).
+pub fn load_defaults_for(
+    config_dir: &Path,
+    client: Option<&str>,
+    overrides: &[(String, String)],
+) -> Result<Defaults> {
+    let layered = LayeredConfig::load_with(config_dir, client, overrides)?;
+    let defaults: Defaults = layered.deserialize()?;
+    layered.validate_extract(&defaults.extract)?;
     Ok(defaults)
 }
 
@@ -46,13 +397,27 @@ pub fn load_codes(config_dir: &Path) -> Result<HashMap<u32, String>> {
     load_codes_for(config_dir, None)
 }
 
+/// Localiza o `codes.toml` do cliente. Numa campanha sem tabela própria,
+/// usa a do cliente.
+pub fn codes_path_for(config_dir: &Path, client: Option<&str>) -> PathBuf {
+    let dirs = layer_dirs(config_dir, client);
+    let candidates = if client.is_some() { &dirs[1..] } else { &dirs[..] };
+    candidates
+        .iter()
+        .rev()
+        .map(|d| d.join("codes.toml"))
+        .find(|p| p.exists())
+        .unwrap_or_else(|| resolve_config_path(config_dir, client).join("codes.toml"))
+}
+
 pub fn load_codes_for(config_dir: &Path, client: Option<&str>) -> Result<HashMap<u32, String>> {
-    let dir = resolve_config_path(config_dir, client);
-    let path = dir.join("codes.toml");
-    let content = std::fs::read_to_string(&path)
-        .with_context(|| format!("Não foi possível ler {}", path.display()))?;
-    let raw: CodesFileRaw = toml::from_str(&content)
-        .map_err(|e| anyhow::anyhow!("Erro ao parsear {}: {e}", path.display()))?;
+    let path = codes_path_for(config_dir, client);
+    let content = std::fs::read_to_string(&path).map_err(|e| {
+        EncoderError::ConfigError(format!("não foi possível ler {}: {e}", path.display()))
+    })?;
+    let raw: CodesFileRaw = toml::from_str(&content).map_err(|e| {
+        EncoderError::ConfigError(format!("erro ao parsear {}: {e}", path.display()))
+    })?;
     let codes = raw
         .codes
         .into_iter()
@@ -62,46 +427,46 @@ pub fn load_codes_for(config_dir: &Path, client: Option<&str>) -> Result<HashMap
 }
 
 /// Lista subpastas de `config_dir` que contenham `defaults.toml` e `codes.toml`.
-/// Retorna os nomes das subpastas (nomes dos clientes), ordenados alfabeticamente.
+/// Campanhas (subpastas do cliente com `defaults.toml`) entram como `CLIENTE/CAMPANHA`.
+/// Retorna os nomes ordenados alfabeticamente.
 pub fn list_clients(config_dir: &Path) -> Vec<String> {
-    let Ok(entries) = std::fs::read_dir(config_dir) else {
-        return Vec::new();
-    };
-    let mut clients: Vec<String> = entries
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
-        .filter(|e| {
-            let p = e.path();
-            p.join("defaults.toml").exists() && p.join("codes.toml").exists()
-        })
-        .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
-        .collect();
+    fn subdirs(dir: &Path) -> Vec<(String, PathBuf)> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|ft| ft.is_dir()).unwrap_or(false))
+            .filter_map(|e| e.file_name().to_str().map(|s| (s.to_string(), e.path())))
+            .collect()
+    }
+
+    let mut clients = Vec::new();
+    for (name, path) in subdirs(config_dir) {
+        if !(path.join("defaults.toml").exists() && path.join("codes.toml").exists()) {
+            continue;
+        }
+        for (campaign, sub) in subdirs(&path) {
+            if sub.join("defaults.toml").exists() {
+                clients.push(format!("{name}/{campaign}"));
+            }
+        }
+        clients.push(name);
+    }
     clients.sort();
     clients
 }
 
-/// Extrai o código numérico do nome do arquivo.
+/// Extrai o código numérico do nome do arquivo com a regra padrão.
 /// Ex: "FEV_PROMO_17.mp4" → 17
 pub fn extract_code_from_filename(filename: &str) -> Option<u32> {
-    let stem = Path::new(filename)
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or(filename);
-
-    // Último segmento após '_'
-    stem.rsplit('_').next().and_then(|s| s.parse::<u32>().ok())
+    ExtractRules::default().extract(filename).code
 }
 
-/// Busca o registro na tabela de códigos.
-/// Se o código > 50, subtrai 40 (ex: 60→20, 71→31, 72→32).
+/// Busca o registro na tabela de códigos, sem aliases.
+/// Pra aplicar as regras do cliente, use `ExtractRules::lookup_registro`.
 pub fn lookup_registro(code: u32, codes: &HashMap<u32, String>) -> Option<String> {
-    if let Some(reg) = codes.get(&code) {
-        return Some(reg.clone());
-    }
-    if code > 50 {
-        return codes.get(&(code - 40)).cloned();
-    }
-    None
+    codes.get(&code).cloned()
 }
 
 #[cfg(test)]
@@ -123,8 +488,181 @@ mod tests {
         codes.insert(31, "2024017422031-6".to_string());
 
         assert_eq!(lookup_registro(20, &codes), Some("2024017422020-0".to_string()));
-        assert_eq!(lookup_registro(60, &codes), Some("2024017422020-0".to_string()));
-        assert_eq!(lookup_registro(71, &codes), Some("2024017422031-6".to_string()));
+        assert_eq!(lookup_registro(60, &codes), None);
         assert_eq!(lookup_registro(99, &codes), None);
     }
+
+    #[test]
+    fn test_rules_offset_alias() {
+        let mut codes = HashMap::new();
+        codes.insert(20, "2024017422020-0".to_string());
+        codes.insert(31, "2024017422031-6".to_string());
+        let rules: ExtractRules =
+            toml::from_str("offset = { above = 50, subtract = 40 }").unwrap();
+
+        assert_eq!(rules.lookup_registro(20, &codes), Some("2024017422020-0".to_string()));
+        assert_eq!(rules.lookup_registro(60, &codes), Some("2024017422020-0".to_string()));
+        assert_eq!(rules.lookup_registro(71, &codes), Some("2024017422031-6".to_string()));
+        assert_eq!(rules.lookup_registro(99, &codes), None);
+        // Abaixo do limite não aplica offset
+        assert_eq!(rules.lookup_registro(45, &codes), None);
+    }
+
+    #[test]
+    fn test_rules_table_alias() {
+        let mut codes = HashMap::new();
+        codes.insert(17, "2024017422017-0".to_string());
+        codes.insert(20, "2024017422020-0".to_string());
+        let rules: ExtractRules = toml::from_str(
+            r#"
+            offset = { above = 50, subtract = 40 }
+            aliases = { 60 = 17, 5 = 20 }
+            "#,
+        )
+        .unwrap();
+        rules.validate().unwrap();
+
+        // Tabela tem prioridade sobre o offset
+        assert_eq!(rules.lookup_registro(60, &codes), Some("2024017422017-0".to_string()));
+        assert_eq!(rules.lookup_registro(5, &codes), Some("2024017422020-0".to_string()));
+        // Código presente na tabela principal não passa pelos aliases
+        assert_eq!(rules.lookup_registro(17, &codes), Some("2024017422017-0".to_string()));
+
+        let bad: ExtractRules = toml::from_str(r#"aliases = { x = 1 }"#).unwrap();
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_rules_regex() {
+        let rules: ExtractRules = toml::from_str(
+            r#"pattern = '^(?P<titulo>.+?)_(?P<idioma>PT|ES)_V(?P<versao>\d+)_(?P<code>\d+)$'"#,
+        )
+        .unwrap();
+        rules.validate().unwrap();
+
+        let info = rules.extract("ABR_PROMO_ES_V2_17.mp4");
+        assert_eq!(info.code, Some(17));
+        assert_eq!(info.titulo.as_deref(), Some("ABR_PROMO"));
+        assert_eq!(info.idioma.as_deref(), Some("ES"));
+        assert_eq!(info.versao.as_deref(), Some("2"));
+
+        // Não casa com a regex → nada extraído
+        assert_eq!(rules.extract("ABR_PROMO_17.mp4"), FilenameInfo::default());
+
+        // Só o código, sem grupos opcionais
+        let rules: ExtractRules = toml::from_str(r#"pattern = '^(?P<code>\d+)_'"#).unwrap();
+        let info = rules.extract("0042_FILME.mov");
+        assert_eq!(info.code, Some(42));
+        assert_eq!(info.titulo, None);
+    }
+
+    #[test]
+    fn test_rules_regex_invalid() {
+        let rules: ExtractRules = toml::from_str(r#"pattern = '(unclosed'"#).unwrap();
+        assert!(rules.validate().is_err());
+        // Não cai na regra padrão (último segmento)
+        assert_eq!(rules.extract("FEV_PROMO_17.mp4"), FilenameInfo::default());
+        // Sem grupo `code`
+        let rules: ExtractRules = toml::from_str(r#"pattern = '^(?P<titulo>.+)$'"#).unwrap();
+        assert!(rules.validate().is_err());
+    }
+
+    #[test]
+    fn test_rules_default_matches_legacy() {
+        let rules = ExtractRules::default();
+        assert_eq!(rules.extract("FEV_PROMO_17.mp4").code, Some(17));
+        assert_eq!(rules.extract("nocode.mp4").code, None);
+        assert_eq!(rules.extract("FEV_PROMO_17.mp4").titulo, None);
+    }
+
+    /// Cria `config/` temporário com os arquivos informados (caminho relativo → conteúdo).
+    fn temp_config(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("encoder_test_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (rel, content) in files {
+            let path = dir.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_layered_config_merge() {
+        let dir = temp_config(
+            "layers",
+            &[
+                (
+                    "defaults.toml",
+                    "produto = \"RAIZ\"\nprodutora = \"POST.E\"\nagencia = \"A\"\nanunciante = \"B\"\ndiretor = \"D\"\n[peach]\nwebhook_url = \"https://hook\"\n",
+                ),
+                (
+                    "CLI/defaults.toml",
+                    "produto = \"CLIENTE\"\n[peach]\navisador_id = \"BRA0743\"\n[peach.destinos]\nhd = [\"BR1230\"]\n",
+                ),
+                ("CLI/codes.toml", "[codes]\n1 = \"2024017422001-4\"\n"),
+                ("CLI/NATAL/defaults.toml", "produto = \"NATAL\"\n[peach]\ncampana = \"NATAL\"\n"),
+            ],
+        );
+
+        let d = load_defaults_for(&dir, Some("CLI"), &[]).unwrap();
+        assert_eq!(d.produto, "CLIENTE");
+        assert_eq!(d.produtora, "POST.E");
+
+        let layered =
+            LayeredConfig::load_with(&dir, Some("CLI/NATAL"), &[("diretor".into(), "X".into())])
+                .unwrap();
+        let d: Defaults = layered.deserialize().unwrap();
+        assert_eq!(d.produto, "NATAL");
+        assert_eq!(d.diretor, "X");
+        let src = |k: &str| layered.sources.get(k).cloned().unwrap_or_default();
+        let file = |parts: &[&str]| {
+            let mut p = dir.clone();
+            for part in parts {
+                p = p.join(part);
+            }
+            p.join("defaults.toml").display().to_string()
+        };
+        assert_eq!(src("produto"), file(&["CLI", "NATAL"]));
+        assert_eq!(src("produtora"), file(&[]));
+        assert_eq!(src("peach.avisador_id"), file(&["CLI"]));
+        assert_eq!(src("peach.campana"), file(&["CLI", "NATAL"]));
+        assert_eq!(src("peach.webhook_url"), file(&[]));
+        assert_eq!(src("diretor"), "--set");
+        assert!(lookup(&layered.table, "peach.destinos.hd").unwrap().contains("BR1230"));
+
+        // Campanha sem codes.toml usa a tabela do cliente
+        assert_eq!(codes_path_for(&dir, Some("CLI/NATAL")), dir.join("CLI").join("codes.toml"));
+        assert_eq!(list_clients(&dir), vec!["CLI".to_string(), "CLI/NATAL".to_string()]);
+
+        // Pasta mais específica precisa ter defaults.toml
+        assert!(LayeredConfig::load_with(&dir, Some("OUTRO"), &[]).is_err());
+
+        // Erro de tipo aponta o arquivo (ou o --set) que definiu a chave
+        let err = load_defaults_for(&dir, Some("CLI"), &[("diretor".into(), "7".into())]).unwrap_err();
+        assert!(format!("{err:#}").contains(": --set: "), "{err:#}");
+        let err = load_defaults_for(&dir, Some("CLI"), &[("extract.pattern".into(), "(x".into())])
+            .unwrap_err();
+        assert!(format!("{err:#}").contains(": --set: "), "{err:#}");
+        std::fs::write(dir.join("CLI/NATAL/defaults.toml"), "produto = 2\n").unwrap();
+        let err = load_defaults_for(&dir, Some("CLI/NATAL"), &[]).unwrap_err();
+        assert!(format!("{err:#}").contains(&file(&["CLI", "NATAL"])), "{err:#}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_override_values() {
+        let mut layered = LayeredConfig::default();
+        layered.set("peach.id_marca", "9758", "--set").unwrap();
+        layered.set("produto", "PROMO X", "--set").unwrap();
+        assert_eq!(lookup(&layered.table, "peach.id_marca").unwrap(), "9758");
+        assert_eq!(lookup(&layered.table, "produto").unwrap(), "\"PROMO X\"");
+
+        assert_eq!(
+            parse_override("peach.campana = NATAL").unwrap(),
+            ("peach.campana".to_string(), "NATAL".to_string())
+        );
+        assert!(parse_override("sem_igual").is_err());
+        assert!(parse_override("=x").is_err());
+    }
 }
@@ -1,7 +1,9 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, Result};
 use std::path::Path;
 use std::process::Command;
+use std::sync::{Condvar, Mutex};
 
+use crate::error::{self, EncoderError};
 use crate::metadata::VideoMetadata;
 
 /// Duração da claquete (slate) que o encoder sempre adiciona no início.
@@ -12,6 +14,42 @@ pub const BLACK_DURATION_SECS: u64 = 2;
 /// Use isso pra calcular a duração comercial subtraindo do MXF gerado.
 pub const SLATE_BLACK_TOTAL_SECS: u64 = SLATE_DURATION_SECS + BLACK_DURATION_SECS;
 
+// Sessões de decode NVDEC (`-hwaccel cuda`) abertas ao mesmo tempo. As GPUs
+// têm limite próprio, independente de quantos núcleos de CPU sobram pro
+// batch paralelo: (limite, em uso). Limite 0 = sem limite.
+static GPU_SESSIONS: Mutex<(usize, usize)> = Mutex::new((0, 0));
+static GPU_FREED: Condvar = Condvar::new();
+
+/// Define quantos FFmpeg podem decodificar na GPU ao mesmo tempo (0 = sem limite).
+pub fn set_gpu_sessions(limit: usize) {
+    if let Ok(mut s) = GPU_SESSIONS.lock() {
+        s.0 = limit;
+    }
+    GPU_FREED.notify_all();
+}
+
+/// Vaga de decode na GPU; liberada no drop.
+struct GpuSession;
+
+impl GpuSession {
+    fn acquire() -> Self {
+        let mut s = GPU_SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        while s.0 > 0 && s.1 >= s.0 {
+            s = GPU_FREED.wait(s).unwrap_or_else(|e| e.into_inner());
+        }
+        s.1 += 1;
+        GpuSession
+    }
+}
+
+impl Drop for GpuSession {
+    fn drop(&mut self) {
+        let mut s = GPU_SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+        s.1 -= 1;
+        GPU_FREED.notify_one();
+    }
+}
+
 /// Retorna filtro FFmpeg para ajustar duração ao segundo exato.
 /// Frames a mais: trim. Frames faltando: congela último frame.
 fn duration_adjust_filter(metadata: &VideoMetadata) -> String {
@@ -32,12 +70,30 @@ fn duration_adjust_filter(metadata: &VideoMetadata) -> String {
     }
 }
 
-pub fn encode(
+/// Linha de comando legível (pro `--dry-run`): programa + argumentos, com
+/// aspas nos que têm espaço ou caracteres especiais.
+pub fn command_line(cmd: &Command) -> String {
+    let quote = |s: &str| {
+        if !s.is_empty() && !s.contains(|c: char| c.is_whitespace() || "\"';|&<>()[]$`*?".contains(c)) {
+            s.to_string()
+        } else {
+            format!("\"{}\"", s.replace('"', "\\\""))
+        }
+    };
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|a| quote(&a.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Monta o FFmpeg do MXF (claquete + preto + vídeo), sem executar.
+pub fn encode_command(
     slate_path: &Path,
     video_path: &Path,
     output_path: &Path,
     metadata: &VideoMetadata,
-) -> Result<()> {
+) -> Command {
     let slate_duration = SLATE_DURATION_SECS;
     let black_duration = BLACK_DURATION_SECS;
     let silence_duration = (slate_duration + black_duration) as i32;
@@ -121,8 +177,22 @@ pub fn encode(
     // Output format: MXF
     cmd.args(["-f", "mxf"]);
     cmd.arg(output_path);
+    cmd
+}
+
+pub fn encode(
+    slate_path: &Path,
+    video_path: &Path,
+    output_path: &Path,
+    metadata: &VideoMetadata,
+) -> Result<()> {
+    let slate_duration = SLATE_DURATION_SECS;
+    let black_duration = BLACK_DURATION_SECS;
+    let silence_duration = (slate_duration + black_duration) as i32;
+    let mut cmd = encode_command(slate_path, video_path, output_path, metadata);
 
     crate::log::emit("Executando FFmpeg...");
+    crate::log::debug(command_line(&cmd));
     crate::log::emit(format!(
         "  Slate: {}s | Black: {}s | Vídeo: {}s",
         slate_duration, black_duration, metadata.duration_secs
@@ -132,9 +202,10 @@ pub fn encode(
         silence_duration as u64 + metadata.duration_secs
     ));
 
-    let output = cmd
-        .output()
-        .context("Falha ao executar FFmpeg")?;
+    let output = {
+        let _gpu = GpuSession::acquire();
+        cmd.output().map_err(|e| error::spawn_failed(e, "Falha ao executar FFmpeg"))?
+    };
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -149,30 +220,30 @@ pub fn encode(
             .rev()
             .collect::<Vec<_>>()
             .join("\n");
-        bail!("FFmpeg falhou:\n{last_lines}");
+        bail!(EncoderError::EncodingFailed(format!("FFmpeg falhou:\n{last_lines}")));
     }
 
     crate::log::emit(format!("Encoding concluído: {}", output_path.display()));
     Ok(())
 }
 
-/// Encode versão agência: MP4 H.264 leve (~7MB) sem claquete
-pub fn encode_agency(
-    video_path: &Path,
-    output_path: &Path,
-    metadata: &VideoMetadata,
-) -> Result<()> {
-    // Calcular bitrate de vídeo para target ~7MB
-    // 7MB = 56000 kbit; desconta áudio 160kbps
+/// Bitrate do áudio da versão agência, em kbps.
+const AGENCY_AUDIO_KBPS: u64 = 160;
+
+/// Bitrate de vídeo da versão agência pra ficar em ~7MB.
+fn agency_video_kbps(metadata: &VideoMetadata) -> u64 {
+    // 7MB = 56000 kbit; desconta áudio 160kbps. Sem duração: 3000kbps
     let target_kbits = 56000u64;
-    let audio_kbps = 160u64;
-    let video_kbps = if metadata.duration_secs > 0 {
-        (target_kbits / metadata.duration_secs).saturating_sub(audio_kbps)
-    } else {
-        3000
-    };
+    let video_kbps = target_kbits
+        .checked_div(metadata.duration_secs)
+        .map_or(3000, |k| k.saturating_sub(AGENCY_AUDIO_KBPS));
     // Clamp: mínimo 500kbps, máximo 5000kbps
-    let video_kbps = video_kbps.max(500).min(5000);
+    video_kbps.clamp(500, 5000)
+}
+
+/// Monta o FFmpeg da versão agência, sem executar.
+pub fn agency_command(video_path: &Path, output_path: &Path, metadata: &VideoMetadata) -> Command {
+    let video_kbps = agency_video_kbps(metadata);
 
     let mut cmd = Command::new("ffmpeg");
     cmd.env("QT_LOGGING_RULES", "*=false");
@@ -216,13 +287,29 @@ pub fn encode_agency(
     // MP4 output
     cmd.args(["-movflags", "+faststart"]);
     cmd.arg(output_path);
+    cmd
+}
+
+/// Encode versão agência: MP4 H.264 leve (~7MB) sem claquete
+pub fn encode_agency(
+    video_path: &Path,
+    output_path: &Path,
+    metadata: &VideoMetadata,
+) -> Result<()> {
+    let video_kbps = agency_video_kbps(metadata);
+    let audio_kbps = AGENCY_AUDIO_KBPS;
+    let mut cmd = agency_command(video_path, output_path, metadata);
 
     crate::log::emit("Encodando versão agência (MP4 ~7MB)...");
+    crate::log::debug(command_line(&cmd));
     crate::log::emit(format!(
         "  Bitrate vídeo: {video_kbps}kbps | Áudio: {audio_kbps}kbps"
     ));
 
-    let output = cmd.output().context("Falha ao executar FFmpeg (agência)")?;
+    let output = {
+        let _gpu = GpuSession::acquire();
+        cmd.output().map_err(|e| error::spawn_failed(e, "Falha ao executar FFmpeg (agência)"))?
+    };
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -236,7 +323,7 @@ pub fn encode_agency(
             .rev()
             .collect::<Vec<_>>()
             .join("\n");
-        bail!("FFmpeg (agência) falhou:\n{last_lines}");
+        bail!(EncoderError::EncodingFailed(format!("FFmpeg (agência) falhou:\n{last_lines}")));
     }
 
     crate::log::emit(format!("Versão agência concluída: {}", output_path.display()));
@@ -296,3 +383,28 @@ fn build_audio_filters(metadata: &VideoMetadata, silence_duration: i32) -> Vec<S
 
     filters
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line() {
+        let meta = VideoMetadata {
+            duration_raw: 30.0,
+            duration_secs: 30,
+            width: 1920,
+            height: 1080,
+            fps_num: 30000,
+            fps_den: 1001,
+            audio_channels: 2,
+            has_audio: true,
+        };
+        let cmd = agency_command(Path::new("C:/in/FEV 17.mp4"), Path::new("out.mp4"), &meta);
+        let line = command_line(&cmd);
+        assert!(line.starts_with("ffmpeg -y -hide_banner"), "{line}");
+        assert!(line.contains(" -i \"C:/in/FEV 17.mp4\" "), "{line}");
+        assert!(line.contains(" -b:v 1706k "), "{line}");
+        assert!(line.ends_with(" out.mp4"), "{line}");
+    }
+}
@@ -1,7 +1,33 @@
+//! Erros tipados da biblioteca.
+//!
+//! As funções continuam devolvendo `anyhow::Result` (com contexto), mas a
+//! causa de cada falha conhecida é um [`EncoderError`]: quem chama acha o
+//! tipo com [`find`] em vez de comparar mensagens. A CLI usa
+//! [`EncoderError::exit_code`] como código de saída:
+//!
+//! | código | erro |
+//! |---|---|
+//! | 0 | sucesso |
+//! | 1 | outro erro |
+//! | 2 | uso inválido (argumentos) |
+//! | 3 | FFmpeg/FFprobe fora do PATH |
+//! | 4 | FFprobe não leu o vídeo |
+//! | 5 | código sem registro no codes.toml |
+//! | 6 | template da claquete ausente |
+//! | 7 | FFmpeg falhou |
+//! | 8 | configuração inválida ou ilegível |
+//! | 9 | batch/watch com vídeo(s) que falharam |
+//! | 10 | login/sessão do Peach |
+//! | 11 | resposta do init_upload sem credenciais STS |
+//! | 12 | upload no S3 |
+//! | 13 | spot rejeitado pelo QC do Peach |
+//! | 14 | validação do envio falhou |
+//! | 15 | upload no Drive |
+//! | 16 | resposta inesperada do Peach |
+
 use std::fmt;
 
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EncoderError {
     FfmpegNotFound,
     FfprobeError(String),
@@ -9,6 +35,85 @@ pub enum EncoderError {
     TemplateNotFound(String),
     EncodingFailed(String),
     ConfigError(String),
+    /// Lote terminou com vídeos que falharam (cada erro já foi mostrado).
+    BatchFailed { failed: usize, total: usize },
+    /// Login recusado ou sessão do Peach inativa.
+    PeachAuth(String),
+    /// `init_upload` não devolveu as credenciais STS esperadas.
+    StsParse(String),
+    /// Falha no multipart do S3.
+    S3(String),
+    /// Spot rejeitado pelo QC do Peach.
+    QcRejected(String),
+    /// `validate` do envio não passou (QC pendente, erro sem detalhe...).
+    ValidateFailed(String),
+    /// Falha no upload do MP4 agência pro Drive.
+    Drive(String),
+    /// Peach respondeu com status de erro numa chamada sem erro mais
+    /// específico.
+    PeachResponse(String),
+}
+
+impl EncoderError {
+    /// Código estável (JSON da CLI, logs).
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::FfmpegNotFound => "ffmpeg_not_found",
+            Self::FfprobeError(_) => "probe_failed",
+            Self::CodeNotFound(_) => "code_not_found",
+            Self::TemplateNotFound(_) => "template_not_found",
+            Self::EncodingFailed(_) => "encoding_failed",
+            Self::ConfigError(_) => "config",
+            Self::BatchFailed { .. } => "batch_failed",
+            Self::PeachAuth(_) => "peach_auth",
+            Self::StsParse(_) => "sts_parse",
+            Self::S3(_) => "s3",
+            Self::QcRejected(_) => "qc_rejected",
+            Self::ValidateFailed(_) => "validate_failed",
+            Self::Drive(_) => "drive",
+            Self::PeachResponse(_) => "peach_response",
+        }
+    }
+
+    /// Código de saída da CLI (tabela no topo do módulo).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::FfmpegNotFound => 3,
+            Self::FfprobeError(_) => 4,
+            Self::CodeNotFound(_) => 5,
+            Self::TemplateNotFound(_) => 6,
+            Self::EncodingFailed(_) => 7,
+            Self::ConfigError(_) => 8,
+            Self::BatchFailed { .. } => 9,
+            Self::PeachAuth(_) => 10,
+            Self::StsParse(_) => 11,
+            Self::S3(_) => 12,
+            Self::QcRejected(_) => 13,
+            Self::ValidateFailed(_) => 14,
+            Self::Drive(_) => 15,
+            Self::PeachResponse(_) => 16,
+        }
+    }
+
+    /// O que o operador pode fazer a respeito (mostrado na GUI).
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::FfmpegNotFound => "Instale o FFmpeg e adicione a pasta bin ao PATH.",
+            Self::FfprobeError(_) => "Confira se o arquivo é um vídeo válido e não está sendo copiado.",
+            Self::CodeNotFound(_) => "Cadastre o código no codes.toml do cliente ou informe o registro.",
+            Self::TemplateNotFound(_) => "Coloque o template em assets/template.png (1920x1080).",
+            Self::EncodingFailed(_) => "Veja a saída do FFmpeg no log.",
+            Self::ConfigError(_) => "Corrija o defaults.toml/codes.toml do cliente.",
+            Self::BatchFailed { .. } => "Corrija os vídeos com erro e rode de novo com --resume.",
+            Self::PeachAuth(_) => "Confira e-mail e senha em config/peach_credentials.toml.",
+            Self::StsParse(_) => "O MXF foi preservado; tente enviar de novo.",
+            Self::S3(_) => "O MXF foi preservado; envie de novo que o upload continua de onde parou.",
+            Self::QcRejected(_) => "Corrija o vídeo e envie de novo; veja o motivo no portal.",
+            Self::ValidateFailed(_) => "Tente distribuir mais tarde ou pelo portal.",
+            Self::Drive(_) => "O envio ao Peach não foi afetado; compartilhe o MP4 manualmente.",
+            Self::PeachResponse(_) => "Tente de novo mais tarde; se persistir, confira no portal.",
+        }
+    }
 }
 
 impl fmt::Display for EncoderError {
@@ -24,8 +129,36 @@ impl fmt::Display for EncoderError {
             }
             Self::EncodingFailed(msg) => write!(f, "Falha no encoding: {msg}"),
             Self::ConfigError(msg) => write!(f, "Erro na configuração: {msg}"),
+            Self::BatchFailed { failed, total } => write!(f, "{failed} de {total} vídeo(s) com erro"),
+            Self::PeachAuth(msg) => write!(f, "Falha de autenticação no Peach: {msg}"),
+            Self::StsParse(msg) => write!(f, "Resposta do Peach sem credenciais de upload: {msg}"),
+            Self::S3(msg) => write!(f, "Falha no upload S3: {msg}"),
+            Self::QcRejected(msg) => write!(f, "Rejeitado pelo QC do Peach: {msg}"),
+            Self::ValidateFailed(msg) => write!(f, "Validação do envio falhou: {msg}"),
+            Self::Drive(msg) => write!(f, "Falha no upload pro Drive: {msg}"),
+            Self::PeachResponse(msg) => write!(f, "Resposta inesperada do Peach: {msg}"),
         }
     }
 }
 
 impl std::error::Error for EncoderError {}
+
+/// Erro tipado mais externo da cadeia de `e`, se houver.
+pub fn find(e: &anyhow::Error) -> Option<&EncoderError> {
+    e.chain().find_map(|cause| cause.downcast_ref::<EncoderError>())
+}
+
+/// Código de saída da CLI pra `e` (1 quando não é um erro conhecido).
+pub fn exit_code(e: &anyhow::Error) -> i32 {
+    find(e).map(EncoderError::exit_code).unwrap_or(1)
+}
+
+/// Falha ao executar o FFmpeg/FFprobe: `FfmpegNotFound` se o binário não
+/// existe, senão o erro de sistema com `what` de contexto.
+pub(crate) fn spawn_failed(e: std::io::Error, what: &'static str) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        EncoderError::FfmpegNotFound.into()
+    } else {
+        anyhow::Error::new(e).context(what)
+    }
+}
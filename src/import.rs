@@ -0,0 +1,467 @@
+//! Importação da tabela de códigos (`codes.toml`) a partir de planilhas da
+//! agência (CSV ou XLSX).
+//!
+//! Cada linha da planilha vira uma entrada `código → registro ANCINE`. Título e
+//! duração, quando presentes, são gravados como comentário ao lado da entrada
+//! (o `codes.toml` continua sendo um mapa simples de strings).
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Nomes das colunas da planilha. Comparação sem acento e sem caixa.
+/// Se `None`, tenta os aliases comuns (ex: "código", "cod", "code").
+#[derive(Debug, Clone, Default)]
+pub struct ImportColumns {
+    pub code: Option<String>,
+    pub registro: Option<String>,
+    pub titulo: Option<String>,
+    pub duracao: Option<String>,
+}
+
+const CODE_ALIASES: &[&str] = &["codigo", "cod", "code", "numero", "n"];
+const REGISTRO_ALIASES: &[&str] = &["registro", "crt", "registro ancine", "ancine"];
+const TITULO_ALIASES: &[&str] = &["titulo", "title", "peca", "pieza", "nome"];
+const DURACAO_ALIASES: &[&str] = &["duracao", "duration", "tempo", "dur"];
+
+/// Uma linha válida da planilha.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeRow {
+    /// Linha na planilha (1 = cabeçalho), pra mensagens de erro.
+    pub line: usize,
+    pub code: u32,
+    pub registro: String,
+    pub titulo: String,
+    pub duracao: String,
+}
+
+impl CodeRow {
+    /// Comentário gravado ao lado da entrada no `codes.toml`.
+    pub fn comment(&self) -> String {
+        match (self.titulo.is_empty(), self.duracao.is_empty()) {
+            (true, true) => String::new(),
+            (false, true) => self.titulo.clone(),
+            (true, false) => self.duracao.clone(),
+            (false, false) => format!("{} ({})", self.titulo, self.duracao),
+        }
+    }
+}
+
+/// Resultado da leitura: linhas válidas + erros por linha.
+#[derive(Debug, Default)]
+pub struct ImportResult {
+    pub rows: Vec<CodeRow>,
+    pub errors: Vec<String>,
+}
+
+/// Lê a planilha (CSV ou XLSX, pela extensão) e valida cada linha.
+/// `sheet` escolhe a aba do XLSX (default: primeira).
+pub fn read_spreadsheet(
+    path: &Path,
+    columns: &ImportColumns,
+    sheet: Option<&str>,
+) -> Result<ImportResult> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    let table = match ext.as_str() {
+        "csv" | "txt" => read_csv(path)?,
+        "xlsx" | "xlsm" | "xls" | "ods" => read_xlsx(path, sheet)?,
+        _ => bail!(
+            "Formato não suportado: {} (use .csv ou .xlsx)",
+            path.display()
+        ),
+    };
+    parse_table(&table, columns)
+}
+
+/// Lê um CSV como tabela de strings. Detecta `;` (Excel pt-BR) ou `,`.
+fn read_csv(path: &Path) -> Result<Vec<Vec<String>>> {
+    let content = std::fs::read(path)
+        .with_context(|| format!("Não foi possível ler {}", path.display()))?;
+    // Excel pt-BR salva CSV em Windows-1252; aceita UTF-8 com ou sem BOM e cai pra Latin-1.
+    let text = match String::from_utf8(content) {
+        Ok(s) => s.trim_start_matches('\u{feff}').to_string(),
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    };
+    let first_line = text.lines().next().unwrap_or("");
+    let delimiter = if first_line.matches(';').count() > first_line.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut table = Vec::new();
+    for record in reader.records() {
+        let record = record.with_context(|| format!("Erro ao ler CSV {}", path.display()))?;
+        table.push(record.iter().map(|c| c.trim().to_string()).collect());
+    }
+    Ok(table)
+}
+
+/// Lê uma aba do XLSX como tabela de strings.
+fn read_xlsx(path: &Path, sheet: Option<&str>) -> Result<Vec<Vec<String>>> {
+    use calamine::{open_workbook_auto, Data, Reader};
+
+    let mut workbook = open_workbook_auto(path)
+        .with_context(|| format!("Não foi possível abrir {}", path.display()))?;
+    let sheet_name = match sheet {
+        Some(s) => s.to_string(),
+        None => workbook
+            .sheet_names()
+            .first()
+            .cloned()
+            .with_context(|| format!("Planilha sem abas: {}", path.display()))?,
+    };
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .with_context(|| format!("Aba '{sheet_name}' não encontrada em {}", path.display()))?;
+
+    let table = range
+        .rows()
+        .map(|row| {
+            row.iter()
+                .map(|cell| match cell {
+                    // Números inteiros vêm como float (17 → 17.0)
+                    Data::Float(f) if f.fract() == 0.0 => format!("{}", *f as i64),
+                    Data::Empty => String::new(),
+                    other => other.to_string().trim().to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    Ok(table)
+}
+
+/// Normaliza um cabeçalho: minúsculas, sem acentos, sem pontuação nas bordas.
+pub(crate) fn normalize_header(s: &str) -> String {
+    s.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'ì' | 'î' | 'ï' => 'i',
+            'ó' | 'ò' | 'ô' | 'õ' | 'ö' => 'o',
+            'ú' | 'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            other => other,
+        })
+        .collect::<String>()
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_string()
+}
+
+/// Acha o índice da coluna: nome explícito ou primeiro alias encontrado.
+fn find_column(headers: &[String], explicit: Option<&str>, aliases: &[&str]) -> Option<usize> {
+    let normalized: Vec<String> = headers.iter().map(|h| normalize_header(h)).collect();
+    match explicit {
+        Some(name) => {
+            let name = normalize_header(name);
+            normalized.iter().position(|h| *h == name)
+        }
+        None => aliases
+            .iter()
+            .find_map(|a| normalized.iter().position(|h| h == a)),
+    }
+}
+
+/// Converte a tabela (primeira linha = cabeçalho) em linhas validadas.
+fn parse_table(table: &[Vec<String>], columns: &ImportColumns) -> Result<ImportResult> {
+    let Some(headers) = table.first() else {
+        bail!("Planilha vazia");
+    };
+
+    let code_col = find_column(headers, columns.code.as_deref(), CODE_ALIASES)
+        .with_context(|| format!("Coluna de código não encontrada. Cabeçalho: {headers:?}"))?;
+    let registro_col = find_column(headers, columns.registro.as_deref(), REGISTRO_ALIASES)
+        .with_context(|| format!("Coluna de registro não encontrada. Cabeçalho: {headers:?}"))?;
+    let titulo_col = find_column(headers, columns.titulo.as_deref(), TITULO_ALIASES);
+    let duracao_col = find_column(headers, columns.duracao.as_deref(), DURACAO_ALIASES);
+    if columns.titulo.is_some() && titulo_col.is_none() {
+        bail!("Coluna de título '{}' não encontrada", columns.titulo.as_deref().unwrap_or(""));
+    }
+    if columns.duracao.is_some() && duracao_col.is_none() {
+        bail!("Coluna de duração '{}' não encontrada", columns.duracao.as_deref().unwrap_or(""));
+    }
+
+    let mut result = ImportResult::default();
+    let mut seen: HashMap<u32, usize> = HashMap::new();
+    let cell = |row: &Vec<String>, col: Option<usize>| -> String {
+        col.and_then(|c| row.get(c)).cloned().unwrap_or_default()
+    };
+
+    for (i, row) in table.iter().enumerate().skip(1) {
+        let line = i + 1;
+        if row.iter().all(|c| c.is_empty()) {
+            continue;
+        }
+        let code_str = cell(row, Some(code_col));
+        let registro = cell(row, Some(registro_col));
+
+        let code = match code_str.parse::<u32>() {
+            Ok(c) => c,
+            Err(_) => {
+                result
+                    .errors
+                    .push(format!("linha {line}: código inválido '{code_str}'"));
+                continue;
+            }
+        };
+        if let Err(e) = validate_registro(&registro) {
+            result.errors.push(format!("linha {line}: código {code}: {e}"));
+            continue;
+        }
+        let duracao = match normalize_duracao(&cell(row, duracao_col)) {
+            Ok(d) => d,
+            Err(e) => {
+                result.errors.push(format!("linha {line}: código {code}: {e}"));
+                continue;
+            }
+        };
+        if let Some(prev) = seen.insert(code, line) {
+            result
+                .errors
+                .push(format!("linha {line}: código {code} duplicado (já na linha {prev})"));
+            continue;
+        }
+
+        result.rows.push(CodeRow {
+            line,
+            code,
+            registro,
+            titulo: cell(row, titulo_col),
+            duracao,
+        });
+    }
+    Ok(result)
+}
+
+/// Valida o formato do registro ANCINE: 13 dígitos + traço + dígito verificador.
+/// Ex: "2024017422017-0".
+pub fn validate_registro(registro: &str) -> Result<()> {
+    let re = Regex::new(r"^\d{13}-\d$").unwrap();
+    if registro.is_empty() {
+        bail!("registro vazio");
+    }
+    if !re.is_match(registro) {
+        bail!("registro '{registro}' fora do formato 0000000000000-0");
+    }
+    Ok(())
+}
+
+/// Diferença entre a tabela atual e a importada.
+#[derive(Debug, Default)]
+pub struct CodesDiff {
+    pub added: Vec<(u32, String)>,
+    /// (código, registro atual, registro novo)
+    pub changed: Vec<(u32, String, String)>,
+    pub unchanged: usize,
+}
+
+impl CodesDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty()
+    }
+}
+
+pub fn diff(existing: &HashMap<u32, String>, rows: &[CodeRow]) -> CodesDiff {
+    let mut d = CodesDiff::default();
+    for row in rows {
+        match existing.get(&row.code) {
+            None => d.added.push((row.code, row.registro.clone())),
+            Some(old) if *old != row.registro => {
+                d.changed.push((row.code, old.clone(), row.registro.clone()))
+            }
+            Some(_) => d.unchanged += 1,
+        }
+    }
+    d.added.sort();
+    d.changed.sort();
+    d
+}
+
+/// Gera o conteúdo do `codes.toml` com a tabela atual + linhas importadas.
+///
+/// Edita o arquivo atual em vez de reescrevê-lo: comentários, linhas em
+/// branco e entradas que não foram reimportadas ficam como estão. Entradas
+/// ficam ordenadas por código.
+pub fn merge_codes_toml(existing_content: &str, rows: &[CodeRow]) -> Result<String> {
+    let mut doc: toml_edit::DocumentMut =
+        existing_content.parse().context("codes.toml atual é inválido")?;
+
+    if !doc.contains_key("codes") {
+        // Comentários soltos do arquivo (cabeçalho) ficam acima do [codes]
+        let mut header = doc.trailing().as_str().unwrap_or_default().to_string();
+        doc.set_trailing("");
+        if !header.is_empty() && !header.ends_with("\n\n") {
+            header.push('\n');
+        }
+        let mut codes = toml_edit::Table::new();
+        codes.decor_mut().set_prefix(header);
+        doc.insert("codes", toml_edit::Item::Table(codes));
+    }
+    let codes = doc["codes"]
+        .as_table_mut()
+        .context("[codes] do codes.toml não é uma tabela")?;
+
+    for row in rows {
+        let mut value = toml_edit::Value::from(row.registro.as_str());
+        let comment = row.comment();
+        if !comment.is_empty() {
+            value.decor_mut().set_suffix(format!("  # {comment}"));
+        }
+        codes.insert(&row.code.to_string(), toml_edit::Item::Value(value));
+    }
+    // Numéricos em ordem; chaves que não são código vão pro fim
+    let order = |k: &toml_edit::Key| (k.get().parse::<u32>().unwrap_or(u32::MAX), k.get().to_string());
+    codes.sort_values_by(|k1, _, k2, _| order(k1).cmp(&order(k2)));
+
+    let out = doc.to_string();
+    // Garante que o resultado continua parseável
+    toml::from_str::<toml::Value>(&out).context("codes.toml gerado é inválido")?;
+    Ok(out)
+}
+
+/// Normaliza a duração da planilha pra segundos (`30"`). Aceita `30`,
+/// `30"`, `30''`, `30s`, `30 seg` e `0:30`. Vazio continua vazio.
+pub fn normalize_duracao(duracao: &str) -> Result<String> {
+    let d = duracao.trim();
+    if d.is_empty() {
+        return Ok(String::new());
+    }
+    let secs_re = Regex::new(r#"^(\d{1,4})\s*(?:"|''|”|s|seg|segs|segundos)?$"#).unwrap();
+    let mmss_re = Regex::new(r"^(\d{1,2}):([0-5]\d)$").unwrap();
+    let secs = if let Some(c) = secs_re.captures(&d.to_lowercase()) {
+        c[1].parse::<u32>()?
+    } else if let Some(c) = mmss_re.captures(d) {
+        c[1].parse::<u32>()? * 60 + c[2].parse::<u32>()?
+    } else {
+        bail!("duração '{d}' inválida (use segundos, ex: 30\" ou 0:30)");
+    };
+    if secs == 0 {
+        bail!("duração '{d}' inválida: zero segundos");
+    }
+    Ok(format!("{secs}\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|r| r.iter().map(|c| c.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_table_aliases_and_validation() {
+        let t = table(&[
+            &["Código", "Título", "Duração", "Registro ANCINE"],
+            &["17", "PROMO ABRIL", "30\"", "2024017422017-0"],
+            &["18", "", "", "2024017422018"],
+            &["x", "", "", "2024017422019-7"],
+            &["17", "", "", "2024017422017-0"],
+            &["", "", "", ""],
+            &["20", "", "15\"", "2024017422020-3"],
+            &["21", "", "meio minuto", "2024017422021-1"],
+            &["22", "", "0:45", "2024017422022-0"],
+        ]);
+        let r = parse_table(&t, &ImportColumns::default()).unwrap();
+        assert_eq!(r.rows.len(), 3);
+        assert_eq!(r.rows[0].code, 17);
+        assert_eq!(r.rows[0].titulo, "PROMO ABRIL");
+        assert_eq!(r.rows[0].comment(), "PROMO ABRIL (30\")");
+        assert_eq!(r.rows[1].comment(), "15\"");
+        assert_eq!(r.rows[2].duracao, "45\"");
+        assert_eq!(r.errors.len(), 4);
+        assert!(r.errors[0].starts_with("linha 3:"));
+        assert!(r.errors[2].contains("duplicado"));
+        assert!(r.errors[3].contains("duração 'meio minuto'"));
+    }
+
+    #[test]
+    fn test_parse_table_explicit_columns() {
+        let t = table(&[&["A", "B"], &["5", "2024017422005-7"]]);
+        assert!(parse_table(&t, &ImportColumns::default()).is_err());
+        let cols = ImportColumns {
+            code: Some("a".into()),
+            registro: Some("B".into()),
+            ..Default::default()
+        };
+        let r = parse_table(&t, &cols).unwrap();
+        assert_eq!(r.rows[0].code, 5);
+        assert!(r.errors.is_empty());
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut existing = HashMap::new();
+        existing.insert(1, "2024017422001-4".to_string());
+        existing.insert(2, "2024017422002-2".to_string());
+        let row = |code, registro: &str| CodeRow {
+            line: 0,
+            code,
+            registro: registro.into(),
+            titulo: String::new(),
+            duracao: String::new(),
+        };
+        let d = diff(
+            &existing,
+            &[row(1, "2024017422001-4"), row(2, "2024017422099-2"), row(3, "2024017422003-0")],
+        );
+        assert_eq!(d.added, vec![(3, "2024017422003-0".to_string())]);
+        assert_eq!(d.changed.len(), 1);
+        assert_eq!(d.unchanged, 1);
+    }
+
+    #[test]
+    fn test_merge_codes_toml() {
+        let existing = "# Tabela do cliente\n\n[codes]\n2 = \"2024017422002-2\"  # antigo\n1 = \"2024017422001-4\"\n";
+        let rows = vec![CodeRow {
+            line: 2,
+            code: 1,
+            registro: "2024017422001-9".into(),
+            titulo: "NOVO".into(),
+            duracao: String::new(),
+        }];
+        let out = merge_codes_toml(existing, &rows).unwrap();
+        assert_eq!(
+            out,
+            "# Tabela do cliente\n\n[codes]\n1 = \"2024017422001-9\"  # NOVO\n2 = \"2024017422002-2\"  # antigo\n"
+        );
+
+        // Arquivo novo
+        let out = merge_codes_toml("", &rows).unwrap();
+        assert_eq!(out, "[codes]\n1 = \"2024017422001-9\"  # NOVO\n");
+
+        // Comentários e linhas que não são código dentro do [codes] ficam
+        let existing = "[codes]\n# Campanha de abril\n3 = \"2024017422003-0\"\nobs = \"manter\"\n";
+        let out = merge_codes_toml(existing, &rows).unwrap();
+        assert_eq!(
+            out,
+            "[codes]\n1 = \"2024017422001-9\"  # NOVO\n# Campanha de abril\n3 = \"2024017422003-0\"\nobs = \"manter\"\n"
+        );
+
+        // Só o cabeçalho (client new)
+        let out = merge_codes_toml("# Tabela\n", &rows).unwrap();
+        assert_eq!(out, "# Tabela\n\n[codes]\n1 = \"2024017422001-9\"  # NOVO\n");
+    }
+
+    #[test]
+    fn test_validate_registro() {
+        assert!(validate_registro("2024017422017-0").is_ok());
+        assert!(validate_registro("").is_err());
+        assert!(validate_registro("2024017422017").is_err());
+        assert!(validate_registro("2024017422017-00").is_err());
+    }
+}
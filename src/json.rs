@@ -0,0 +1,243 @@
+//! Saída estruturada da CLI (`--json`), pra outras ferramentas (agendador,
+//! scripts) dirigirem o encoder sem parsear o texto em português.
+//!
+//! Com `--json`, o log legível vai pro stderr e o stdout recebe só JSON: um
+//! objeto por linha, sempre com `command` e `ok`. Falhas trazem
+//! `error: { code, message }`; os códigos estão em [`error_code`].
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::error;
+use crate::metadata::VideoMetadata;
+
+/// Erro com código estável (pra máquina) e mensagem (pra gente).
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorInfo {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ErrorInfo {
+    pub fn from_error(e: &anyhow::Error) -> Self {
+        Self {
+            code: error_code(e),
+            message: format!("{e:#}"),
+        }
+    }
+}
+
+/// Código de um erro, pela causa mais específica encontrada na cadeia:
+///
+/// | código | causa |
+/// |---|---|
+/// | `ffmpeg_not_found` | FFmpeg/FFprobe fora do PATH |
+/// | `probe_failed` | FFprobe não leu o vídeo |
+/// | `code_not_found` | código sem registro no codes.toml |
+/// | `template_not_found` | template da claquete ausente |
+/// | `encoding_failed` | FFmpeg falhou |
+/// | `config` | configuração inválida ou ilegível |
+/// | `batch_failed` | batch/watch com vídeo(s) que falharam |
+/// | `peach_auth` | login/sessão do Peach |
+/// | `sts_parse` | init_upload sem credenciais STS |
+/// | `s3` | upload no S3 |
+/// | `qc_rejected` | spot rejeitado pelo QC |
+/// | `validate_failed` | validação do envio falhou |
+/// | `drive` | upload no Drive |
+/// | `peach_response` | resposta inesperada do Peach |
+/// | `network` | falha de rede/HTTP |
+/// | `io` | erro de arquivo |
+/// | `error` | qualquer outro |
+pub fn error_code(e: &anyhow::Error) -> &'static str {
+    if let Some(err) = error::find(e) {
+        return err.code();
+    }
+    for cause in e.chain() {
+        if cause.is::<toml::de::Error>() {
+            return "config";
+        }
+        if cause.is::<reqwest::Error>() {
+            return "network";
+        }
+    }
+    if e.chain().any(|c| c.is::<std::io::Error>()) {
+        return "io";
+    }
+    "error"
+}
+
+/// Resultado de um vídeo (comando principal, cada item do batch e do watch).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VideoReport {
+    pub video: PathBuf,
+    /// `encoded`, `uploaded`, `distributed`, `planned` (`--dry-run`), `skipped` ou `failed`.
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub titulo: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registro: Option<String>,
+    /// Duração do vídeo de origem (sem claquete), em segundos.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mxf: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mp4: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spot_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id_envio: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub destinos: Vec<String>,
+    /// Motivo de `skipped`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorInfo>,
+    /// Tempo gasto, em segundos.
+    pub elapsed_secs: f64,
+}
+
+/// `encoder probe`: metadados do vídeo e o que o nome do arquivo resolve.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeReport {
+    pub video: PathBuf,
+    pub metadata: VideoMetadata,
+    pub code: Option<u32>,
+    pub titulo: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versao: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idioma: Option<String>,
+    pub registro: Option<String>,
+    /// Motivo de `registro` nulo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// `encoder slate`: claquete gerada.
+#[derive(Debug, Clone, Serialize)]
+pub struct SlateReport {
+    pub video: PathBuf,
+    pub output: PathBuf,
+    pub titulo: String,
+    pub registro: String,
+    pub duracao: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    pub manifest: PathBuf,
+    pub total: usize,
+    pub done: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub elapsed_secs: f64,
+    pub items: Vec<VideoReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoginReport {
+    pub usuario: String,
+    pub email: String,
+    pub empresa: String,
+    pub id_empresa: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadReport {
+    pub video: PathBuf,
+    pub pieza: String,
+    pub codigo: String,
+    /// Duração comercial informada ao Peach (sem claquete).
+    pub duration_secs: u64,
+    pub size: u64,
+    /// SHA-256 do arquivo enviado (conferido contra o S3).
+    pub sha256: String,
+    pub spot_id: Option<u64>,
+    pub id_envio: String,
+    pub destination: String,
+    pub elapsed_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SendReport {
+    pub spot_ids: Vec<u64>,
+    pub destinos_hd: Vec<String>,
+    pub destinos_sd: Vec<String>,
+    pub summary: String,
+    pub elapsed_secs: f64,
+}
+
+/// Situação de um spot no Peach (`peach status` e `peach list`).
+#[derive(Debug, Clone, Serialize)]
+pub struct SpotReport {
+    pub spot_id: u64,
+    pub pieza: String,
+    pub codigo: String,
+    pub verificacion: String,
+    pub verificacion_manual: String,
+    /// O Peach já libera o envio (`spot_se_puede_enviar`).
+    pub sendable: bool,
+}
+
+impl From<&crate::peach::SpotStatus> for SpotReport {
+    fn from(s: &crate::peach::SpotStatus) -> Self {
+        Self {
+            spot_id: s.id_spot,
+            pieza: s.pieza.clone(),
+            codigo: s.codigo.clone(),
+            verificacion: s.verificacion.clone(),
+            verificacion_manual: s.verificacion_manual.clone(),
+            sendable: s.spot_se_puede_enviar,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpotsReport {
+    pub spots: Vec<SpotReport>,
+    /// Spots pedidos que não apareceram na listagem.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    pub all_sendable: bool,
+}
+
+/// Imprime uma linha JSON no stdout: `{"command": ..., "ok": ..., <campos>}`.
+pub fn print(command: &str, ok: bool, body: &impl Serialize) {
+    let mut value = serde_json::json!({ "command": command, "ok": ok });
+    if let (Some(obj), Ok(serde_json::Value::Object(fields))) =
+        (value.as_object_mut(), serde_json::to_value(body))
+    {
+        obj.extend(fields);
+    }
+    println!("{value}");
+}
+
+/// Imprime a falha de um comando inteiro.
+pub fn print_error(command: &str, e: &anyhow::Error) {
+    print(command, false, &serde_json::json!({ "error": ErrorInfo::from_error(e) }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EncoderError;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_code() {
+        let e = anyhow::Error::new(EncoderError::CodeNotFound(17)).context("FEV_17.mp4");
+        assert_eq!(error_code(&e), "code_not_found");
+        let e = anyhow::Error::new(EncoderError::QcRejected("spot 1".into())).context("distribuindo");
+        assert_eq!(error_code(&e), "qc_rejected");
+        assert_eq!(error::exit_code(&e), 13);
+        let e = std::fs::read("/nao/existe")
+            .context("lendo")
+            .unwrap_err();
+        assert_eq!(error_code(&e), "io");
+        assert_eq!(error_code(&anyhow::anyhow!("qualquer")), "error");
+        let info = ErrorInfo::from_error(&e);
+        assert!(info.message.starts_with("lendo: "));
+    }
+}
@@ -1,10 +1,16 @@
+pub mod batch;
+pub mod check;
 pub mod config;
 pub mod encoder;
 pub mod error;
+pub mod import;
+pub mod json;
 pub mod log;
 pub mod metadata;
+pub mod onboarding;
 pub mod peach;
 pub mod slate;
+pub mod watch;
 
 use anyhow::{bail, Result};
 use std::path::{Path, PathBuf};
@@ -25,7 +31,7 @@ pub fn find_template(exe_dir: &Path) -> Result<PathBuf> {
         }
     }
 
-    bail!(
-        "Template da claquete não encontrado. Coloque o arquivo em assets/template.png (1920x1080)"
-    );
+    bail!(error::EncoderError::TemplateNotFound(
+        "assets/template.png (1920x1080)".to_string()
+    ));
 }
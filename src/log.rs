@@ -1,29 +1,397 @@
-//! Log thread-local: encoder e demais módulos chamam `emit(...)` em vez de
-//! `println!`. A GUI configura um Sender no início de cada spawn pra capturar
-//! as mensagens e exibir num painel próprio.
+//! Log thread-local: encoder e demais módulos chamam `emit(...)` (ou
+//! `debug`/`warn`/`error`) em vez de `println!`. A GUI configura um Sender no
+//! início de cada spawn pra capturar os registros e exibir num painel próprio.
 //!
-//! Em modo console (CLI ou debug), ainda imprime no stdout pra facilitar
-//! diagnóstico via terminal.
+//! Cada registro tem nível, horário e o job da thread (se houver). O console
+//! (CLI ou debug) mostra só a mensagem, a partir de `info` (ou `debug` com
+//! `set_console_level`). Os registros completos vão pra:
+//!
+//! - o log do job (`begin_job` + `attach_job_file`), ao lado das saídas do
+//!   vídeo. O que foi emitido antes de saber onde ficam as saídas fica em
+//!   memória e é gravado quando o arquivo é anexado;
+//! - o log da aplicação (`init_app_log`), `encoder.log` rotacionado por
+//!   tamanho em `encoder.log.1`, `.2`...
+//!
+//! Com `--json` a CLI manda o log pro stderr (`set_console_stderr`), deixando
+//! o stdout só pro JSON.
+//!
+//! No batch paralelo cada worker define um prefixo (ex: `[3/10] `) pra
+//! identificar de qual vídeo é cada linha no console.
 
+use anyhow::{Context, Result};
 use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Nome do log da aplicação.
+pub const APP_LOG_FILE: &str = "encoder.log";
+/// Tamanho a partir do qual o log da aplicação é rotacionado.
+pub const APP_LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+/// Quantos arquivos antigos (`encoder.log.1`..) são mantidos.
+pub const APP_LOG_KEEP: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        Self::ALL.get(v as usize).copied().unwrap_or(Level::Info)
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Uma mensagem de log.
+#[derive(Debug, Clone)]
+pub struct Record {
+    pub level: Level,
+    pub time: chrono::DateTime<chrono::Local>,
+    pub job: Option<String>,
+    pub message: String,
+}
+
+impl Record {
+    /// Linhas no formato dos arquivos: `data hora NÍVEL [job] mensagem`
+    /// (mensagem de várias linhas vira várias linhas com o mesmo cabeçalho).
+    pub fn to_lines(&self) -> String {
+        let head = format!("{} {:<5}", self.time.format("%Y-%m-%d %H:%M:%S%.3f"), self.level.as_str());
+        let head = match &self.job {
+            Some(job) => format!("{head} [{job}]"),
+            None => head,
+        };
+        self.message
+            .lines()
+            .map(|l| format!("{head} {l}\n"))
+            .collect()
+    }
+
+    /// Versão curta pro painel da GUI: `hora NÍVEL mensagem`.
+    pub fn short(&self) -> String {
+        format!("{} {:<5} {}", self.time.format("%H:%M:%S"), self.level.as_str(), self.message)
+    }
+}
+
+/// Log de um job (um vídeo): em memória até `attach_job_file`, depois em disco.
+pub struct JobLog {
+    id: String,
+    sink: Mutex<JobSink>,
+}
+
+enum JobSink {
+    Buffer(String),
+    File(File),
+}
+
+impl JobLog {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn write(&self, text: &str) {
+        let mut sink = self.sink.lock().unwrap_or_else(|e| e.into_inner());
+        match &mut *sink {
+            JobSink::Buffer(buf) => buf.push_str(text),
+            JobSink::File(f) => {
+                let _ = f.write_all(text.as_bytes());
+            }
+        }
+    }
+}
 
 thread_local! {
-    static LOG_TX: RefCell<Option<Sender<String>>> = const { RefCell::new(None) };
+    static LOG_TX: RefCell<Option<Sender<Record>>> = const { RefCell::new(None) };
+    static PREFIX: RefCell<Option<String>> = const { RefCell::new(None) };
+    static JOB: RefCell<Option<Arc<JobLog>>> = const { RefCell::new(None) };
+}
+
+static CONSOLE_STDERR: AtomicBool = AtomicBool::new(false);
+static CONSOLE_LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+static APP_LOG: Mutex<Option<AppLog>> = Mutex::new(None);
+
+/// Imprime o log no stderr em vez do stdout (vale pra todas as threads).
+pub fn set_console_stderr(enabled: bool) {
+    CONSOLE_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn console_stderr() -> bool {
+    CONSOLE_STDERR.load(Ordering::Relaxed)
+}
+
+/// Nível mínimo mostrado no console (padrão `Info`). Arquivos e sender
+/// recebem tudo.
+pub fn set_console_level(level: Level) {
+    CONSOLE_LEVEL.store(level as u8, Ordering::Relaxed);
 }
 
 /// Configura o sender para a thread atual. Passe `None` pra resetar.
-pub fn set_sender(tx: Option<Sender<String>>) {
+pub fn set_sender(tx: Option<Sender<Record>>) {
     LOG_TX.with(|cell| *cell.borrow_mut() = tx);
 }
 
-/// Emite uma mensagem de log: imprime no stdout e envia pro sender da thread (se houver).
-pub fn emit(msg: impl Into<String>) {
-    let s = msg.into();
-    println!("{s}");
+/// Prefixo das mensagens da thread atual. Passe `None` pra remover.
+pub fn set_prefix(prefix: Option<String>) {
+    PREFIX.with(|cell| *cell.borrow_mut() = prefix);
+}
+
+/// Prefixo da thread atual (pra repassar a threads filhas).
+pub fn prefix() -> Option<String> {
+    PREFIX.with(|cell| cell.borrow().clone())
+}
+
+/// ID de job legível e único o bastante: `20261018-153012-FEV_17`.
+pub fn job_id_for(name: &str) -> String {
+    format!("{}-{name}", chrono::Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Inicia um job na thread atual. Termina quando o guard sai de escopo.
+pub fn begin_job(id: impl Into<String>) -> JobGuard {
+    let job = Arc::new(JobLog {
+        id: id.into(),
+        sink: Mutex::new(JobSink::Buffer(String::new())),
+    });
+    set_job(Some(job));
+    JobGuard(())
+}
+
+/// Encerra o job da thread atual ao sair de escopo.
+pub struct JobGuard(());
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        set_job(None);
+    }
+}
+
+/// Job da thread atual (pra repassar a threads filhas com `set_job`).
+pub fn job() -> Option<Arc<JobLog>> {
+    JOB.with(|cell| cell.borrow().clone())
+}
+
+pub fn set_job(job: Option<Arc<JobLog>>) {
+    JOB.with(|cell| *cell.borrow_mut() = job);
+}
+
+/// Grava o log do job atual em `path` (em modo append, pra upload e encode
+/// do mesmo vídeo caírem no mesmo arquivo), incluindo o que já foi emitido.
+/// Sem job na thread, não faz nada.
+pub fn attach_job_file(path: &Path) -> Result<()> {
+    let Some(job) = job() else { return Ok(()) };
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Não foi possível abrir o log {}", path.display()))?;
+    let mut sink = job.sink.lock().unwrap_or_else(|e| e.into_inner());
+    if let JobSink::Buffer(buf) = &*sink {
+        file.write_all(buf.as_bytes())
+            .with_context(|| format!("Não foi possível escrever o log {}", path.display()))?;
+    }
+    *sink = JobSink::File(file);
+    Ok(())
+}
+
+/// Log da aplicação, rotacionado por tamanho.
+struct AppLog {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+}
+
+impl AppLog {
+    fn open(dir: &Path, max_bytes: u64) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Não foi possível criar diretório: {}", dir.display()))?;
+        let path = dir.join(APP_LOG_FILE);
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Não foi possível abrir o log {}", path.display()))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+        })
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.size > 0 && self.size + text.len() as u64 > self.max_bytes {
+            if let Ok(rotated) = self.rotate() {
+                *self = rotated;
+            }
+        }
+        if self.file.write_all(text.as_bytes()).is_ok() {
+            self.size += text.len() as u64;
+        }
+    }
+
+    /// `encoder.log` → `.1` → `.2`... (o mais antigo some) e abre um novo.
+    fn rotate(&self) -> Result<Self> {
+        let path = |n: usize| match n {
+            0 => self.dir.join(APP_LOG_FILE),
+            n => self.dir.join(format!("{APP_LOG_FILE}.{n}")),
+        };
+        let _ = std::fs::remove_file(path(APP_LOG_KEEP));
+        for n in (0..APP_LOG_KEEP).rev() {
+            let _ = std::fs::rename(path(n), path(n + 1));
+        }
+        Self::open(&self.dir, self.max_bytes)
+    }
+}
+
+/// Pasta padrão do log da aplicação: `ENCODER_LOG_DIR`, ou `logs/` ao lado
+/// do executável.
+pub fn app_log_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("ENCODER_LOG_DIR") {
+        return PathBuf::from(dir);
+    }
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|d| d.join("logs")))
+        .unwrap_or_else(|| PathBuf::from("logs"))
+}
+
+/// Liga o log da aplicação em `dir/encoder.log` (vale pra todas as threads).
+pub fn init_app_log(dir: &Path) -> Result<()> {
+    let log = AppLog::open(dir, APP_LOG_MAX_BYTES)?;
+    *APP_LOG.lock().unwrap_or_else(|e| e.into_inner()) = Some(log);
+    Ok(())
+}
+
+/// Registra uma mensagem com nível: console, sender da thread, log do job e
+/// log da aplicação.
+pub fn log(level: Level, msg: impl Into<String>) {
+    let job = job();
+    let record = Record {
+        level,
+        time: chrono::Local::now(),
+        job: job.as_ref().map(|j| j.id.clone()),
+        message: msg.into(),
+    };
+
+    if level >= Level::from_u8(CONSOLE_LEVEL.load(Ordering::Relaxed)) {
+        let text = match level {
+            Level::Warn => format!("Aviso: {}", record.message),
+            Level::Error => format!("ERRO: {}", record.message),
+            _ => record.message.clone(),
+        };
+        let s = match prefix() {
+            Some(p) => text
+                .lines()
+                .map(|l| if l.is_empty() { String::new() } else { format!("{p}{l}") })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            None => text,
+        };
+        if console_stderr() {
+            eprintln!("{s}");
+        } else {
+            println!("{s}");
+        }
+    }
+
+    if !record.message.trim().is_empty() {
+        let lines = record.to_lines();
+        if let Some(job) = &job {
+            job.write(&lines);
+        }
+        if let Some(app) = APP_LOG.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            app.write(&lines);
+        }
+    }
+
     LOG_TX.with(|cell| {
         if let Some(tx) = cell.borrow().as_ref() {
-            let _ = tx.send(s);
+            let _ = tx.send(record);
         }
     });
 }
+
+/// Emite uma mensagem `info` (o nível de quase todo o log).
+pub fn emit(msg: impl Into<String>) {
+    log(Level::Info, msg);
+}
+
+pub fn debug(msg: impl Into<String>) {
+    log(Level::Debug, msg);
+}
+
+pub fn warn(msg: impl Into<String>) {
+    log(Level::Warn, msg);
+}
+
+pub fn error(msg: impl Into<String>) {
+    log(Level::Error, msg);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_file_and_rotation() {
+        let dir = std::env::temp_dir().join(format!("encoder_test_log_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Antes do arquivo, fica em memória; depois, vai direto pro disco.
+        let job_path = dir.join("FEV_17.log");
+        {
+            let _job = begin_job("job-1");
+            debug("antes");
+            attach_job_file(&job_path).unwrap();
+            warn("depois\nsegunda linha");
+        }
+        debug("fora do job");
+        let text = std::fs::read_to_string(&job_path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].contains("DEBUG [job-1] antes"));
+        assert!(lines[1].contains("WARN  [job-1] depois"));
+        assert!(lines[2].ends_with("[job-1] segunda linha"));
+
+        let mut app = AppLog::open(&dir, 40).unwrap();
+        app.write("primeira linha com uns 30 bytes\n");
+        app.write("segunda linha, que estoura\n");
+        app.write("terceira linha bem longa\n");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("encoder.log.2")).unwrap(),
+            "primeira linha com uns 30 bytes\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("encoder.log.1")).unwrap(),
+            "segunda linha, que estoura\n"
+        );
+        assert_eq!(std::fs::read_to_string(dir.join(APP_LOG_FILE)).unwrap(), "terceira linha bem longa\n");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,9 +1,12 @@
 use anyhow::{bail, Context, Result};
+use serde::Serialize;
 use serde_json::Value;
 use std::path::Path;
 use std::process::Command;
 
-#[derive(Debug, Clone)]
+use crate::error::{self, EncoderError};
+
+#[derive(Debug, Clone, Serialize)]
 pub struct VideoMetadata {
     pub duration_raw: f64,
     pub duration_secs: u64,
@@ -30,9 +33,11 @@ fn suppress_qt_warnings(cmd: &mut Command) -> &mut Command {
 pub fn check_ffmpeg() -> Result<()> {
     suppress_qt_warnings(Command::new("ffmpeg").arg("-version"))
         .output()
+        .map_err(|_| EncoderError::FfmpegNotFound)
         .context("FFmpeg não encontrado no PATH")?;
     suppress_qt_warnings(Command::new("ffprobe").arg("-version"))
         .output()
+        .map_err(|_| EncoderError::FfmpegNotFound)
         .context("FFprobe não encontrado no PATH")?;
     Ok(())
 }
@@ -51,15 +56,19 @@ pub fn probe(video_path: &Path) -> Result<VideoMetadata> {
         ])
         .arg(video_path)
         .output()
-        .context("Falha ao executar FFprobe")?;
+        .map_err(|e| error::spawn_failed(e, "Falha ao executar FFprobe"))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        bail!("FFprobe retornou erro: {stderr}");
+        bail!(EncoderError::FfprobeError(format!("FFprobe retornou erro: {stderr}")));
     }
+    parse_probe(&output.stdout)
+        .map_err(|e| EncoderError::FfprobeError(format!("{e:#}")).into())
+}
 
+fn parse_probe(stdout: &[u8]) -> Result<VideoMetadata> {
     let json: Value =
-        serde_json::from_slice(&output.stdout).context("Falha ao parsear saída do FFprobe")?;
+        serde_json::from_slice(stdout).context("Falha ao parsear saída do FFprobe")?;
 
     let streams = json["streams"]
         .as_array()
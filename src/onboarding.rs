@@ -0,0 +1,265 @@
+//! Cadastro de cliente novo (`encoder client new` e diálogo da GUI).
+//!
+//! Gera `config/<CLIENTE>/defaults.toml` e `codes.toml` no mesmo formato do
+//! `_EXEMPLO`, em vez de copiar a pasta na mão. O resultado passa pelo
+//! `config check` antes de ficar no disco.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::check;
+use crate::config;
+use crate::import::{self, CodeRow};
+
+/// Respostas do cadastro.
+#[derive(Debug, Clone, Default)]
+pub struct NewClient {
+    pub name: String,
+    pub produto: String,
+    pub produtora: String,
+    pub agencia: String,
+    pub anunciante: String,
+    pub diretor: String,
+    pub output: String,
+    pub peach: Option<NewClientPeach>,
+}
+
+/// Bloco `[peach]` do cliente novo (só os campos que variam por cliente).
+#[derive(Debug, Clone, Default)]
+pub struct NewClientPeach {
+    pub avisador_id: String,
+    pub cnpj_avisador: String,
+    pub id_marca: u32,
+    pub id_producto: u32,
+    pub agencia_id: String,
+    pub cnpj_agencia: String,
+    pub id_listas: Vec<u32>,
+    /// (id, nome) — nome pode ser vazio.
+    pub hd: Vec<(String, String)>,
+    pub sd: Vec<(String, String)>,
+}
+
+impl NewClient {
+    /// Começa com os campos da claquete herdados da config raiz.
+    pub fn from_root(config_dir: &Path, name: &str) -> Self {
+        let mut client = Self {
+            name: name.to_string(),
+            ..Self::default()
+        };
+        if let Ok(d) = config::load_defaults(config_dir) {
+            client.produto = d.produto;
+            client.produtora = d.produtora;
+            client.agencia = d.agencia;
+            client.anunciante = d.anunciante;
+            client.diretor = d.diretor;
+        }
+        client
+    }
+}
+
+/// Nome de pasta aceitável pra um cliente (sem separadores nem `..`).
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        bail!("Nome do cliente vazio");
+    }
+    if name.starts_with('.')
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' ' | '.'))
+    {
+        bail!("Nome do cliente inválido: '{name}' (use letras, números, '_', '-' ou espaço)");
+    }
+    Ok(())
+}
+
+/// Gera o conteúdo do `defaults.toml`.
+pub fn render_defaults(client: &NewClient) -> String {
+    let s = |v: &str| toml::Value::String(v.to_string()).to_string();
+    let mut out = format!(
+        "# Configuração do cliente {name}\n\
+         # Gerado por `encoder client new`. Campos ausentes são herdados de\n\
+         # config/defaults.toml (inclusive o bloco [extract]).\n\
+         # Para ver o resultado: encoder config show --client {name}\n\n",
+        name = client.name
+    );
+    out.push_str(&format!("produto = {}\n", s(&client.produto)));
+    out.push_str(&format!("produtora = {}\n", s(&client.produtora)));
+    out.push_str(&format!("agencia = {}\n", s(&client.agencia)));
+    out.push_str(&format!("anunciante = {}\n", s(&client.anunciante)));
+    out.push_str(&format!("diretor = {}\n", s(&client.diretor)));
+    out.push_str("\n# Diretório de saída padrão para este cliente.\n");
+    out.push_str("# Deixe vazio para salvar no mesmo diretório do vídeo.\n");
+    out.push_str(&format!("output = {}\n", s(&client.output)));
+
+    let Some(p) = &client.peach else {
+        return out;
+    };
+    out.push_str("\n# Configuração de envio para o Peach\n[peach]\n");
+    out.push_str(&format!("avisador_id = {}\n", s(&p.avisador_id)));
+    out.push_str(&format!("cnpj_avisador = {}\n", s(&p.cnpj_avisador)));
+    out.push_str(&format!("id_marca = {}\n", p.id_marca));
+    out.push_str(&format!("id_producto = {}\n", p.id_producto));
+    out.push_str(&format!("agencia_id = {}\n", s(&p.agencia_id)));
+    out.push_str(&format!("cnpj_agencia = {}\n", s(&p.cnpj_agencia)));
+
+    if p.id_listas.is_empty() && p.hd.is_empty() && p.sd.is_empty() {
+        return out;
+    }
+    let entries = |list: &[(String, String)]| -> String {
+        if list.is_empty() {
+            return "[]".to_string();
+        }
+        let mut a = String::from("[\n");
+        for (id, nome) in list {
+            if nome.is_empty() {
+                a.push_str(&format!("    {},\n", s(id)));
+            } else {
+                a.push_str(&format!("    {{ id = {}, nome = {} }},\n", s(id), s(nome)));
+            }
+        }
+        a.push(']');
+        a
+    };
+    out.push_str("\n# Destinos (emissoras) disponíveis para distribuição.\n");
+    out.push_str("# id_listas: IDs das listas de destinos salvas no portal Peach.\n");
+    out.push_str("[peach.destinos]\n");
+    let listas: Vec<String> = p.id_listas.iter().map(|n| n.to_string()).collect();
+    out.push_str(&format!("id_listas = [{}]\n", listas.join(", ")));
+    out.push_str(&format!("hd = {}\n", entries(&p.hd)));
+    out.push_str(&format!("sd = {}\n", entries(&p.sd)));
+    out
+}
+
+/// Gera o conteúdo do `codes.toml` (vazio ou com as linhas importadas).
+pub fn render_codes(rows: &[CodeRow]) -> Result<String> {
+    let header = "# Tabela de códigos ANCINE do cliente\n\
+                  # Cada entrada mapeia um número (extraído do nome do arquivo) para o registro ANCINE.\n\
+                  # Para atualizar a partir da planilha da agência: encoder codes import <arquivo> --client <CLIENTE>\n";
+    import::merge_codes_toml(header, rows)
+}
+
+/// Cria a pasta do cliente. Falha se ela já tiver um `defaults.toml` ou se
+/// o `config check` do resultado acusar erro (nesse caso nada fica no disco).
+/// Retorna a pasta criada e os avisos do check.
+pub fn create_client(
+    config_dir: &Path,
+    client: &NewClient,
+    codes: &[CodeRow],
+) -> Result<(PathBuf, Vec<check::Diagnostic>)> {
+    validate_name(&client.name)?;
+    let dir = config_dir.join(&client.name);
+    if dir.join("defaults.toml").exists() {
+        bail!("Cliente já existe: {}", dir.display());
+    }
+    let created_dir = !dir.exists();
+
+    let defaults = render_defaults(client);
+    let codes = render_codes(codes)?;
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Não foi possível criar {}", dir.display()))?;
+    let cleanup = || {
+        let _ = std::fs::remove_file(dir.join("defaults.toml"));
+        let _ = std::fs::remove_file(dir.join("codes.toml"));
+        if created_dir {
+            let _ = std::fs::remove_dir(&dir);
+        }
+    };
+    let write = || -> Result<()> {
+        std::fs::write(dir.join("defaults.toml"), &defaults)
+            .with_context(|| format!("Não foi possível escrever {}", dir.join("defaults.toml").display()))?;
+        std::fs::write(dir.join("codes.toml"), &codes)
+            .with_context(|| format!("Não foi possível escrever {}", dir.join("codes.toml").display()))?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        cleanup();
+        return Err(e);
+    }
+
+    let mut report = check::Report::default();
+    check::check_client(config_dir, Some(&client.name), &mut report);
+    if report.errors() > 0 {
+        cleanup();
+        let msgs: Vec<String> = report
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == check::Severity::Error)
+            .map(|d| d.message.clone())
+            .collect();
+        bail!("Configuração gerada inválida:\n  {}", msgs.join("\n  "));
+    }
+    Ok((dir, report.diagnostics))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> NewClient {
+        NewClient {
+            name: "NOVO".into(),
+            produto: "VÁRIOS".into(),
+            produtora: "POST.E".into(),
+            agencia: "C3 \"Comunicação\"".into(),
+            anunciante: "NOVO".into(),
+            diretor: "FULANO".into(),
+            output: "C:\\Videos\\NOVO".into(),
+            peach: Some(NewClientPeach {
+                avisador_id: "BRA0743".into(),
+                cnpj_avisador: "30689848000130".into(),
+                id_marca: 9758,
+                id_producto: 25322,
+                agencia_id: "BR0741".into(),
+                cnpj_agencia: "01936260000136".into(),
+                id_listas: vec![7428],
+                hd: vec![("BR_GLOBO_112".into(), "TV Santa Cruz".into()), ("BR1230".into(), String::new())],
+                sd: vec![],
+            }),
+        }
+    }
+
+    #[test]
+    fn test_render_defaults_roundtrip() {
+        let content = render_defaults(&sample());
+        let parsed: crate::peach::config::DefaultsWithPeach = toml::from_str(&content).unwrap();
+        assert_eq!(parsed.base.agencia, "C3 \"Comunicação\"");
+        assert_eq!(parsed.base.output, "C:\\Videos\\NOVO");
+        let peach = parsed.peach.unwrap();
+        assert_eq!(peach.id_producto, 25322);
+        assert_eq!(peach.destinos.id_listas, vec![7428]);
+        assert_eq!(peach.destinos.hd[0].label(), "TV Santa Cruz");
+        assert_eq!(peach.destinos.hd[1].id(), "BR1230");
+    }
+
+    #[test]
+    fn test_create_client() {
+        let dir = std::env::temp_dir().join(format!("encoder_test_onboarding_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("defaults.toml"),
+            "produto = \"P\"\nprodutora = \"POST.E\"\nagencia = \"A\"\nanunciante = \"B\"\ndiretor = \"D\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("codes.toml"), "[codes]\n").unwrap();
+
+        let mut client = sample();
+        client.output = String::new();
+        let (created, _) = create_client(&dir, &client, &[]).unwrap();
+        assert!(created.join("codes.toml").exists());
+        assert!(config::list_clients(&dir).contains(&"NOVO".to_string()));
+        assert!(config::load_codes_for(&dir, Some("NOVO")).unwrap().is_empty());
+        assert!(create_client(&dir, &client, &[]).is_err());
+
+        // CNPJ inválido: nada fica no disco
+        let mut bad = sample();
+        bad.name = "RUIM".into();
+        bad.output = String::new();
+        bad.peach.as_mut().unwrap().cnpj_agencia = "01936260000137".into();
+        let err = create_client(&dir, &bad, &[]).unwrap_err().to_string();
+        assert!(err.contains("cnpj_agencia"), "{err}");
+        assert!(!dir.join("RUIM").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,17 +1,58 @@
 //! Login e gerenciamento de sessão no Peach.
+//!
+//! Os cookies de cada conta ficam salvos em disco ([`session_dir`]), só
+//! legíveis pelo usuário: o próximo comando valida a sessão com
+//! `session_data` e só refaz o login se ela tiver caído. Durante o uso,
+//! qualquer chamada que volte com a sessão expirada refaz o login e é
+//! repetida uma vez ([`PeachClient::request`]).
 
 use anyhow::{bail, Context, Result};
-use reqwest::Client;
-use serde::Deserialize;
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-const BASE: &str = "https://latam.peachvideo.com";
+use super::retry::{self, RetryPolicy};
+use crate::error::EncoderError;
+
+pub const BASE: &str = "https://latam.peachvideo.com";
 const USER_AGENT: &str =
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:149.0) Gecko/20100101 Firefox/149.0";
 
+/// Caminhos cujos cookies são salvos (o PHP pode restringir o `Path`).
+const COOKIE_PATHS: &[&str] = &["/", "/amasv/", "/app/"];
+
 /// Cliente HTTP com cookie store, persistindo a sessão entre requests.
 pub struct PeachClient {
     pub(crate) http: Client,
+    jar: Arc<Jar>,
+    /// Onde salvar os cookies (`None` = só em memória).
+    session_file: Option<PathBuf>,
+    /// Se os cookies vieram do disco (sessão possivelmente reaproveitável).
+    restored: bool,
+    /// E-mail e senha do último login, pra relogar quando a sessão cai.
+    account: Mutex<Option<(String, String)>>,
+    retry: RetryPolicy,
+}
+
+/// Resposta já lida de uma chamada ao Peach.
+pub(crate) struct PeachResponse {
+    pub status: StatusCode,
+    /// URL final, depois dos redirects.
+    pub url: reqwest::Url,
+    pub body: String,
+}
+
+/// Cookies salvos de uma conta.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedSession {
+    email: String,
+    saved_at: String,
+    /// Pares `nome=valor` por caminho.
+    cookies: Vec<(String, String)>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -32,74 +73,253 @@ pub struct SessionInfo {
 }
 
 impl PeachClient {
+    /// Cliente com sessão só em memória.
     pub fn new() -> Result<Self> {
+        Self::build(None)
+    }
+
+    /// Cliente que guarda a sessão de `email` em [`session_dir`], carregando
+    /// os cookies salvos se houver.
+    pub fn for_account(email: &str) -> Result<Self> {
+        Self::build(Some(session_file(&session_dir(), email)))
+    }
+
+    fn build(session_file: Option<PathBuf>) -> Result<Self> {
+        let jar = Arc::new(Jar::default());
+        let restored = match &session_file {
+            Some(path) => match load_cookies(&jar, path) {
+                Ok(restored) => restored,
+                Err(e) => {
+                    crate::log::warn(format!("[peach] sessão salva ignorada: {e:#}"));
+                    false
+                }
+            },
+            None => false,
+        };
         let http = Client::builder()
             .user_agent(USER_AGENT)
-            .cookie_store(true)
+            .cookie_provider(jar.clone())
             .gzip(true)
             .brotli(true)
             .redirect(reqwest::redirect::Policy::limited(5))
             .build()
             .context("falha ao criar cliente HTTP")?;
-        Ok(Self { http })
+        Ok(Self {
+            http,
+            jar,
+            session_file,
+            restored,
+            account: Mutex::new(None),
+            retry: RetryPolicy::default(),
+        })
+    }
+
+    /// Troca a política de retry (padrão: [`RetryPolicy::default`]).
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Reaproveita a sessão salva se `session_data` confirmar que ainda está
+    /// ativa; senão faz login.
+    pub async fn connect(&self, email: &str, password: &str) -> Result<SessionInfo> {
+        if self.restored {
+            match self.session_data().await {
+                Ok(info) => {
+                    self.remember(email, password);
+                    crate::log::debug(format!("[peach] sessão salva reaproveitada ({email})"));
+                    return Ok(info);
+                }
+                Err(e) => crate::log::debug(format!("[peach] sessão salva expirou: {e:#}")),
+            }
+        }
+        self.login(email, password).await
     }
 
     /// Faz login + redirect 307 para `secure_login.php` + valida sessão.
     pub async fn login(&self, email: &str, password: &str) -> Result<SessionInfo> {
         let url = format!("{BASE}/login/login/login");
         let res = self
-            .http
-            .post(&url)
-            .form(&[
-                ("user_email", email),
-                ("user_password", password),
-                ("country", "BR"),
-                ("lang", "pt_BR"),
-            ])
-            .header("Origin", BASE)
-            .header(
-                "Referer",
-                format!("{BASE}/login/login/index?pais=BR"),
-            )
-            .send()
-            .await
-            .context("falha no POST de login")?;
-
-        if !res.status().is_success() {
-            bail!("login retornou status {}", res.status());
+            .send_retrying("login", false, &|http: &Client| {
+                http.post(&url)
+                    .form(&[
+                        ("user_email", email),
+                        ("user_password", password),
+                        ("country", "BR"),
+                        ("lang", "pt_BR"),
+                    ])
+                    .header("Origin", BASE)
+                    .header(
+                        "Referer",
+                        format!("{BASE}/login/login/index?pais=BR"),
+                    )
+            })
+            .await?;
+
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachAuth(format!("login retornou status {}", res.status)));
         }
 
         // O reqwest seguiu o redirect 307 e mantém os cookies.
         // Validamos chamando session_data.
-        self.session_data().await
+        let info = self.session_data().await?;
+        self.remember(email, password);
+        self.save_session(email);
+        Ok(info)
+    }
+
+    fn remember(&self, email: &str, password: &str) {
+        *self.account.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some((email.to_string(), password.to_string()));
+    }
+
+    /// Grava os cookies atuais; falha aqui só custa um login a mais.
+    fn save_session(&self, email: &str) {
+        let Some(path) = &self.session_file else { return };
+        if let Err(e) = save_cookies(&self.jar, path, email) {
+            crate::log::warn(format!("[peach] não foi possível salvar a sessão: {e:#}"));
+        }
+    }
+
+    /// Envia a request montada por `build` e lê a resposta, repetindo
+    /// falhas transitórias conforme a [`RetryPolicy`]. Se o Peach responder
+    /// com a sessão expirada (tela de login, 401/403), refaz o login com a
+    /// última conta usada e repete a chamada uma vez.
+    pub(crate) async fn request<F>(&self, what: &str, build: F) -> Result<PeachResponse>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        self.request_with(what, Retry::Transient, &build).await
+    }
+
+    /// Como [`request`](Self::request), mas sem retry de falhas transitórias:
+    /// pra chamadas que não podem ser repetidas às cegas.
+    pub(crate) async fn request_once<F>(&self, what: &str, build: F) -> Result<PeachResponse>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        self.request_with(what, Retry::Never, &build).await
+    }
+
+    /// Como [`request`](Self::request), mas só repete quando a request nem
+    /// chegou ao servidor (conexão recusada): pra chamadas que criam algo no
+    /// portal, onde um timeout pode ter sido processado.
+    pub(crate) async fn request_unsent<F>(&self, what: &str, build: F) -> Result<PeachResponse>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        self.request_with(what, Retry::Unsent, &build).await
+    }
+
+    async fn request_with<F>(&self, what: &str, mode: Retry, build: &F) -> Result<PeachResponse>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let send = || async {
+            match mode {
+                Retry::Transient => self.send_retrying(what, false, build).await,
+                Retry::Unsent => self.send_retrying(what, true, build).await,
+                Retry::Never => self
+                    .send_once(build)
+                    .await
+                    .with_context(|| format!("falha na chamada {what}")),
+            }
+        };
+        let res = send().await?;
+        // Sessão caída = a chamada não foi processada; relogar e repetir é seguro.
+        if !session_expired(&res) {
+            return Ok(res);
+        }
+        let account = self.account.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let Some((email, password)) = account else {
+            bail!(EncoderError::PeachAuth(format!("{what}: sessão expirada")));
+        };
+        crate::log::emit("[peach] Sessão expirada, refazendo login...");
+        self.login(&email, &password).await?;
+        let res = send().await?;
+        if session_expired(&res) {
+            bail!(EncoderError::PeachAuth(format!(
+                "{what}: sessão expirada mesmo após novo login"
+            )));
+        }
+        Ok(res)
+    }
+
+    /// Uma tentativa + as repetições da política pra falhas transitórias.
+    /// `unsent_only`: só repete conexão recusada (a request não saiu).
+    async fn send_retrying<F>(&self, what: &str, unsent_only: bool, build: &F) -> Result<PeachResponse>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let mut attempt = 1;
+        loop {
+            let outcome = self.send_once(build).await;
+            let reason = match &outcome {
+                Err(e) if unsent_only && e.is_connect() => e.to_string(),
+                _ if unsent_only => return outcome.with_context(|| format!("falha na chamada {what}")),
+                Ok(res) if retry::is_transient_status(res.status) => format!("status {}", res.status),
+                Err(e) if retry::is_transient_error(e) => e.to_string(),
+                _ => return outcome.with_context(|| format!("falha na chamada {what}")),
+            };
+            if attempt >= self.retry.attempts {
+                return outcome.with_context(|| format!("falha na chamada {what}"));
+            }
+            let delay = self.retry.delay(attempt);
+            crate::log::warn(format!(
+                "[peach] {what}: {reason}; tentando de novo em {:.1}s ({}/{})",
+                delay.as_secs_f64(),
+                attempt + 1,
+                self.retry.attempts
+            ));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_once<F>(&self, build: &F) -> reqwest::Result<PeachResponse>
+    where
+        F: Fn(&Client) -> RequestBuilder,
+    {
+        let res = build(&self.http).send().await?;
+        let status = res.status();
+        let url = res.url().clone();
+        let body = res.text().await?;
+        Ok(PeachResponse { status, url, body })
     }
 
     /// Valida que a sessão atual está ativa, retornando dados do usuário.
     pub async fn session_data(&self) -> Result<SessionInfo> {
         let res = self
-            .http
-            .post(format!("{BASE}/app/comun/session_data.php"))
-            .header("X-Requested-With", "XMLHttpRequest")
-            .header("Origin", BASE)
-            .header(
-                "Referer",
-                format!("{BASE}/amasv/app/index_general.php"),
-            )
-            .header("Content-Length", "0")
-            .send()
-            .await
-            .context("falha no POST session_data")?;
-
-        if !res.status().is_success() {
-            bail!("session_data retornou status {}", res.status());
+            .send_retrying("session_data", false, &|http: &Client| {
+                http.post(format!("{BASE}/app/comun/session_data.php"))
+                    .header("X-Requested-With", "XMLHttpRequest")
+                    .header("Origin", BASE)
+                    .header(
+                        "Referer",
+                        format!("{BASE}/amasv/app/index_general.php"),
+                    )
+                    .header("Content-Length", "0")
+            })
+            .await?;
+
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachAuth(format!("session_data retornou status {}", res.status)));
         }
 
-        let body = res.text().await.context("falha ao ler corpo session_data")?;
-        let info: SessionInfo = serde_json::from_str(&body)
-            .with_context(|| format!("falha ao parsear JSON session_data: {}", body))?;
+        let body = res.body;
+        let info: SessionInfo = serde_json::from_str(&body).map_err(|e| {
+            EncoderError::PeachAuth(format!("falha ao parsear JSON session_data ({e}): {body}"))
+        })?;
 
         if info.iniciada != 1 {
-            bail!("sessão não está ativa (iniciada={})", info.iniciada);
+            bail!(EncoderError::PeachAuth(format!(
+                "sessão não está ativa (iniciada={})",
+                info.iniciada
+            )));
         }
         Ok(info)
     }
@@ -109,3 +329,164 @@ impl PeachClient {
         BASE
     }
 }
+
+/// Resposta típica de sessão caída: 401/403, redirect pra tela de login ou
+/// o formulário de login no lugar do conteúdo pedido.
+fn session_expired(res: &PeachResponse) -> bool {
+    matches!(res.status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+        || res.url.path().starts_with("/login/")
+        || res.body.contains("name=\"user_password\"")
+}
+
+/// Pasta das sessões salvas: `ENCODER_SESSION_DIR`, ou a pasta de dados do
+/// usuário (`%LOCALAPPDATA%\encoder\peach`, `~/.local/state/encoder/peach`).
+pub fn session_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("ENCODER_SESSION_DIR") {
+        return PathBuf::from(dir);
+    }
+    let base = std::env::var_os("LOCALAPPDATA")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("XDG_STATE_HOME").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".local/state")))
+        .unwrap_or_else(std::env::temp_dir);
+    base.join("encoder").join("peach")
+}
+
+/// Um arquivo por conta; o nome é o hash do e-mail.
+fn session_file(dir: &Path, email: &str) -> PathBuf {
+    let digest = Sha256::digest(email.trim().to_lowercase().as_bytes());
+    let name: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    dir.join(format!("{name}.json"))
+}
+
+/// Carrega os cookies salvos em `jar`. `Ok(false)` se não há sessão salva.
+fn load_cookies(jar: &Jar, path: &Path) -> Result<bool> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("falha ao ler {}", path.display())),
+    };
+    let saved: SavedSession = serde_json::from_str(&text)
+        .with_context(|| format!("falha ao parsear {}", path.display()))?;
+    for (cookie_path, pair) in &saved.cookies {
+        let url: reqwest::Url = format!("{BASE}{cookie_path}").parse()?;
+        jar.add_cookie_str(&format!("{pair}; Path={cookie_path}"), &url);
+    }
+    Ok(!saved.cookies.is_empty())
+}
+
+/// Salva os cookies de `jar` em `path`, só com permissão do usuário.
+fn save_cookies(jar: &Jar, path: &Path, email: &str) -> Result<()> {
+    let mut cookies: Vec<(String, String)> = Vec::new();
+    for cookie_path in COOKIE_PATHS {
+        let url: reqwest::Url = format!("{BASE}{cookie_path}").parse()?;
+        let Some(header) = jar.cookies(&url) else { continue };
+        for pair in header.to_str().unwrap_or_default().split("; ") {
+            let name = pair.split('=').next().unwrap_or_default();
+            if !pair.is_empty() && !cookies.iter().any(|(_, p)| p.split('=').next() == Some(name)) {
+                cookies.push((cookie_path.to_string(), pair.to_string()));
+            }
+        }
+    }
+    let saved = SavedSession {
+        email: email.to_string(),
+        saved_at: chrono::Local::now().to_rfc3339(),
+        cookies,
+    };
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("falha ao criar {}", dir.display()))?;
+    }
+    write_private(path, serde_json::to_string_pretty(&saved)?.as_bytes())
+        .with_context(|| format!("falha ao gravar {}", path.display()))
+}
+
+/// Grava `data` num arquivo legível só pelo dono (0600 no Unix; no Windows
+/// vale a ACL da pasta do perfil).
+pub(crate) fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        opts.mode(0o600);
+        // arquivos criados antes com outra permissão
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    opts.open(path)?.write_all(data)
+}
+
+/// Apaga a sessão salva de `email` (próximo comando faz login de novo).
+pub fn forget_session(email: &str) -> Result<bool> {
+    let path = session_file(&session_dir(), email);
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e).with_context(|| format!("falha ao apagar {}", path.display())),
+    }
+}
+
+/// Quais falhas o `request_with` repete.
+#[derive(Clone, Copy)]
+enum Retry {
+    /// Conexão, timeout, 5xx...: chamadas que podem ser repetidas.
+    Transient,
+    /// Só conexão recusada: chamadas que criam algo no portal.
+    Unsent,
+    Never,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_jar_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("encoder-session-{}", std::process::id()));
+        let path = session_file(&dir, "Op@Example.com");
+        assert_eq!(path, session_file(&dir, "op@example.com"));
+
+        let jar = Jar::default();
+        let url: reqwest::Url = format!("{BASE}/").parse().unwrap();
+        jar.add_cookie_str("PHPSESSID=abc123; Path=/", &url);
+        jar.add_cookie_str("lang=pt_BR; Path=/", &url);
+        save_cookies(&jar, &path, "op@example.com").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let restored = Jar::default();
+        assert!(load_cookies(&restored, &path).unwrap());
+        let header = restored.cookies(&url).unwrap();
+        let header = header.to_str().unwrap();
+        assert!(header.contains("PHPSESSID=abc123"));
+        assert!(header.contains("lang=pt_BR"));
+
+        assert!(!load_cookies(&Jar::default(), &dir.join("nada.json")).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_session_expired() {
+        let res = |status, path: &str, body: &str| PeachResponse {
+            status,
+            url: format!("{BASE}{path}").parse().unwrap(),
+            body: body.to_string(),
+        };
+        let listing = "/amasv/app/modulos/reel/inc.reel.vista_img.php";
+        assert!(session_expired(&res(StatusCode::UNAUTHORIZED, listing, "")));
+        assert!(session_expired(&res(StatusCode::OK, "/login/login/index", "")));
+        assert!(session_expired(&res(
+            StatusCode::OK,
+            listing,
+            r#"<form><input type="password" name="user_password"></form>"#
+        )));
+        assert!(!session_expired(&res(StatusCode::OK, listing, r#"Spot["1"] = {}"#)));
+    }
+}
@@ -2,7 +2,9 @@
 
 use anyhow::{bail, Context, Result};
 use serde::Deserialize;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::error::EncoderError;
 
 /// Credenciais do usuário Peach + ID da produtora (post.e).
 /// Carregado de `config/peach_credentials.toml` (gitignored) ou env vars.
@@ -34,15 +36,16 @@ impl PeachCredentials {
         // 2. Arquivo
         let path = config_dir.join("peach_credentials.toml");
         if !path.exists() {
-            bail!(
+            bail!(EncoderError::ConfigError(format!(
                 "Credenciais do Peach não encontradas. Crie {} ou defina PEACH_EMAIL/PEACH_PASSWORD.",
                 path.display()
-            );
+            )));
         }
         let content = std::fs::read_to_string(&path)
             .with_context(|| format!("Não foi possível ler {}", path.display()))?;
-        let creds: Self = toml::from_str(&content)
-            .with_context(|| format!("Erro ao parsear {}", path.display()))?;
+        let creds: Self = toml::from_str(&content).map_err(|e| {
+            EncoderError::ConfigError(format!("erro ao parsear {}: {e}", path.display()))
+        })?;
         Ok(creds)
     }
 }
@@ -190,6 +193,14 @@ pub struct PeachConfig {
     /// Extrair da URL da pasta: https://drive.google.com/drive/folders/<ID_AQUI>
     #[serde(default)]
     pub drive_folder_id: String,
+
+    /// Retry das chamadas HTTP ao Peach (`[peach.retry]`).
+    #[serde(default)]
+    pub retry: super::retry::RetryPolicy,
+
+    /// Partes e paralelismo do upload S3 (`[peach.upload]`).
+    #[serde(default)]
+    pub upload: super::upload::UploadOptions,
 }
 
 impl PeachConfig {
@@ -245,17 +256,291 @@ pub struct DefaultsWithPeach {
 }
 
 impl DefaultsWithPeach {
-    /// Carrega `defaults.toml` (raiz ou cliente) com suporte ao bloco `[peach]`.
-    pub fn load(config_dir: &Path, client: Option<&str>) -> Result<Self> {
-        let dir = match client {
-            Some(name) => config_dir.join(name),
-            None => config_dir.to_path_buf(),
-        };
-        let path = dir.join("defaults.toml");
-        let content = std::fs::read_to_string(&path)
-            .with_context(|| format!("Não foi possível ler {}", path.display()))?;
-        let parsed: Self = toml::from_str(&content)
-            .with_context(|| format!("Erro ao parsear {}", path.display()))?;
+    /// Carrega o `defaults.toml` efetivo (raiz + cliente + campanha +
+    /// `overrides`) com suporte ao bloco `[peach]`.
+    pub fn load(
+        config_dir: &Path,
+        client: Option<&str>,
+        overrides: &[(String, String)],
+    ) -> Result<Self> {
+        let layered = crate::config::LayeredConfig::load_with(config_dir, client, overrides)?;
+        let parsed: Self = layered.deserialize()?;
+        layered.validate_extract(&parsed.base.extract)?;
         Ok(parsed)
     }
 }
+
+/// Grava campos no bloco `[peach]` do `defaults.toml` do cliente
+/// (`config_dir/<cliente>/defaults.toml`), sem mexer no resto do arquivo:
+/// chaves existentes têm o valor trocado (mantendo o comentário da linha),
+/// as que faltam entram no fim do bloco. Usado pelo `peach lookup --write`.
+pub fn write_peach_fields(
+    config_dir: &Path,
+    client: &str,
+    fields: &[(&str, toml::Value)],
+) -> Result<PathBuf> {
+    edit_client_defaults(config_dir, client, |doc| {
+        let peach = table_mut(doc, &["peach"])?;
+        for (key, value) in fields {
+            let value: toml_edit::Value = value
+                .to_string()
+                .parse()
+                .with_context(|| format!("valor inválido pra peach.{key}"))?;
+            set_value(peach, key, value);
+        }
+        Ok(())
+    })
+}
+
+/// Acrescenta emissoras (`hd`/`sd`) e listas (`id_listas`) ao
+/// `[peach.destinos]` do `defaults.toml` do cliente, pulando IDs que já
+/// estão lá. Uma lista gravada no cliente substitui a herdada, então ela
+/// parte da lista efetiva (raiz + cliente + campanha, sem `--set`): o que
+/// vinha herdado continua valendo. Retorna o arquivo e quantos itens
+/// entraram.
+pub fn add_peach_destinos(
+    config_dir: &Path,
+    client: &str,
+    hd: &[DestinoEntry],
+    sd: &[DestinoEntry],
+    id_listas: &[u32],
+) -> Result<(PathBuf, usize)> {
+    let merged = crate::config::LayeredConfig::load_with(config_dir, Some(client), &[])?;
+    let mut added = 0;
+    let path = edit_client_defaults(config_dir, client, |doc| {
+        let mut current: PeachDestinos = match merged.table.get("peach").and_then(|p| p.get("destinos")) {
+            Some(v) => v.clone().try_into().map_err(|e| {
+                EncoderError::ConfigError(format!("[peach.destinos] inválido: {e}"))
+            })?,
+            None => PeachDestinos::default(),
+        };
+
+        for (list, new) in [(&mut current.hd, hd), (&mut current.sd, sd)] {
+            for entry in new {
+                if !list.iter().any(|d| d.id() == entry.id()) {
+                    list.push(entry.clone());
+                    added += 1;
+                }
+            }
+        }
+        for id in id_listas {
+            if !current.id_listas.contains(id) {
+                current.id_listas.push(*id);
+                added += 1;
+            }
+        }
+
+        let table = table_mut(doc, &["peach", "destinos"])?;
+        if !id_listas.is_empty() {
+            let listas: toml_edit::Array = current.id_listas.iter().map(|&n| i64::from(n)).collect();
+            set_value(table, "id_listas", listas.into());
+        }
+        if !hd.is_empty() {
+            set_value(table, "hd", render_destinos(&current.hd).into());
+        }
+        if !sd.is_empty() {
+            set_value(table, "sd", render_destinos(&current.sd).into());
+        }
+        Ok(())
+    })?;
+    Ok((path, added))
+}
+
+/// Lista de destinos no mesmo formato do `encoder client new`: um por linha.
+fn render_destinos(list: &[DestinoEntry]) -> toml_edit::Array {
+    let mut array = toml_edit::Array::new();
+    for d in list {
+        let value: toml_edit::Value = match d {
+            DestinoEntry::Detailed { id, nome } if !nome.is_empty() => {
+                let mut t = toml_edit::InlineTable::new();
+                t.insert("id", id.as_str().into());
+                t.insert("nome", nome.as_str().into());
+                t.fmt();
+                t.into()
+            }
+            _ => d.id().into(),
+        };
+        array.push_formatted(value.decorated("\n    ", ""));
+    }
+    if !array.is_empty() {
+        array.set_trailing_comma(true);
+        array.set_trailing("\n");
+    }
+    array
+}
+
+/// Lê o `defaults.toml` do cliente, aplica `edit` e grava de volta.
+/// Comentários e formatação do que não foi editado ficam como estão.
+fn edit_client_defaults(
+    config_dir: &Path,
+    client: &str,
+    edit: impl FnOnce(&mut toml_edit::DocumentMut) -> Result<()>,
+) -> Result<PathBuf> {
+    let dir = config_dir.join(client);
+    if !dir.is_dir() {
+        bail!(EncoderError::ConfigError(format!(
+            "cliente '{client}' não existe ({} não é uma pasta)",
+            dir.display()
+        )));
+    }
+    let path = dir.join("defaults.toml");
+    let content = if path.exists() {
+        std::fs::read_to_string(&path)
+            .with_context(|| format!("Não foi possível ler {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let mut doc: toml_edit::DocumentMut = content.parse().map_err(|e| {
+        EncoderError::ConfigError(format!("erro ao parsear {}: {e}", path.display()))
+    })?;
+    edit(&mut doc)?;
+    std::fs::write(&path, doc.to_string())
+        .with_context(|| format!("Falha ao gravar {}", path.display()))?;
+    Ok(path)
+}
+
+/// Tabela `[a.b]` do documento, criada no fim do arquivo se não existir.
+fn table_mut<'a>(
+    doc: &'a mut toml_edit::DocumentMut,
+    path: &[&str],
+) -> Result<&'a mut toml_edit::Table> {
+    let has_content = !doc.as_table().is_empty();
+    let mut table = doc.as_table_mut();
+    for (i, key) in path.iter().enumerate() {
+        let last = i == path.len() - 1;
+        let item = table.entry(key).or_insert_with(|| {
+            let mut t = toml_edit::Table::new();
+            // Só a tabela pedida ganha cabeçalho; as de cima ficam implícitas
+            t.set_implicit(!last);
+            if has_content {
+                t.decor_mut().set_prefix("\n");
+            }
+            toml_edit::Item::Table(t)
+        });
+        let name = path[..=i].join(".");
+        table = item.as_table_mut().with_context(|| {
+            EncoderError::ConfigError(format!("`{name}` precisa ser uma tabela [{name}]"))
+        })?;
+        if last {
+            table.set_implicit(false);
+        }
+    }
+    Ok(table)
+}
+
+/// Troca (ou acrescenta no fim da tabela) `key = value`. Os comentários da
+/// linha do valor antigo são mantidos.
+fn set_value(table: &mut toml_edit::Table, key: &str, mut value: toml_edit::Value) {
+    match table.get_mut(key) {
+        // Troca só o valor: o comentário acima da chave fica com ela
+        Some(toml_edit::Item::Value(old)) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        _ => {
+            table.insert(key, toml_edit::Item::Value(value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_value() {
+        let edit = |content: &str, path: &[&str], fields: Vec<(&str, toml_edit::Value)>| {
+            let mut doc: toml_edit::DocumentMut = content.parse().unwrap();
+            let table = table_mut(&mut doc, path).unwrap();
+            for (k, v) in fields {
+                set_value(table, k, v);
+            }
+            doc.to_string()
+        };
+        let content = "\
+[video]
+fps = 30
+
+[peach]
+# Anunciante
+avisador_id = \"OLD\"    # ID do anunciante
+id_marca = 1
+
+[peach.destinos]
+hd = []
+";
+        let out = edit(
+            content,
+            &["peach"],
+            vec![("avisador_id", "BRA0743".into()), ("id_producto", 25322.into())],
+        );
+        assert!(out.contains("# Anunciante\navisador_id = \"BRA0743\"    # ID do anunciante\n"));
+        assert!(out.contains("id_marca = 1\nid_producto = 25322\n\n[peach.destinos]"));
+        assert!(out.starts_with("[video]\nfps = 30\n"));
+
+        let out = edit("[video]\nfps = 30\n", &["peach"], vec![("id_marca", 9.into())]);
+        assert_eq!(out, "[video]\nfps = 30\n\n[peach]\nid_marca = 9\n");
+
+        let out = edit("produto = \"P\"\n", &["peach", "destinos"], vec![("sd", render_destinos(&[]).into())]);
+        assert_eq!(out, "produto = \"P\"\n\n[peach.destinos]\nsd = []\n");
+
+        // Array de várias linhas é trocado por inteiro
+        let content = "[peach.destinos]\nid_listas = [1]\nhd = [\n    \"BR1\",  # [x]\n]\nsd = []\n";
+        let hd = render_destinos(&[
+            DestinoEntry::Id("BR1".into()),
+            DestinoEntry::Detailed { id: "BR2".into(), nome: "TV Dois".into() },
+        ]);
+        let listas: toml_edit::Array = [1i64, 2].into_iter().collect();
+        let out = edit(content, &["peach", "destinos"], vec![("hd", hd.into()), ("id_listas", listas.into())]);
+        assert_eq!(
+            out,
+            "[peach.destinos]\nid_listas = [1, 2]\nhd = [\n    \"BR1\",\n    { id = \"BR2\", nome = \"TV Dois\" },\n]\nsd = []\n"
+        );
+    }
+
+    #[test]
+    fn test_add_peach_destinos() {
+        let dir = std::env::temp_dir().join(format!("encoder_test_destinos_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("X")).unwrap();
+        std::fs::write(
+            dir.join("defaults.toml"),
+            "[peach.destinos]\nhd = [{ id = \"BR9\", nome = \"TV Raiz\" }]\nsd = [\"BR8\"]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("X/defaults.toml"),
+            "produto = \"P\"\n\n[peach.destinos]\nhd = [{ id = \"BR1\", nome = \"TV Um\" }]\n",
+        )
+        .unwrap();
+
+        let new = [
+            DestinoEntry::Id("BR1".into()),
+            DestinoEntry::Detailed { id: "BR2".into(), nome: "TV Dois".into() },
+        ];
+        let (path, added) = add_peach_destinos(&dir, "X", &new, &[], &[7428]).unwrap();
+        assert_eq!(added, 2);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let table: toml::Table = toml::from_str(&content).unwrap();
+        let destinos: PeachDestinos = table["peach"]["destinos"].clone().try_into().unwrap();
+        // O que já estava no cliente continua, com o nome original; o hd da
+        // raiz já não valia (o cliente tem o seu) e o sd herdado não mudou
+        assert_eq!(destinos.hd.len(), 2);
+        assert_eq!(destinos.hd[0].label(), "TV Um");
+        assert_eq!(destinos.hd[1].id(), "BR2");
+        assert_eq!(destinos.id_listas, vec![7428]);
+        assert!(destinos.sd.is_empty());
+
+        let (_, added) = add_peach_destinos(&dir, "X", &new, &[], &[7428]).unwrap();
+        assert_eq!(added, 0);
+
+        // sd gravado no cliente parte do herdado da raiz
+        let (path, added) = add_peach_destinos(&dir, "X", &[], &[DestinoEntry::Id("BR3".into())], &[]).unwrap();
+        assert_eq!(added, 1);
+        let table: toml::Table = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let destinos: PeachDestinos = table["peach"]["destinos"].clone().try_into().unwrap();
+        let sd: Vec<&str> = destinos.sd.iter().map(|d| d.id()).collect();
+        assert_eq!(sd, ["BR8", "BR3"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,234 @@
+//! Emissoras de destino e listas de destinos salvas da conta no Peach.
+//!
+//! Alimenta o `[peach.destinos]` do cliente sem copiar IDs (`BR_GLOBO_112`,
+//! `BR1230`...) e nomes à mão. Usa as mesmas chamadas do modal de destinos
+//! da tela "Enviar" do portal:
+//!
+//! - `destinos.php?accion=buscar` → emissoras habilitadas pra conta; as
+//!   sub-emisoras (afiliadas de uma rede) trazem o ID da emissora mãe;
+//! - `lista_destinos.php` (`accion=listar`) → listas salvas (`id_listas`).
+//!
+//! O portal não é consistente nos nomes dos campos entre as telas, então o
+//! parser procura as colunas pelo sufixo (`*ID_EMPRESA`, `*NOMBRE`,
+//! `*CIUDAD`...), sem diferenciar maiúsculas.
+//!
+//! **Experimental:** o formato das respostas foi deduzido do JavaScript do
+//! portal, sem uma resposta real capturada. Confira no portal os IDs
+//! gravados antes do primeiro envio. Sub-emisoras aparecem na busca mas não
+//! são gravadas: o envio vai como `<empresa>||<HD/SD>;` (sem sub-emisora) e
+//! o formato `<mãe>|<sub>|<HD/SD>` ainda não foi conferido.
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use super::auth::PeachClient;
+use super::config::DestinoEntry;
+use super::lookup::scalar_to_string;
+use crate::error::EncoderError;
+use crate::import::normalize_header;
+
+/// Emissora que pode receber spots da conta.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DestinoEmpresa {
+    /// `ID_EMPRESA` (ex: "BR_GLOBO_112")
+    pub id: String,
+    pub nome: String,
+    pub cidade: String,
+    /// Rede/cadeia (ex: "GLOBO"); vazio pra independentes.
+    pub rede: String,
+    /// Afiliadas que recebem pela emissora (vazio na maioria).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sub_emisoras: Vec<DestinoEmpresa>,
+}
+
+impl DestinoEmpresa {
+    /// Nome pro `nome` do `[peach.destinos]`: o nome da emissora, com a
+    /// cidade quando ela não faz parte do nome.
+    pub fn label(&self) -> String {
+        let nome = if self.nome.is_empty() { &self.id } else { &self.nome };
+        if self.cidade.is_empty() || normalize_header(nome).contains(&normalize_header(&self.cidade)) {
+            nome.clone()
+        } else {
+            format!("{nome} ({})", self.cidade)
+        }
+    }
+
+    /// Entrada pronta pro `hd`/`sd` do cliente.
+    pub fn to_entry(&self) -> DestinoEntry {
+        DestinoEntry::Detailed {
+            id: self.id.clone(),
+            nome: self.label(),
+        }
+    }
+
+    /// Busca sem acento e sem diferenciar maiúsculas por nome, cidade, rede
+    /// ou ID, inclusive nas sub-emisoras. Filtro vazio casa com tudo.
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = normalize_header(filter);
+        filter.is_empty() || self.matches_normalized(&filter)
+    }
+
+    fn matches_normalized(&self, filter: &str) -> bool {
+        [&self.id, &self.nome, &self.cidade, &self.rede]
+            .iter()
+            .any(|f| normalize_header(f).contains(filter))
+            || self.sub_emisoras.iter().any(|s| s.matches_normalized(filter))
+    }
+}
+
+/// Lista de destinos salva no portal (`id_listas`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DestinoLista {
+    pub id: u32,
+    pub nome: String,
+}
+
+impl PeachClient {
+    /// Emissoras de destino da conta, com as sub-emisoras aninhadas.
+    pub async fn list_destinos(&self) -> Result<Vec<DestinoEmpresa>> {
+        let url = format!("{}/amasv/app/modulos/enviar/destinos.php", self.base());
+        let res = self
+            .request("destinos.php", |http| {
+                http.post(&url)
+                    .form(&[("accion", "buscar"), ("q", "")])
+                    .header("X-Requested-With", "XMLHttpRequest")
+                    .header(
+                        "Referer",
+                        format!("{}/amasv/app/index_general.php", self.base()),
+                    )
+            })
+            .await?;
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachResponse(format!(
+                "destinos.php retornou status {}", res.status
+            )));
+        }
+        parse_destinos(&res.body)
+    }
+
+    /// Listas de destinos salvas da conta.
+    pub async fn list_destinos_listas(&self) -> Result<Vec<DestinoLista>> {
+        let url = format!(
+            "{}/amasv/app/modulos/enviar/lista_destinos.php",
+            self.base()
+        );
+        let res = self
+            .request("lista_destinos", |http| {
+                http.post(&url)
+                    .form(&[("accion", "listar")])
+                    .header("X-Requested-With", "XMLHttpRequest")
+                    .header(
+                        "Referer",
+                        format!("{}/amasv/app/index_general.php", self.base()),
+                    )
+            })
+            .await?;
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachResponse(format!(
+                "lista_destinos retornou status {}", res.status
+            )));
+        }
+        parse_listas(&res.body)
+    }
+}
+
+/// Linhas (objetos) da resposta: o próprio array ou o primeiro array dentro
+/// do objeto (`{"data": [...]}`, `{"data": {"destinos": [...]}}`).
+fn json_rows(value: &serde_json::Value) -> Vec<&serde_json::Map<String, serde_json::Value>> {
+    match value {
+        serde_json::Value::Array(list) => list.iter().filter_map(|v| v.as_object()).collect(),
+        serde_json::Value::Object(obj) => obj
+            .values()
+            .map(json_rows)
+            .find(|rows| !rows.is_empty())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Primeiro campo cuja chave (em maiúsculas) satisfaz `pred`.
+fn field(row: &serde_json::Map<String, serde_json::Value>, pred: impl Fn(&str) -> bool) -> String {
+    row.iter()
+        .find(|(k, _)| pred(&k.to_uppercase()))
+        .and_then(|(_, v)| scalar_to_string(v))
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn parse_destinos(body: &str) -> Result<Vec<DestinoEmpresa>> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).context("resposta inesperada do destinos.php")?;
+
+    let mut flat: Vec<(DestinoEmpresa, String)> = Vec::new();
+    for row in json_rows(&value) {
+        let is_parent = |k: &str| k.contains("PADRE") || k.contains("EXHIBIDOR");
+        let id = field(row, |k| k.ends_with("ID_EMPRESA") && !is_parent(k));
+        if id.is_empty() {
+            continue;
+        }
+        let empresa = DestinoEmpresa {
+            id,
+            nome: field(row, |k| k.ends_with("NOMBRE") && !k.contains("RED") && !is_parent(k)),
+            cidade: field(row, |k| k.contains("CIUDAD")),
+            rede: field(row, |k| {
+                k == "RED" || k.ends_with("_RED") || k.starts_with("RED_") || k.contains("CADENA")
+            }),
+            sub_emisoras: Vec::new(),
+        };
+        let parent = field(row, |k| is_parent(k) && k.contains("ID"));
+        flat.push((empresa, parent));
+    }
+
+    // Sub-emisoras entram embaixo da mãe; se a mãe não veio, ficam no topo.
+    let ids: Vec<String> = flat.iter().map(|(e, _)| e.id.clone()).collect();
+    let (subs, mut tops): (Vec<_>, Vec<_>) = flat
+        .into_iter()
+        .partition(|(e, parent)| !parent.is_empty() && *parent != e.id && ids.contains(parent));
+    for (sub, parent) in subs {
+        if let Some((top, _)) = tops.iter_mut().find(|(t, _)| t.id == parent) {
+            top.sub_emisoras.push(sub);
+        }
+    }
+    Ok(tops.into_iter().map(|(e, _)| e).collect())
+}
+
+fn parse_listas(body: &str) -> Result<Vec<DestinoLista>> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).context("resposta inesperada do lista_destinos.php")?;
+    Ok(json_rows(&value)
+        .into_iter()
+        .filter_map(|row| {
+            let id = field(row, |k| k.ends_with("ID_LISTA")).parse().ok()?;
+            let nome = field(row, |k| k.ends_with("NOMBRE"));
+            Some(DestinoLista { id, nome })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_destinos() {
+        let body = r#"{"data":[
+            {"ID_EMPRESA":"BR_GLOBO_112","EMPRESA_NOMBRE":"TV Santa Cruz","CIUDAD":"Itabuna","RED_NOMBRE":"GLOBO","ID_EMPRESA_PADRE":""},
+            {"ID_EMPRESA":"BR_GLOBO_113","EMPRESA_NOMBRE":"TV Santa Cruz Ilhéus","CIUDAD":"Ilhéus","RED_NOMBRE":"GLOBO","ID_EMPRESA_PADRE":"BR_GLOBO_112"},
+            {"ID_EMPRESA":"BR1230","EMPRESA_NOMBRE":"TV Vitória","CIUDAD":"Vitória","RED_NOMBRE":"RECORD"}
+        ]}"#;
+        let list = parse_destinos(body).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].id, "BR_GLOBO_112");
+        assert_eq!(list[0].rede, "GLOBO");
+        assert_eq!(list[0].sub_emisoras[0].id, "BR_GLOBO_113");
+        assert_eq!(list[0].label(), "TV Santa Cruz (Itabuna)");
+        assert_eq!(list[1].label(), "TV Vitória");
+
+        assert!(list[0].matches("ilheus"));
+        assert!(list[1].matches("record"));
+        assert!(!list[1].matches("globo"));
+
+        let listas = parse_listas(r#"[{"ID_LISTA":"7428","NOMBRE":"Globo ES"}]"#).unwrap();
+        assert_eq!(listas, vec![DestinoLista { id: 7428, nome: "Globo ES".into() }]);
+    }
+}
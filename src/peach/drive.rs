@@ -12,6 +12,8 @@ use base64::Engine;
 use std::io::Write;
 use std::path::Path;
 
+use crate::error::EncoderError;
+
 /// Resultado do upload no Drive.
 #[derive(Debug, Clone)]
 pub struct DriveUploadResult {
@@ -30,6 +32,16 @@ pub async fn upload_mp4_zipped(
     webhook_url: &str,
     mp4_path: &Path,
     folder_id: &str,
+) -> Result<DriveUploadResult> {
+    upload_zipped(webhook_url, mp4_path, folder_id)
+        .await
+        .map_err(|e| EncoderError::Drive(format!("{e:#}")).into())
+}
+
+async fn upload_zipped(
+    webhook_url: &str,
+    mp4_path: &Path,
+    folder_id: &str,
 ) -> Result<DriveUploadResult> {
     if webhook_url.is_empty() {
         bail!("webhook_url vazio");
@@ -0,0 +1,196 @@
+//! Busca de anunciante, marca, produto e agência no Peach.
+//!
+//! São os mesmos autocompletes do formulário de upload do portal
+//! (`AviMarPro2.php`, um select2 paginado), mais o `busca_CNPJ.php` que o
+//! portal chama ao escolher uma empresa. Servem pra montar o bloco `[peach]`
+//! de um cliente novo sem caçar IDs no HTML do portal.
+//!
+//! A resposta do `AviMarPro2.php` é JSON no formato:
+//!
+//! ```json
+//! {"data": {"avisadores": [{"avisador_ID_EMPRESA": "BRA0743", "avisador_NOMBRE": "..."}]}}
+//! ```
+//!
+//! com `marcas` (`marca_ID_MARCA`) e `productos` (`producto_ID_PRODUCTO`)
+//! no mesmo esquema.
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::Serialize;
+
+use super::auth::PeachClient;
+use crate::error::EncoderError;
+
+/// Um resultado da busca.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LookupEntry {
+    /// ID no Peach (ex: "BRA0743" pra empresa, "1234" pra marca/produto)
+    pub id: String,
+    pub nome: String,
+    /// CNPJ (só dígitos), quando a entrada é uma empresa e o Peach conhece.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnpj: Option<String>,
+}
+
+impl PeachClient {
+    /// Busca anunciantes pelo nome.
+    pub async fn lookup_avisadores(&self, q: &str) -> Result<Vec<LookupEntry>> {
+        self.avi_mar_pro("avisador", "", "", q).await
+    }
+
+    /// Busca agências pelo nome.
+    pub async fn lookup_agencias(&self, q: &str) -> Result<Vec<LookupEntry>> {
+        self.avi_mar_pro("agencia", "", "", q).await
+    }
+
+    /// Lista as marcas de um anunciante (filtradas por `q`, se não vazio).
+    pub async fn lookup_marcas(&self, id_avisador: &str, q: &str) -> Result<Vec<LookupEntry>> {
+        self.avi_mar_pro("marca", id_avisador, "", q).await
+    }
+
+    /// Lista os produtos de uma marca (filtrados por `q`, se não vazio).
+    pub async fn lookup_productos(
+        &self,
+        id_avisador: &str,
+        id_marca: u32,
+        q: &str,
+    ) -> Result<Vec<LookupEntry>> {
+        self.avi_mar_pro("producto", id_avisador, &id_marca.to_string(), q)
+            .await
+    }
+
+    /// CNPJ de uma empresa (anunciante ou agência). `None` se o Peach não tiver.
+    pub async fn lookup_cnpj(&self, id_empresa: &str) -> Result<Option<String>> {
+        let url = format!("{}/app/comun/busca_CNPJ.php", self.base());
+        let res = self
+            .request("busca_CNPJ.php", |http| {
+                http.get(&url)
+                    .query(&[("accion", "buscar"), ("id_empresa", id_empresa)])
+                    .header("X-Requested-With", "XMLHttpRequest")
+            })
+            .await?;
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachResponse(format!(
+                "busca_CNPJ.php retornou status {}", res.status
+            )));
+        }
+        Ok(parse_cnpj(&res.body))
+    }
+
+    async fn avi_mar_pro(
+        &self,
+        item: &str,
+        id_avisador: &str,
+        id_marca: &str,
+        q: &str,
+    ) -> Result<Vec<LookupEntry>> {
+        let url = format!("{}/amasv/app/comun/AviMarPro2.php", self.base());
+        let res = self
+            .request("AviMarPro2.php", |http| {
+                http.get(&url)
+                    .query(&[
+                        ("item", item),
+                        ("id_avisador", id_avisador),
+                        ("id_marca", id_marca),
+                        ("elecciones", "0"),
+                        ("q", q),
+                        ("page", "1"),
+                    ])
+                    .header("X-Requested-With", "XMLHttpRequest")
+            })
+            .await?;
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachResponse(format!(
+                "AviMarPro2.php ({item}) retornou status {}", res.status
+            )));
+        }
+        parse_lookup(&res.body, item)
+    }
+}
+
+/// Extrai a lista de `item` da resposta do `AviMarPro2.php`.
+///
+/// O ID é o campo `<item>_ID_*` e o nome `<item>_NOMBRE`. Se a lista não vier
+/// na chave esperada (`<item>s`/`<item>es`), usa o primeiro array de `data`.
+fn parse_lookup(body: &str, item: &str) -> Result<Vec<LookupEntry>> {
+    let value: serde_json::Value = serde_json::from_str(body)
+        .with_context(|| format!("resposta inesperada do AviMarPro2.php ({item})"))?;
+    let Some(data) = value.get("data").and_then(|d| d.as_object()) else {
+        return Ok(Vec::new());
+    };
+    let list = [format!("{item}s"), format!("{item}es")]
+        .iter()
+        .find_map(|k| data.get(k).and_then(|v| v.as_array()))
+        .or_else(|| data.values().find_map(|v| v.as_array()));
+    let Some(list) = list else {
+        return Ok(Vec::new());
+    };
+
+    let id_prefix = format!("{item}_ID_");
+    let name_key = format!("{item}_NOMBRE");
+    let entries = list
+        .iter()
+        .filter_map(|e| e.as_object())
+        .filter_map(|obj| {
+            let id = obj
+                .iter()
+                .find(|(k, _)| k.starts_with(&id_prefix))
+                .and_then(|(_, v)| scalar_to_string(v))?;
+            let nome = obj
+                .get(&name_key)
+                .and_then(scalar_to_string)
+                .unwrap_or_default();
+            Some(LookupEntry {
+                id,
+                nome: nome.trim().to_string(),
+                cnpj: None,
+            })
+        })
+        .collect();
+    Ok(entries)
+}
+
+/// Procura um CNPJ (com ou sem pontuação) na resposta do `busca_CNPJ.php`.
+fn parse_cnpj(body: &str) -> Option<String> {
+    let re = Regex::new(r"\d{2}\.?\d{3}\.?\d{3}/?\d{4}-?\d{2}").unwrap();
+    let found = re.find(body)?;
+    Some(found.as_str().chars().filter(|c| c.is_ascii_digit()).collect())
+}
+
+pub(super) fn scalar_to_string(v: &serde_json::Value) -> Option<String> {
+    match v {
+        serde_json::Value::String(s) if !s.is_empty() => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lookup_and_cnpj() {
+        let body = r#"{"data":{"avisadores":[
+            {"avisador_ID_EMPRESA":"BRA0743","avisador_NOMBRE":"SIPOLATTI ","foo":1},
+            {"avisador_ID_EMPRESA":"BRA0999","avisador_NOMBRE":"OUTRO"}
+        ]},"pagination":{"more":false}}"#;
+        let list = parse_lookup(body, "avisador").unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].id, "BRA0743");
+        assert_eq!(list[0].nome, "SIPOLATTI");
+
+        let body = r#"{"data":{"marcas":[{"marca_ID_MARCA":1234,"marca_NOMBRE":"NATAL"}]}}"#;
+        let list = parse_lookup(body, "marca").unwrap();
+        assert_eq!(list[0].id, "1234");
+
+        assert!(parse_lookup(r#"{"data":[]}"#, "producto").unwrap().is_empty());
+
+        assert_eq!(
+            parse_cnpj(r#"{"CNPJ":"01.936.260/0001-36"}"#).as_deref(),
+            Some("01936260000136")
+        );
+        assert_eq!(parse_cnpj(r#"[{"cnpj":"01936260000136"}]"#).as_deref(), Some("01936260000136"));
+        assert_eq!(parse_cnpj(r#"{"CNPJ":""}"#), None);
+    }
+}
@@ -12,17 +12,23 @@
 
 pub mod auth;
 pub mod config;
+pub mod destinos;
 pub mod drive;
+pub mod lookup;
+pub mod retry;
 pub mod send;
 pub mod status;
 pub mod upload;
 
 pub use auth::{PeachClient, SessionInfo};
 pub use config::{DestinoEntry, PeachConfig, PeachCredentials, PeachDestinos};
+pub use destinos::{DestinoEmpresa, DestinoLista};
 pub use drive::{upload_mp4_zipped, DriveUploadResult};
+pub use lookup::LookupEntry;
+pub use retry::RetryPolicy;
 pub use send::{SendRequest, ValidateResponse};
-pub use status::SpotStatus;
-pub use upload::{StsCredentials, UploadParams};
+pub use status::{SpotFilter, SpotKey, SpotStatus};
+pub use upload::{StsCredentials, UploadOptions, UploadParams};
 
 use anyhow::Result;
 use std::path::Path;
@@ -38,10 +44,10 @@ pub async fn login_and_upload<F>(
 where
     F: Fn(u64, u64) + Send + Sync + 'static,
 {
-    let client = PeachClient::new()?;
-    let session = client.login(&credentials.email, &credentials.password).await?;
-    let sts = client.init_upload(params, cfg, &credentials.productora_id).await?;
-    upload::s3_multipart_upload(params.video_path, &sts, on_progress).await?;
+    let client = PeachClient::for_account(&credentials.email)?.with_retry(cfg.retry.clone());
+    let session = client.connect(&credentials.email, &credentials.password).await?;
+    let sts = client.init_or_resume_upload(params, cfg, &credentials.productora_id).await?;
+    upload::s3_multipart_upload(params.video_path, &sts, &cfg.upload, on_progress).await?;
     Ok(format!(
         "Upload OK | id_envio={} | usuário={} ({})",
         sts.id_envio, session.nombre_usuario_activo, session.id_empresa
@@ -54,14 +60,14 @@ pub fn registro_to_codigo(registro: &str) -> String {
     registro.replace('-', "")
 }
 
-/// Resolve o `codigo` (registro ANCINE sem traço) a partir do nome do arquivo
-/// e da tabela de codes.toml carregada.
+/// Resolve o `codigo` (registro ANCINE sem traço) a partir do nome do arquivo,
+/// das regras `[extract]` do cliente e da tabela de codes.toml carregada.
 pub fn resolve_codigo_from_filename(
     filename: &str,
     codes: &std::collections::HashMap<u32, String>,
+    rules: &crate::config::ExtractRules,
 ) -> Option<String> {
-    let code = crate::config::extract_code_from_filename(filename)?;
-    let registro = crate::config::lookup_registro(code, codes)?;
+    let registro = rules.resolve_registro(filename, codes)?;
     Some(registro_to_codigo(&registro))
 }
 
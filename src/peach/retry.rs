@@ -0,0 +1,123 @@
+//! Política de retry das chamadas HTTP ao Peach.
+//!
+//! Falhas transitórias (conexão caída, timeout, 5xx, 429) são repetidas com
+//! backoff exponencial e jitter. Configurável por cliente no bloco
+//! `[peach.retry]` do `defaults.toml`:
+//!
+//! ```toml
+//! [peach.retry]
+//! attempts = 4          # total de tentativas (1 = sem retry)
+//! backoff_ms = 1000     # espera antes da 2ª tentativa; dobra a cada nova
+//! max_backoff_ms = 30000
+//! jitter = 0.2          # ±20% aleatório na espera
+//! ```
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total de tentativas, contando a primeira.
+    pub attempts: u32,
+    /// Espera antes da segunda tentativa (ms); dobra a cada tentativa.
+    pub backoff_ms: u64,
+    /// Teto da espera (ms).
+    pub max_backoff_ms: u64,
+    /// Fração da espera sorteada pra mais ou pra menos (0.0 a 1.0).
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            backoff_ms: 1000,
+            max_backoff_ms: 30_000,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Espera antes da tentativa `attempt + 1` (`attempt` começa em 1).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = self
+            .backoff_ms
+            .saturating_mul(1u64 << attempt.saturating_sub(1).min(20))
+            .min(self.max_backoff_ms);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + jitter * (2.0 * random_unit() - 1.0);
+        Duration::from_millis((exp as f64 * factor) as u64)
+    }
+}
+
+/// Status HTTP que vale repetir.
+pub(crate) fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+/// Erro de rede que vale repetir (conexão recusada/resetada, timeout).
+pub(crate) fn is_transient_error(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout() || e.is_request() || e.is_body()
+}
+
+/// Procura um erro transitório do reqwest na cadeia de `e`.
+pub(crate) fn is_transient(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|c| c.downcast_ref::<reqwest::Error>())
+        .any(is_transient_error)
+}
+
+/// Se a request nem chegou ao servidor (conexão recusada): repetir é seguro
+/// mesmo pra chamadas que não são idempotentes.
+pub(crate) fn never_sent(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|c| c.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_connect())
+}
+
+/// Número em [0, 1) sem depender de crate de random (semente do `RandomState`).
+fn random_unit() -> f64 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_backoff_and_jitter() {
+        let p = RetryPolicy {
+            attempts: 5,
+            backoff_ms: 1000,
+            max_backoff_ms: 5000,
+            jitter: 0.0,
+        };
+        assert_eq!(p.delay(1), Duration::from_millis(1000));
+        assert_eq!(p.delay(2), Duration::from_millis(2000));
+        assert_eq!(p.delay(3), Duration::from_millis(4000));
+        assert_eq!(p.delay(4), Duration::from_millis(5000));
+
+        let p = RetryPolicy { jitter: 0.5, ..p };
+        for _ in 0..50 {
+            let d = p.delay(2).as_millis();
+            assert!((1000..=3000).contains(&d), "{d}");
+        }
+
+        assert!(is_transient_status(StatusCode::BAD_GATEWAY));
+        assert!(is_transient_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_transient_status(StatusCode::NOT_FOUND));
+    }
+}
@@ -12,6 +12,9 @@ use std::path::Path;
 
 use super::auth::PeachClient;
 use super::config::PeachConfig;
+use super::retry;
+use super::status::SpotFilter;
+use crate::error::EncoderError;
 
 /// Resposta do endpoint `/amasv/public/delivery/validate`.
 #[derive(Debug, Deserialize)]
@@ -37,10 +40,10 @@ impl<'a> SendRequest<'a> {
     /// Validação básica antes de chamar a API.
     pub fn check(&self) -> Result<()> {
         if self.spot_ids.is_empty() {
-            bail!("nenhum spot informado para envio");
+            bail!(EncoderError::ConfigError("nenhum spot informado para envio".into()));
         }
         if self.destinos_hd.is_empty() && self.destinos_sd.is_empty() {
-            bail!("nenhum destino informado");
+            bail!(EncoderError::ConfigError("nenhum destino informado".into()));
         }
         Ok(())
     }
@@ -56,20 +59,21 @@ impl PeachClient {
             self.base()
         );
         let res = self
-            .http
-            .post(&url)
-            .form(&[("accion", "buscar"), ("id_lista", &id_lista.to_string())])
-            .header("X-Requested-With", "XMLHttpRequest")
-            .header(
-                "Referer",
-                format!("{}/amasv/app/index_general.php", self.base()),
-            )
-            .send()
-            .await
-            .context("falha no POST lista_destinos")?;
-
-        if !res.status().is_success() {
-            bail!("lista_destinos retornou status {}", res.status());
+            .request("lista_destinos", |http| {
+                http.post(&url)
+                    .form(&[("accion", "buscar"), ("id_lista", &id_lista.to_string())])
+                    .header("X-Requested-With", "XMLHttpRequest")
+                    .header(
+                        "Referer",
+                        format!("{}/amasv/app/index_general.php", self.base()),
+                    )
+            })
+            .await?;
+
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachResponse(format!(
+                "lista_destinos retornou status {}", res.status
+            )));
         }
         crate::log::emit(format!(
             "[peach] lista_destinos(id_lista={id_lista}) carregada"
@@ -85,29 +89,28 @@ impl PeachClient {
             self.base()
         );
         let res = self
-            .http
-            .post(&url)
-            .form(&[
-                ("accion", "getCantidadDestinosEmisoras"),
-                (
-                    "destino_record",
-                    if has_record { "true" } else { "false" },
-                ),
-                (
-                    "destino_redetv",
-                    if has_redetv { "true" } else { "false" },
-                ),
-            ])
-            .header("X-Requested-With", "XMLHttpRequest")
-            .header(
-                "Referer",
-                format!("{}/amasv/app/index_general.php", self.base()),
-            )
-            .send()
-            .await
-            .context("falha no POST exhibidor_emisoras")?;
-
-        let body = res.text().await.unwrap_or_default();
+            .request("exhibidor_emisoras", |http| {
+                http.post(&url)
+                    .form(&[
+                        ("accion", "getCantidadDestinosEmisoras"),
+                        (
+                            "destino_record",
+                            if has_record { "true" } else { "false" },
+                        ),
+                        (
+                            "destino_redetv",
+                            if has_redetv { "true" } else { "false" },
+                        ),
+                    ])
+                    .header("X-Requested-With", "XMLHttpRequest")
+                    .header(
+                        "Referer",
+                        format!("{}/amasv/app/index_general.php", self.base()),
+                    )
+            })
+            .await?;
+
+        let body = res.body;
         let count: u32 = body.trim().parse().unwrap_or(0);
         crate::log::emit(format!(
             "[peach] exhibidor_emisoras: cantidad={count}"
@@ -134,31 +137,21 @@ impl PeachClient {
         url.push_str("tipoExhibidor=");
 
         let res = self
-            .http
-            .get(&url)
-            .header("X-Requested-With", "XMLHttpRequest")
-            .header(
-                "Referer",
-                format!("{}/amasv/app/index_general.php", self.base()),
-            )
-            .send()
-            .await
-            .context("falha no GET /delivery/validate")?;
-
-        if !res.status().is_success() {
-            bail!("validate retornou status {}", res.status());
+            .request("/delivery/validate", |http| {
+                http.get(&url)
+                    .header("X-Requested-With", "XMLHttpRequest")
+                    .header(
+                        "Referer",
+                        format!("{}/amasv/app/index_general.php", self.base()),
+                    )
+            })
+            .await?;
+
+        if !res.status.is_success() {
+            bail!(EncoderError::ValidateFailed(format!("validate retornou status {}", res.status)));
         }
 
-        let body = res
-            .text()
-            .await
-            .context("falha ao ler corpo de /delivery/validate")?;
-
-        let mut parsed: ValidateResponse = serde_json::from_str(&body).with_context(|| {
-            format!("falha ao parsear validate JSON: {}", &body[..body.len().min(500)])
-        })?;
-        parsed.raw_body = body;
-        Ok(parsed)
+        parse_validate(res.body)
     }
 
     /// Etapa 2: confirma o envio (server gera HTML do diálogo de confirmação).
@@ -190,20 +183,21 @@ impl PeachClient {
             self.base()
         );
         let res = self
-            .http
-            .post(&url)
-            .form(&body)
-            .header("X-Requested-With", "XMLHttpRequest")
-            .header(
-                "Referer",
-                format!("{}/amasv/app/index_general.php", self.base()),
-            )
-            .send()
-            .await
-            .context("falha no POST enviar_confirmar")?;
-
-        if !res.status().is_success() {
-            bail!("enviar_confirmar retornou status {}", res.status());
+            .request("enviar_confirmar", |http| {
+                http.post(&url)
+                    .form(&body)
+                    .header("X-Requested-With", "XMLHttpRequest")
+                    .header(
+                        "Referer",
+                        format!("{}/amasv/app/index_general.php", self.base()),
+                    )
+            })
+            .await?;
+
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachResponse(format!(
+                "enviar_confirmar retornou status {}", res.status
+            )));
         }
         Ok(())
     }
@@ -212,44 +206,78 @@ impl PeachClient {
     pub async fn execute_send(&self, req: &SendRequest<'_>) -> Result<String> {
         req.check()?;
 
-        let s_param = format_spots(req.spot_ids);
-        // Formato action: <empresa>||<HD/SD>; (sub-emisora vazio, fluxo simples)
-        let mut e_param = String::new();
-        for d in req.destinos_hd {
-            e_param.push_str(&format!("{d}||HD;"));
-        }
-        for d in req.destinos_sd {
-            e_param.push_str(&format!("{d}||SD;"));
-        }
-
-        let body = vec![
-            ("s", s_param.as_str()),
-            ("e", e_param.as_str()),
-            ("comercializadoras", ""),
-        ];
+        let body = execute_send_form(req);
 
         let url = format!(
             "{}/amasv/app/modulos/enviar/enviar_confirma_accion.php?email_aviso_envio=&DC=false&aux_envio=1&id_req=&id_material=&selPais=BR",
             self.base()
         );
-        let res = self
-            .http
-            .post(&url)
-            .form(&body)
-            .header("X-Requested-With", "XMLHttpRequest")
-            .header(
-                "Referer",
-                format!("{}/amasv/app/index_general.php", self.base()),
-            )
-            .send()
-            .await
-            .context("falha no POST enviar_confirma_accion")?;
-
-        if !res.status().is_success() {
-            bail!("enviar_confirma_accion retornou status {}", res.status());
+        // Não idempotente: cada tentativa pode gerar um envio. Depois de uma
+        // falha transitória só repete se a request nem saiu ou se o Peach
+        // confirma que o envio não existe.
+        let policy = self.retry_policy().clone();
+        let mut attempt = 1;
+        let res = loop {
+            let outcome = self
+                .request_once("enviar_confirma_accion", |http| {
+                    http.post(&url)
+                        .form(&body)
+                        .header("X-Requested-With", "XMLHttpRequest")
+                        .header(
+                            "Referer",
+                            format!("{}/amasv/app/index_general.php", self.base()),
+                        )
+                })
+                .await;
+            let (reason, maybe_sent) = match &outcome {
+                Ok(res) if retry::is_transient_status(res.status) => {
+                    (format!("status {}", res.status), true)
+                }
+                Err(e) if retry::is_transient(e) => (format!("{e:#}"), !retry::never_sent(e)),
+                _ => break outcome?,
+            };
+            if attempt >= policy.attempts {
+                break outcome?;
+            }
+            crate::log::warn(format!("[peach] enviar_confirma_accion: {reason}"));
+            if maybe_sent {
+                match self.delivery_exists(req).await {
+                    Ok(true) => {
+                        crate::log::emit("[peach] o envio consta no Peach; não será repetido");
+                        return Ok(format!(
+                            "Distribuído: {} spot(s) → {} destino(s) (confirmado após falha na resposta)",
+                            req.spot_ids.len(),
+                            req.destinos_hd.len() + req.destinos_sd.len(),
+                        ));
+                    }
+                    // Sem confirmação de todos os pares, repetir pode duplicar
+                    // o envio e dar por feito pode perder um destino.
+                    Ok(false) => bail!(EncoderError::ValidateFailed(format!(
+                        "enviar_confirma_accion falhou ({reason}) e o Peach não confirmou o envio de todos os spots/destinos. Confira em Reportes no portal antes de enviar de novo."
+                    ))),
+                    Err(e) => bail!(EncoderError::ValidateFailed(format!(
+                        "enviar_confirma_accion falhou ({reason}) e não foi possível conferir se o envio foi feito ({e:#}). Confira em Reportes no portal antes de enviar de novo."
+                    ))),
+                }
+            }
+            let delay = policy.delay(attempt);
+            crate::log::warn(format!(
+                "[peach] enviar_confirma_accion: tentando de novo em {:.1}s ({}/{})",
+                delay.as_secs_f64(),
+                attempt + 1,
+                policy.attempts
+            ));
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachResponse(format!(
+                "enviar_confirma_accion retornou status {}", res.status
+            )));
         }
 
-        let body = res.text().await.unwrap_or_default();
+        let body = res.body;
         // A resposta tipicamente é um script JS com goReportes(...). Sucesso = 200.
         let summary = format!(
             "Distribuído: {} spot(s) → {} destino(s)",
@@ -267,6 +295,14 @@ impl PeachClient {
         }
     }
 
+    /// Confere se o envio de `req` já foi feito por inteiro: o `validate`
+    /// precisa marcar cada combinação spot×destino como já enviada.
+    /// Usado depois de um `execute_send` que falhou no meio.
+    pub async fn delivery_exists(&self, req: &SendRequest<'_>) -> Result<bool> {
+        let val = self.validate_delivery(req).await?;
+        Ok(already_sent(&val.envios, req))
+    }
+
     /// Helper high-level: aguarda QC + valida + confirma + executa.
     ///
     /// Fluxo:
@@ -276,6 +312,19 @@ impl PeachClient {
     ///    retenta com backoff. Se falhar com erro estrutural (Exhibidoras), aborta.
     /// 3. Confirma e executa o envio.
     pub async fn send_spots(&self, req: &SendRequest<'_>, cfg: &PeachConfig) -> Result<String> {
+        self.send_spots_queued(req, cfg, None).await
+    }
+
+    /// [`Self::send_spots`] pra vários envios na mesma sessão (batch): o
+    /// portal guarda contexto na sessão (listas de destinos, validação), então
+    /// cada tentativa de validate + confirm + execute segura uma vaga de
+    /// `turn`. A espera pelo QC fica fora da vaga.
+    pub async fn send_spots_queued(
+        &self,
+        req: &SendRequest<'_>,
+        cfg: &PeachConfig,
+        turn: Option<&tokio::sync::Semaphore>,
+    ) -> Result<String> {
         // Configuração de polling de status do spot
         const SPOT_READY_MAX_ATTEMPTS: usize = 40; // 40 * 15s = 10 min
         const SPOT_READY_DELAY_SECS: u64 = 15;
@@ -283,23 +332,36 @@ impl PeachClient {
         const MAX_QC_RETRIES: usize = 10;
         const QC_RETRY_DELAY_SECS: u64 = 30;
 
-        // Etapa 0: carrega listas de destinos (seta contexto de sessão no servidor)
-        for &id_lista in &cfg.destinos.id_listas {
-            self.load_destinos_list(id_lista).await?;
-        }
-
-        // Etapa 0b: check exhibidor_emisoras (necessário pro fluxo do portal)
-        self.check_exhibidor_emisoras(false, false).await?;
-
-        // Etapa 1: aguarda cada spot ficar pronto pra envio
+        // Etapa 1: aguarda cada spot ficar pronto pra envio. A listagem é
+        // filtrada pelo anunciante do cliente pro spot não sumir entre spots
+        // de outros clientes da conta.
+        let filter = SpotFilter {
+            id_avisador: Some(cfg.avisador_id.clone()).filter(|a| !a.is_empty()),
+            ..SpotFilter::default()
+        };
         for &spot_id in req.spot_ids {
             crate::log::emit(format!("[peach] Aguardando spot {spot_id} ficar pronto..."));
-            self.wait_spot_ready(spot_id, SPOT_READY_MAX_ATTEMPTS, SPOT_READY_DELAY_SECS)
+            self.wait_spot_ready(spot_id, &filter, SPOT_READY_MAX_ATTEMPTS, SPOT_READY_DELAY_SECS)
                 .await?;
         }
 
         // Etapa 2-3: validate (com retry pra QC residual) + confirm + execute
         for attempt in 0..=MAX_QC_RETRIES {
+            let permit = match turn {
+                Some(turn) => Some(turn.acquire().await.context("fila de envios do Peach fechada")?),
+                None => None,
+            };
+            // Contexto de sessão no servidor: refeito a cada vaga, porque
+            // outro envio do lote pode ter usado a sessão no meio.
+            if attempt == 0 || turn.is_some() {
+                // Listas de destinos salvas
+                for &id_lista in &cfg.destinos.id_listas {
+                    self.load_destinos_list(id_lista).await?;
+                }
+                // check exhibidor_emisoras (necessário pro fluxo do portal)
+                self.check_exhibidor_emisoras(false, false).await?;
+            }
+
             let val = self.validate_delivery(req).await?;
 
             if val.status == "Success" {
@@ -328,7 +390,7 @@ impl PeachClient {
             // Analisa o tipo de erro
             let analysis = analyze_validate_errors(&val.envios);
 
-            crate::log::emit(format!(
+            crate::log::warn(format!(
                 "[peach] validate FALHOU (tentativa {}/{}). QC errors: {}, outros: {}",
                 attempt + 1,
                 MAX_QC_RETRIES + 1,
@@ -341,7 +403,7 @@ impl PeachClient {
                 // rigoroso que o action — o servidor aceita o envio mesmo sem
                 // sub-emisoras especificadas no body (preenche com defaults).
                 // Loga warning e PROSSEGUE pro confirm/execute.
-                crate::log::emit(format!(
+                crate::log::warn(format!(
                     "[peach] validate retornou Status=Error com {} aviso(s) não-QC. Prosseguindo mesmo assim (action é tolerante).\nDetalhes:\n{}",
                     analysis.non_qc_errors,
                     val.raw_body
@@ -356,20 +418,19 @@ impl PeachClient {
 
             if analysis.qc_errors == 0 {
                 // Status=Error mas sem erros listados? Estranho. Aborta.
-                bail!(
+                bail!(EncoderError::ValidateFailed(format!(
                     "validate retornou status={} sem detalhes:\n{}",
-                    val.status,
-                    val.raw_body
-                );
+                    val.status, val.raw_body
+                )));
             }
 
             if attempt == MAX_QC_RETRIES {
-                bail!(
+                bail!(EncoderError::ValidateFailed(format!(
                     "QC do Peach ainda não passou após {} tentativas (~{} min). Tente enviar manualmente mais tarde.\n{}",
                     MAX_QC_RETRIES + 1,
                     (MAX_QC_RETRIES as u64 * QC_RETRY_DELAY_SECS) / 60,
                     val.raw_body
-                );
+                )));
             }
 
             crate::log::emit(format!(
@@ -378,9 +439,10 @@ impl PeachClient {
                 attempt + 2,
                 MAX_QC_RETRIES + 1
             ));
+            drop(permit);
             tokio::time::sleep(tokio::time::Duration::from_secs(QC_RETRY_DELAY_SECS)).await;
         }
-        bail!("loop de retry esgotado")
+        bail!(EncoderError::ValidateFailed("QC não ficou pronto após as tentativas".into()))
     }
 }
 
@@ -413,6 +475,53 @@ fn extract_warnings_summary(envios: &serde_json::Value) -> String {
     }
 }
 
+/// Frases do validate pra spot já enviado ao destino (`Campo` = "Envio").
+///
+/// Não confirmadas: nenhuma resposta do validate pra envio repetido foi
+/// capturada ainda, então `Campo` e frases são suposição. Se não baterem, o
+/// `delivery_exists` só nunca confirma, e o envio para pedindo conferência
+/// em Reportes (nunca dá por feito sem a marca).
+const ALREADY_SENT_MARKERS: &[&str] = &["ya fue enviad", "já foi enviad"];
+
+/// Todo par spot×destino de `req` tem, na sua entrada do validate
+/// (`"<spot>@<destino>"`), um erro/warning de envio já feito. Par sem
+/// entrada ou sem a marca = não confirmado.
+fn already_sent(envios: &serde_json::Value, req: &SendRequest<'_>) -> bool {
+    let Some(obj) = envios.as_object() else {
+        return false;
+    };
+    let confirmed = |key: &str| {
+        obj.get(key).is_some_and(|envio| {
+            ["Errores", "Warnings"]
+                .into_iter()
+                .filter_map(|k| envio.get(k).and_then(|v| v.as_array()))
+                .flatten()
+                .any(|m| {
+                    let campo = m.get("Campo").and_then(|c| c.as_str()).unwrap_or("");
+                    let msg = m.get("Mensaje").and_then(|m| m.as_str()).unwrap_or("");
+                    let msg = msg.to_lowercase();
+                    campo.eq_ignore_ascii_case("Envio")
+                        && ALREADY_SENT_MARKERS.iter().any(|mark| msg.contains(mark))
+                })
+        })
+    };
+    let destinos: Vec<&String> = req.destinos_hd.iter().chain(req.destinos_sd).collect();
+    !req.spot_ids.is_empty()
+        && !destinos.is_empty()
+        && req
+            .spot_ids
+            .iter()
+            .all(|spot| destinos.iter().all(|d| confirmed(&format!("{spot}@{d}"))))
+}
+
+fn parse_validate(body: String) -> Result<ValidateResponse> {
+    let mut parsed: ValidateResponse = serde_json::from_str(&body).with_context(|| {
+        format!("falha ao parsear validate JSON: {}", &body[..body.len().min(500)])
+    })?;
+    parsed.raw_body = body;
+    Ok(parsed)
+}
+
 /// Conta erros por tipo. QC errors são recuperáveis (timing), outros não.
 fn analyze_validate_errors(envios: &serde_json::Value) -> ValidateErrorAnalysis {
     let mut qc = 0;
@@ -444,6 +553,24 @@ fn analyze_validate_errors(envios: &serde_json::Value) -> ValidateErrorAnalysis
 
 // ----------------- Helpers de formato -----------------
 
+/// Campos do POST do `execute_send` (o que efetivamente dispara o envio).
+/// Separado pra poder ser mostrado no `--dry-run`.
+pub fn execute_send_form(req: &SendRequest<'_>) -> Vec<(&'static str, String)> {
+    // Formato action: <empresa>||<HD/SD>; (sub-emisora vazio, fluxo simples)
+    let mut e_param = String::new();
+    for d in req.destinos_hd {
+        e_param.push_str(&format!("{d}||HD;"));
+    }
+    for d in req.destinos_sd {
+        e_param.push_str(&format!("{d}||SD;"));
+    }
+    vec![
+        ("s", format_spots(req.spot_ids)),
+        ("e", e_param),
+        ("comercializadoras", String::new()),
+    ]
+}
+
 fn format_spots(ids: &[u64]) -> String {
     // "id1;id2;id3;"
     ids.iter().map(|id| format!("{id};")).collect()
@@ -454,7 +581,6 @@ fn format_destinos_confirm(empresas: &[String], codec: &str) -> String {
     empresas.iter().map(|e| format!("{e}|{codec};")).collect()
 }
 
-/// URL encode mínimo para os valores de destinos[] (apenas chars problemáticos).
 // ----------------- Log CSV -----------------
 
 /// Registro de um envio bem-sucedido.
@@ -468,13 +594,39 @@ pub struct SendLogEntry {
     pub id_envio: String,
     /// URL do MP4 agência no Google Drive (se compartilhado).
     pub agencia_url: String,
+    /// SHA-256 do MXF enviado (prova do que foi entregue).
+    pub sha256: String,
 }
 
+const SEND_LOG_HEADER: &str = "data_hora,titulo,codigo_crt,spot_id,destinos,id_envio,agencia_url,sha256";
+
 /// Grava uma linha no CSV de log de envios.
 /// Cria o arquivo com cabeçalho se não existir, ou appenda se já existir.
+/// Um log com outro cabeçalho (de versão anterior, sem `sha256`) é renomeado
+/// pra `envios_log.old.csv` e o atual recomeça, pra as colunas não
+/// desalinharem na planilha.
 pub fn append_send_log(output_dir: &Path, entry: &SendLogEntry) -> Result<()> {
-    use std::io::Write;
+    use std::io::{BufRead, Write};
     let csv_path = output_dir.join("envios_log.csv");
+    if let Ok(old) = std::fs::File::open(&csv_path) {
+        let mut first = String::new();
+        std::io::BufReader::new(old).read_line(&mut first)?;
+        if first.trim_end() != SEND_LOG_HEADER {
+            let mut rotated = output_dir.join("envios_log.old.csv");
+            let mut n = 2;
+            while rotated.exists() {
+                rotated = output_dir.join(format!("envios_log.old{n}.csv"));
+                n += 1;
+            }
+            std::fs::rename(&csv_path, &rotated)
+                .with_context(|| format!("falha ao renomear {}", csv_path.display()))?;
+            crate::log::warn(format!(
+                "[peach] {} tinha o formato antigo; movido para {}",
+                csv_path.display(),
+                rotated.display()
+            ));
+        }
+    }
     let file_exists = csv_path.exists();
 
     let mut file = std::fs::OpenOptions::new()
@@ -484,10 +636,7 @@ pub fn append_send_log(output_dir: &Path, entry: &SendLogEntry) -> Result<()> {
         .with_context(|| format!("falha ao abrir {}", csv_path.display()))?;
 
     if !file_exists {
-        writeln!(
-            file,
-            "data_hora,titulo,codigo_crt,spot_id,destinos,id_envio,agencia_url"
-        )?;
+        writeln!(file, "{SEND_LOG_HEADER}")?;
     }
 
     // Escapa campos com vírgula
@@ -499,14 +648,15 @@ pub fn append_send_log(output_dir: &Path, entry: &SendLogEntry) -> Result<()> {
 
     writeln!(
         file,
-        "{},{},{},{},{},{},{}",
+        "{},{},{},{},{},{},{},{}",
         entry.timestamp,
         entry.pieza,
         entry.codigo,
         entry.spot_id,
         destinos_escaped,
         entry.id_envio,
-        entry.agencia_url
+        entry.agencia_url,
+        entry.sha256
     )?;
 
     crate::log::emit(format!(
@@ -531,9 +681,10 @@ pub async fn post_webhook(webhook_url: &str, entry: &SendLogEntry, cliente: &str
         "id_envio": entry.id_envio,
         "cliente": cliente,
         "agencia_url": entry.agencia_url,
+        "sha256": entry.sha256,
     });
 
-    crate::log::emit(format!("[peach] Enviando registro pro webhook..."));
+    crate::log::emit("[peach] Enviando registro pro webhook...");
 
     let client = match reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::limited(5))
@@ -541,7 +692,7 @@ pub async fn post_webhook(webhook_url: &str, entry: &SendLogEntry, cliente: &str
     {
         Ok(c) => c,
         Err(e) => {
-            crate::log::emit(format!("[peach] Aviso: falha ao criar HTTP client pro webhook: {e}"));
+            crate::log::warn(format!("[peach] falha ao criar HTTP client pro webhook: {e}"));
             return;
         }
     };
@@ -566,11 +717,12 @@ pub async fn post_webhook(webhook_url: &str, entry: &SendLogEntry, cliente: &str
             }
         }
         Err(e) => {
-            crate::log::emit(format!("[peach] Aviso: falha no webhook: {e}"));
+            crate::log::warn(format!("[peach] falha no webhook: {e}"));
         }
     }
 }
 
+/// URL encode mínimo para os valores de destinos[] (apenas chars problemáticos).
 fn urlencoding_minimal(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
     for c in s.chars() {
@@ -654,6 +806,58 @@ mod tests {
         assert_eq!(r.non_qc_errors, 1);
     }
 
+    #[test]
+    fn test_already_sent() {
+        let hd = vec!["BR1230".to_string(), "BR_GLOBO_79".to_string()];
+        let req = SendRequest {
+            spot_ids: &[2238326],
+            destinos_hd: &hd,
+            destinos_sd: &[],
+        };
+        let envios: serde_json::Value = serde_json::from_str(
+            r#"{
+                "2238326@BR1230": {
+                    "Warnings": [{"Campo":"Envio","Mensaje":"El spot ya fue enviado a este destino"}]
+                },
+                "2238326@BR_GLOBO_79": {
+                    "Errores": [{"Campo":"Envio","Mensaje":"Spot ya fue enviado"}]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert!(already_sent(&envios, &req));
+
+        // Só um dos destinos confirmado: não dá pra afirmar que foi entregue.
+        let envios: serde_json::Value = serde_json::from_str(
+            r#"{
+                "2238326@BR1230": {
+                    "Warnings": [{"Campo":"Envio","Mensaje":"El spot ya fue enviado a este destino"}]
+                },
+                "2238326@BR_GLOBO_79": { "Errores": [{"Campo":"QC","Mensaje":"Spot com QC Error"}] }
+            }"#,
+        )
+        .unwrap();
+        assert!(!already_sent(&envios, &req));
+
+        // "enviad" fora do campo Envio não conta.
+        let envios: serde_json::Value = serde_json::from_str(
+            r#"{ "2238326@BR1230": { "Errores": [{"Campo":"QC","Mensaje":"Spot ainda não enviado ao QC"}] } }"#,
+        )
+        .unwrap();
+        assert!(!already_sent(&envios, &req));
+        assert!(!already_sent(&serde_json::Value::Null, &req));
+
+        // Body do validate com os erros já vistos no portal (QC, exibidoras):
+        // não é envio feito.
+        let val = parse_validate(
+            r#"{"Status":"Error","Envios":{"2238326@BR1230":{"Errores":[{"Campo":"QC","Mensaje":"Spot com QC Error"},{"Campo":"Exhibidoras","Mensaje":"Exibidoras obrigatórios"}]},"2238326@BR_GLOBO_79":{"Errores":[{"Campo":"QC","Mensaje":"Spot com QC Error"}]}}}"#
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(val.status, "Error");
+        assert!(!already_sent(&val.envios, &req));
+    }
+
     #[test]
     fn test_send_request_check() {
         let empty: Vec<String> = vec![];
@@ -683,4 +887,31 @@ mod tests {
         .check()
         .is_err());
     }
+
+    #[test]
+    fn test_append_send_log_rotates_old_format() {
+        let dir = std::env::temp_dir().join(format!("encoder_test_send_log_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let old = "data_hora,titulo,codigo_crt,spot_id,destinos,id_envio,agencia_url\n\
+                   2026-01-05 10:00:00,FEV_17,2024017422017,2238326,BR1230,abc,\n";
+        std::fs::write(dir.join("envios_log.csv"), old).unwrap();
+
+        let entry = SendLogEntry {
+            spot_id: 2238330,
+            sha256: "ff00".into(),
+            ..SendLogEntry::default()
+        };
+        append_send_log(&dir, &entry).unwrap();
+        append_send_log(&dir, &entry).unwrap();
+
+        assert_eq!(std::fs::read_to_string(dir.join("envios_log.old.csv")).unwrap(), old);
+        let csv = std::fs::read_to_string(dir.join("envios_log.csv")).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], SEND_LOG_HEADER);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].ends_with(",2238330,,,,ff00"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
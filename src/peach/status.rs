@@ -12,11 +12,29 @@
 //! ```
 //!
 //! O campo `spot_se_puede_enviar` é o booleano definitivo de prontidão.
+//!
+//! A listagem é paginada (mais recentes primeiro) e aceita os mesmos filtros
+//! da tela "Reel" do portal: período, anunciante, marca, produto e texto.
+//! Em conta movimentada o spot sai da página 1 em pouco tempo, então a busca
+//! percorre as páginas até achar (ou até [`DEFAULT_MAX_PAGES`]).
 
 use anyhow::{bail, Context, Result};
-use serde::Deserialize;
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
 
 use super::auth::PeachClient;
+use crate::error::EncoderError;
+
+/// Páginas da listagem percorridas por padrão ao procurar um spot.
+pub const DEFAULT_MAX_PAGES: u32 = 10;
+
+/// Páginas por consulta no `wait_spot_ready`: o spot acabou de subir, então
+/// está no topo da listagem (filtrada pelo anunciante quando há um).
+const WAIT_MAX_PAGES: u32 = 1;
+
+/// Formato de data dos filtros `fec_ini`/`fec_fin` (o do datepicker do portal).
+const DATE_FORMAT: &str = "%d/%m/%Y";
 
 /// Status simplificado de um spot no Peach.
 #[derive(Debug, Clone, Deserialize)]
@@ -25,6 +43,11 @@ pub struct SpotStatus {
     pub id_spot: u64,
     #[serde(rename = "PIEZA", default)]
     pub pieza: String,
+    /// Código ANCINE (registro sem traço) informado no upload. O nome da
+    /// chave não foi conferido numa listagem real; se vier vazio em todos os
+    /// spots, o `find_spot` avisa que a busca por código não funciona.
+    #[serde(rename = "CODIGO", alias = "SPOT_CODIGO", default, deserialize_with = "string_or_number")]
+    pub codigo: String,
     /// QC automático: "valido" / "rechazado" / "por_verificar" / "verificando"
     #[serde(rename = "VERIFICACION", default)]
     pub verificacion: String,
@@ -36,57 +59,216 @@ pub struct SpotStatus {
     pub spot_se_puede_enviar: bool,
 }
 
+impl SpotStatus {
+    /// O QC automático rejeitou o spot: ele não vai ficar pronto pra envio.
+    pub fn is_rejected(&self) -> bool {
+        self.verificacion == "rechazado"
+    }
+}
+
+/// Filtros da listagem (`None`/vazio = sem filtro).
+#[derive(Debug, Clone, Default)]
+pub struct SpotFilter {
+    /// Data de upload, a partir de (inclusive).
+    pub fec_ini: Option<NaiveDate>,
+    /// Data de upload, até (inclusive).
+    pub fec_fin: Option<NaiveDate>,
+    pub id_avisador: Option<String>,
+    pub id_marca: Option<u32>,
+    pub id_producto: Option<u32>,
+    /// Busca livre do portal (pieza, código...).
+    pub texto: String,
+}
+
+impl SpotFilter {
+    fn query(&self, page: u32) -> Vec<(&'static str, String)> {
+        let date = |d: Option<NaiveDate>| d.map(|d| d.format(DATE_FORMAT).to_string()).unwrap_or_default();
+        let id = |v: Option<String>| v.unwrap_or_else(|| "null".to_string());
+        vec![
+            ("vista", "vista_img".to_string()),
+            ("fec_ini", date(self.fec_ini)),
+            ("fec_fin", date(self.fec_fin)),
+            ("usuario", String::new()),
+            ("id_avisador", id(self.id_avisador.clone())),
+            ("id_marca", id(self.id_marca.map(|n| n.to_string()))),
+            ("id_producto", id(self.id_producto.map(|n| n.to_string()))),
+            ("texto", self.texto.clone()),
+            ("tag", String::new()),
+            ("tagtipo", "carpeta".to_string()),
+            ("page", page.to_string()),
+        ]
+    }
+}
+
+/// Como identificar um spot na listagem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpotKey {
+    Id(u64),
+    /// Nome da peça (nome do arquivo sem extensão); sem diferenciar maiúsculas.
+    Pieza(String),
+    /// Código ANCINE, com ou sem traço.
+    Codigo(String),
+}
+
+impl SpotKey {
+    pub fn matches(&self, s: &SpotStatus) -> bool {
+        match self {
+            SpotKey::Id(id) => s.id_spot == *id,
+            SpotKey::Pieza(p) => s.pieza.eq_ignore_ascii_case(p),
+            SpotKey::Codigo(c) => !s.codigo.is_empty() && digits(&s.codigo) == digits(c),
+        }
+    }
+}
+
+impl FromStr for SpotKey {
+    type Err = std::convert::Infallible;
+
+    /// Número curto = spot_id; registro ANCINE (`2024017422006-5` ou os 14
+    /// dígitos) = código; qualquer outra coisa = pieza.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        let only_digits = |t: &str| !t.is_empty() && t.chars().all(|c| c.is_ascii_digit());
+        Ok(if only_digits(&s.replace('-', "")) && (s.contains('-') || s.len() >= 12) {
+            SpotKey::Codigo(s.to_string())
+        } else if let (true, Ok(id)) = (only_digits(s), s.parse()) {
+            SpotKey::Id(id)
+        } else {
+            SpotKey::Pieza(s.to_string())
+        })
+    }
+}
+
+impl std::fmt::Display for SpotKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpotKey::Id(id) => write!(f, "spot {id}"),
+            SpotKey::Pieza(p) => write!(f, "pieza {p}"),
+            SpotKey::Codigo(c) => write!(f, "código {c}"),
+        }
+    }
+}
+
 impl PeachClient {
-    /// Busca o status de um spot pela listagem `inc.reel.vista_img.php`.
-    /// Retorna `None` se o spot não estiver na primeira página da listagem.
-    pub async fn get_spot_status(&self, spot_id: u64) -> Result<Option<SpotStatus>> {
+    /// Uma página da listagem `inc.reel.vista_img.php` (começa em 1).
+    pub async fn list_spots_page(&self, filter: &SpotFilter, page: u32) -> Result<Vec<SpotStatus>> {
+        let body = self.fetch_listing(filter, page).await?;
+        Ok(parse_spots_from_listing(&body))
+    }
+
+    async fn fetch_listing(&self, filter: &SpotFilter, page: u32) -> Result<String> {
         let url = format!(
             "{}/amasv/app/modulos/reel/inc.reel.vista_img.php",
             self.base()
         );
+        let query = filter.query(page);
         let res = self
-            .http
-            .get(&url)
-            .query(&[
-                ("vista", "vista_img"),
-                ("fec_ini", ""),
-                ("fec_fin", ""),
-                ("usuario", ""),
-                ("id_avisador", "null"),
-                ("id_marca", "null"),
-                ("id_producto", "null"),
-                ("texto", ""),
-                ("tag", ""),
-                ("tagtipo", "carpeta"),
-                ("page", "1"),
-            ])
-            .header("X-Requested-With", "XMLHttpRequest")
-            .header(
-                "Referer",
-                format!("{}/amasv/app/index_general.php", self.base()),
-            )
-            .send()
-            .await
-            .context("falha no GET inc.reel.vista_img.php")?;
+            .request("inc.reel.vista_img.php", |http| {
+                http.get(&url)
+                    .query(&query)
+                    .header("X-Requested-With", "XMLHttpRequest")
+                    .header(
+                        "Referer",
+                        format!("{}/amasv/app/index_general.php", self.base()),
+                    )
+            })
+            .await?;
+
+        if !res.status.is_success() {
+            bail!(EncoderError::PeachResponse(format!(
+                "inc.reel.vista_img.php retornou status {}", res.status
+            )));
+        }
+        Ok(res.body)
+    }
 
-        if !res.status().is_success() {
-            bail!("get_spot_status retornou status {}", res.status());
+    /// Percorre até `max_pages` páginas e devolve os spots na ordem da
+    /// listagem. Para antes na primeira página vazia (ou repetida).
+    pub async fn list_spots(&self, filter: &SpotFilter, max_pages: u32) -> Result<Vec<SpotStatus>> {
+        let mut all: Vec<SpotStatus> = Vec::new();
+        for page in 1..=max_pages {
+            let spots = self.list_spots_page(filter, page).await?;
+            let before = all.len();
+            for s in spots {
+                if !all.iter().any(|a| a.id_spot == s.id_spot) {
+                    all.push(s);
+                }
+            }
+            if all.len() == before {
+                break;
+            }
         }
+        Ok(all)
+    }
 
-        let body = res.text().await.context("falha ao ler corpo do listing")?;
-        parse_spot_from_listing(&body, spot_id)
+    /// Procura um spot página a página, parando no primeiro que casar.
+    /// Pra pieza/código sem `texto` no filtro, usa o próprio valor como
+    /// busca livre (o portal devolve bem menos páginas).
+    pub async fn find_spot(
+        &self,
+        key: &SpotKey,
+        filter: &SpotFilter,
+        max_pages: u32,
+    ) -> Result<Option<SpotStatus>> {
+        let mut filter = filter.clone();
+        if filter.texto.is_empty() {
+            match key {
+                SpotKey::Pieza(p) => filter.texto = p.clone(),
+                SpotKey::Codigo(c) => filter.texto = digits(c),
+                SpotKey::Id(_) => {}
+            }
+        }
+        let mut seen: Vec<u64> = Vec::new();
+        let mut any_codigo = false;
+        for page in 1..=max_pages {
+            let body = self.fetch_listing(&filter, page).await?;
+            if let SpotKey::Id(id) = key {
+                if let Some(s) = parse_spot_from_listing(&body, *id)? {
+                    return Ok(Some(s));
+                }
+            }
+            let spots = parse_spots_from_listing(&body);
+            if let Some(s) = spots.iter().find(|s| key.matches(s)) {
+                return Ok(Some(s.clone()));
+            }
+            any_codigo |= spots.iter().any(|s| !s.codigo.is_empty());
+            let fresh: Vec<u64> = spots.iter().map(|s| s.id_spot).filter(|id| !seen.contains(id)).collect();
+            if fresh.is_empty() {
+                break;
+            }
+            seen.extend(fresh);
+        }
+        if matches!(key, SpotKey::Codigo(_)) && !seen.is_empty() && !any_codigo {
+            crate::log::warn(format!(
+                "[peach] nenhum spot da listagem trouxe o código (CODIGO/SPOT_CODIGO); \
+                 a busca por código pode não funcionar nesta conta. Use o spot_id ou a pieza em vez de {key}."
+            ));
+        }
+        Ok(None)
+    }
+
+    /// Busca o status de um spot pelo ID, percorrendo até
+    /// [`DEFAULT_MAX_PAGES`] páginas da listagem sem filtro.
+    /// Retorna `None` se o spot não aparecer.
+    pub async fn get_spot_status(&self, spot_id: u64) -> Result<Option<SpotStatus>> {
+        self.find_spot(&SpotKey::Id(spot_id), &SpotFilter::default(), DEFAULT_MAX_PAGES)
+            .await
     }
 
     /// Polla o status do spot até estar pronto para envio (`spot_se_puede_enviar=true`)
-    /// ou até dar timeout. Retorna erro se o QC rejeitar o spot.
+    /// ou até dar timeout. Retorna erro se o QC rejeitar o spot. `filter`
+    /// estreita a listagem (ex: só o anunciante do cliente); cada consulta
+    /// lê só [`WAIT_MAX_PAGES`] página(s).
     pub async fn wait_spot_ready(
         &self,
         spot_id: u64,
+        filter: &SpotFilter,
         max_attempts: usize,
         delay_secs: u64,
     ) -> Result<SpotStatus> {
         for attempt in 0..max_attempts {
-            let status = self.get_spot_status(spot_id).await?;
+            let status = self
+                .find_spot(&SpotKey::Id(spot_id), filter, WAIT_MAX_PAGES)
+                .await?;
 
             match status {
                 None => {
@@ -97,14 +279,14 @@ impl PeachClient {
                     ));
                 }
                 Some(s) => {
-                    if s.verificacion == "rechazado" {
-                        bail!(
-                            "Spot {} ({}) foi REJEITADO pelo QC do Peach (VERIFICACION={}, MANUAL={})",
+                    if s.is_rejected() {
+                        bail!(EncoderError::QcRejected(format!(
+                            "spot {} ({}) foi REJEITADO pelo QC do Peach (VERIFICACION={}, MANUAL={})",
                             s.id_spot,
                             s.pieza,
                             s.verificacion,
                             s.verificacion_manual
-                        );
+                        )));
                     }
 
                     if s.spot_se_puede_enviar {
@@ -131,12 +313,12 @@ impl PeachClient {
                 tokio::time::sleep(tokio::time::Duration::from_secs(delay_secs)).await;
             }
         }
-        bail!(
-            "Spot {} não ficou pronto após {} tentativas (~{} min). Tente enviar manualmente mais tarde.",
+        bail!(EncoderError::ValidateFailed(format!(
+            "spot {} não ficou pronto após {} tentativas (~{} min). Tente enviar manualmente mais tarde.",
             spot_id,
             max_attempts,
             (max_attempts as u64 * delay_secs) / 60
-        )
+        )))
     }
 }
 
@@ -146,17 +328,52 @@ fn parse_spot_from_listing(body: &str, spot_id: u64) -> Result<Option<SpotStatus
     let Some(start) = body.find(&needle) else {
         return Ok(None);
     };
-    let json_start = start + needle.len();
-    let tail = &body[json_start..];
+    parse_spot_at(&body[start + needle.len()..], &spot_id.to_string()).map(Some)
+}
+
+/// Todos os `Spot["<id>"] = {...};` da página, na ordem em que aparecem.
+/// Objetos que não parseiam são pulados (ficam no log de depuração).
+fn parse_spots_from_listing(body: &str) -> Vec<SpotStatus> {
+    const PREFIX: &str = r#"Spot[""#;
+    let mut spots = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find(PREFIX) {
+        rest = &rest[start + PREFIX.len()..];
+        let Some((id, tail)) = rest.split_once(r#""] = "#) else {
+            break;
+        };
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        match parse_spot_at(tail, id) {
+            Ok(s) => spots.push(s),
+            Err(e) => crate::log::debug(format!("[peach] listagem: {e:#}")),
+        }
+        rest = tail;
+    }
+    spots
+}
 
+/// Parseia o objeto JSON no início de `tail` (o resto do blob é ignorado).
+fn parse_spot_at(tail: &str, spot_id: &str) -> Result<SpotStatus> {
     // Usa o Deserializer pra detectar o fim do objeto JSON dentro do blob
     let mut de = serde_json::Deserializer::from_str(tail);
     let value = serde_json::Value::deserialize(&mut de)
         .with_context(|| format!("falha ao parsear JSON do spot {spot_id}"))?;
+    serde_json::from_value(value).context("falha ao desserializar SpotStatus")
+}
+
+fn digits(s: &str) -> String {
+    s.chars().filter(|c| c.is_ascii_digit()).collect()
+}
 
-    let status: SpotStatus =
-        serde_json::from_value(value).context("falha ao desserializar SpotStatus")?;
-    Ok(Some(status))
+/// O portal manda alguns campos ora como string, ora como número.
+fn string_or_number<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<String, D::Error> {
+    Ok(match serde_json::Value::deserialize(d)? {
+        serde_json::Value::String(s) => s,
+        serde_json::Value::Number(n) => n.to_string(),
+        _ => String::new(),
+    })
 }
 
 #[cfg(test)]
@@ -184,5 +401,35 @@ mod tests {
 
         // Spot inexistente
         assert!(parse_spot_from_listing(body, 999).unwrap().is_none());
+
+        let all = parse_spots_from_listing(body);
+        assert_eq!(all.iter().map(|s| s.id_spot).collect::<Vec<_>>(), vec![2238303, 2238310]);
+    }
+
+    #[test]
+    fn test_spot_key() {
+        let body = r#"Spot["7"] = {"ID_SPOT":7,"PIEZA":"ABR_PROMO_10","CODIGO":20240174220065};"#;
+        let s = parse_spot_from_listing(body, 7).unwrap().unwrap();
+        assert_eq!(s.codigo, "20240174220065");
+
+        let key = |k: &str| k.parse::<SpotKey>().unwrap();
+        assert_eq!(key("2238303"), SpotKey::Id(2238303));
+        assert_eq!(key("2024017422006-5"), SpotKey::Codigo("2024017422006-5".into()));
+        assert_eq!(key("20240174220065"), SpotKey::Codigo("20240174220065".into()));
+        assert_eq!(key("ABR_PROMO_10"), SpotKey::Pieza("ABR_PROMO_10".into()));
+        assert!(key("2024017422006-5").matches(&s));
+        assert!(key("abr_promo_10").matches(&s));
+        assert!(!key("8").matches(&s));
+
+        let q = SpotFilter {
+            fec_ini: NaiveDate::from_ymd_opt(2026, 3, 1),
+            id_marca: Some(9758),
+            ..SpotFilter::default()
+        }
+        .query(2);
+        assert!(q.contains(&("fec_ini", "01/03/2026".to_string())));
+        assert!(q.contains(&("id_marca", "9758".to_string())));
+        assert!(q.contains(&("id_avisador", "null".to_string())));
+        assert!(q.contains(&("page", "2".to_string())));
     }
 }
@@ -0,0 +1,195 @@
+//! Pasta monitorada (`encoder watch`): a agência larga o master numa pasta
+//! compartilhada e o encoder processa sozinho.
+//!
+//! Um arquivo só entra na fila depois de estável (tamanho e data de
+//! modificação parados por `settle`), pra não pegar cópia pela metade. Depois
+//! de processado, o original vai pra `done/` ou `failed/` dentro da pasta e o
+//! resultado fica registrado em `watch.log`.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const DONE_DIR: &str = "done";
+pub const FAILED_DIR: &str = "failed";
+pub const LOG_FILE: &str = "watch.log";
+
+/// Extensões aceitas como vídeo (as mesmas do seletor da GUI).
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mov", "avi", "mkv", "mxf"];
+
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .map(|e| {
+            let e = e.to_string_lossy();
+            VIDEO_EXTENSIONS.iter().any(|v| e.eq_ignore_ascii_case(v))
+        })
+        .unwrap_or(false)
+}
+
+/// Acompanha o tamanho dos vídeos da pasta entre uma varredura e outra.
+pub struct StableFiles {
+    settle: Duration,
+    /// Tamanho, mtime e desde quando estão assim.
+    seen: HashMap<PathBuf, (u64, Option<SystemTime>, Instant)>,
+    /// Já processados que não saíram da pasta (tamanho e mtime de então).
+    ignored: HashMap<PathBuf, (u64, Option<SystemTime>)>,
+}
+
+impl StableFiles {
+    pub fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            seen: HashMap::new(),
+            ignored: HashMap::new(),
+        }
+    }
+
+    /// Varre `dir` (sem subpastas) e devolve os vídeos que ficaram parados
+    /// por `settle`, em ordem de nome. Os devolvidos saem do acompanhamento.
+    pub fn scan(&mut self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let now = Instant::now();
+        let mut present = Vec::new();
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("Não foi possível ler a pasta {}", dir.display()))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else { continue };
+            if !meta.is_file() || !is_video(&path) {
+                continue;
+            }
+            present.push(path.clone());
+            let (size, mtime) = (meta.len(), meta.modified().ok());
+            match self.ignored.get(&path) {
+                Some(&done) if done == (size, mtime) => continue,
+                // Mudou: é outro arquivo com o mesmo nome.
+                Some(_) => {
+                    self.ignored.remove(&path);
+                }
+                None => {}
+            }
+            self.observe(path, size, mtime, now);
+        }
+        // Arquivo que sumiu (movido/apagado no meio da cópia) sai da lista.
+        self.seen.retain(|p, _| present.contains(p));
+        self.ignored.retain(|p, _| present.contains(p));
+
+        let mut stable: Vec<PathBuf> = self
+            .seen
+            .iter()
+            .filter(|(_, (_, _, since))| now.duration_since(*since) >= self.settle)
+            .map(|(p, _)| p.clone())
+            .collect();
+        stable.sort();
+        for p in &stable {
+            self.seen.remove(p);
+        }
+        Ok(stable)
+    }
+
+    /// Não devolve mais `path` enquanto ele continuar com o mesmo tamanho e
+    /// mtime. Pra vídeo já processado que não pôde sair da pasta.
+    pub fn ignore(&mut self, path: &Path) {
+        if let Ok(meta) = std::fs::metadata(path) {
+            self.ignored
+                .insert(path.to_path_buf(), (meta.len(), meta.modified().ok()));
+        }
+    }
+
+    /// Quantos arquivos ainda estão sendo acompanhados (crescendo ou esperando).
+    pub fn pending(&self) -> usize {
+        self.seen.len()
+    }
+
+    fn observe(&mut self, path: PathBuf, size: u64, mtime: Option<SystemTime>, now: Instant) {
+        match self.seen.get_mut(&path) {
+            Some(entry) if entry.0 == size && entry.1 == mtime => {}
+            Some(entry) => *entry = (size, mtime, now),
+            None => {
+                self.seen.insert(path, (size, mtime, now));
+            }
+        }
+    }
+}
+
+/// Move o vídeo pra `<pasta>/<sub>/`, sem sobrescrever: se já existir um
+/// arquivo com o mesmo nome, acrescenta ` (2)`, ` (3)`...
+pub fn move_to(video: &Path, sub: &str) -> Result<PathBuf> {
+    let dir = video.parent().unwrap_or(Path::new(".")).join(sub);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Não foi possível criar diretório: {}", dir.display()))?;
+    let name = video.file_name().context("arquivo sem nome")?;
+    let mut target = dir.join(name);
+    let stem = video.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let ext = video.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    let mut n = 2;
+    while target.exists() {
+        target = dir.join(format!("{stem} ({n}){ext}"));
+        n += 1;
+    }
+    std::fs::rename(video, &target)
+        .with_context(|| format!("Não foi possível mover {} para {}", video.display(), target.display()))?;
+    Ok(target)
+}
+
+/// Acrescenta uma linha ao `watch.log` da pasta:
+/// `data<TAB>OK|PULADO|ERRO<TAB>arquivo<TAB>detalhe`.
+pub fn append_log(dir: &Path, status: &str, file: &Path, detail: &str) -> Result<()> {
+    let path = dir.join(LOG_FILE);
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Não foi possível abrir {}", path.display()))?;
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let detail = detail.replace(['\n', '\t'], " ");
+    writeln!(
+        f,
+        "{}\t{status}\t{name}\t{detail}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+    )
+    .with_context(|| format!("Não foi possível escrever {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_files_and_move() {
+        let dir = std::env::temp_dir().join(format!("encoder_test_watch_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("FEV_17.mp4");
+        std::fs::write(&video, b"parcial").unwrap();
+        std::fs::write(dir.join("leia.txt"), b"x").unwrap();
+
+        let mut files = StableFiles::new(Duration::from_millis(200));
+        assert!(files.scan(&dir).unwrap().is_empty());
+        assert_eq!(files.pending(), 1);
+
+        // Ainda crescendo: o prazo recomeça.
+        std::thread::sleep(Duration::from_millis(150));
+        std::fs::write(&video, b"parcial + resto").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(files.scan(&dir).unwrap().is_empty());
+
+        std::thread::sleep(Duration::from_millis(250));
+        assert_eq!(files.scan(&dir).unwrap(), vec![video.clone()]);
+        assert_eq!(files.pending(), 0);
+
+        // Processado mas não movido: não volta pra fila até mudar.
+        files.ignore(&video);
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(files.scan(&dir).unwrap().is_empty());
+        assert_eq!(files.pending(), 0);
+
+        let moved = move_to(&video, DONE_DIR).unwrap();
+        assert_eq!(moved, dir.join("done/FEV_17.mp4"));
+        std::fs::write(&video, b"de novo").unwrap();
+        assert_eq!(move_to(&video, DONE_DIR).unwrap(), dir.join("done/FEV_17 (2).mp4"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}